@@ -0,0 +1,9 @@
+// Copyright (C) 2018, Hao Hou
+
+pub mod options;
+pub mod runner;
+pub mod watch;
+pub mod rules;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,446 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The command line options
+ */
+use std::time::Duration;
+use ::reader::exec::ExitPolicy;
+use ::table::filter::Condition;
+use ::table::window::{WindowSize, Aggregate};
+
+/**
+ * @brief The output format
+ **/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    /// The text table which fits into the terminal
+    Table,
+    /// The text table which is printed while we read the rows
+    Stream,
+    Csv,
+    Tsv,
+    Json,
+    Ndjson,
+    Markdown,
+    Html,
+    /// The squery format which can be read back by squery
+    Squery
+}
+
+impl Format {
+    /**
+     * @brief Parse the format name
+     * @param name The format name
+     * @return The format or None if the name is unknown
+     **/
+    pub fn from_name(name:&str) -> Option<Format>
+    {
+        match name
+        {
+            "table"    => Some(Format::Table),
+            "stream"   => Some(Format::Stream),
+            "csv"      => Some(Format::Csv),
+            "tsv"      => Some(Format::Tsv),
+            "json"     => Some(Format::Json),
+            "ndjson"   => Some(Format::Ndjson),
+            "markdown" => Some(Format::Markdown),
+            "html"     => Some(Format::Html),
+            "squery"   => Some(Format::Squery),
+            _          => None
+        }
+    }
+}
+
+/**
+ * @brief The subcommand that looks into the rules rather than running a command
+ **/
+#[derive(Clone, PartialEq, Debug)]
+pub enum RulesCommand {
+    /// List the programs with rules and where the rule files are
+    List,
+    /// Print the rule files of the program
+    Show(String),
+    /// Check all the rule files
+    Validate,
+    /// Tell which rule matches the command and the schema it gives
+    Explain
+}
+
+/**
+ * @brief The parsed command line
+ **/
+pub struct Options {
+    /// If we should print the usage and exit
+    pub help : bool,
+    /// The output format
+    pub format : Format,
+    /// The extra directories we search for the rule files, before the user's rule directory
+    /// and the built-in rules
+    pub rule_paths : Vec<String>,
+    /// What we should do if the command fails, None to use the rule or the default
+    pub exit_policy : Option<ExitPolicy>,
+    /// The shell command we want to run with sh -c, instead of the command
+    pub shell : Option<String>,
+    /// The schema of the output, which overrides the rule
+    pub schema : Option<String>,
+    /// If the command keeps running, so the rows are written as soon as they are read
+    pub follow : bool,
+    /// The selected columns, None for all the columns
+    pub columns : Option<Vec<String>>,
+    /// The conditions the rows should meet
+    pub conditions : Vec<Condition>,
+    /// The window of the aggregate, None if we don't aggregate the rows
+    pub window : Option<WindowSize>,
+    /// The aggregate function applied to the window
+    pub aggregate : Aggregate,
+    /// The interval we run the command again and redraw the table, None to run it once
+    pub watch : Option<Duration>,
+    /// If the rows added, removed or changed since the previous run are highlighted
+    pub diff : bool,
+    /// The key columns we use to match the rows of two runs, None for the sort keys
+    pub keys : Option<Vec<String>>,
+    /// The rules subcommand, None to run the command
+    pub rules : Option<RulesCommand>,
+    /// The command we want to run and its arguments, the pipeline stages are seperated by a
+    /// "|" argument
+    pub command : Vec<String>
+}
+
+/**
+ * @brief The usage text
+ **/
+pub const USAGE : &str = "Usage: squery [OPTIONS] [--] COMMAND [ARGS...] ['|' COMMAND [ARGS...]]...
+       squery [OPTIONS] -c SHELL_COMMAND
+       squery [OPTIONS] < INPUT
+       squery [OPTIONS] rules list | show PROGRAM | validate | explain [--] COMMAND...
+
+Run the command and print its output as a table. A quoted '|' connects the commands
+into a pipeline, and the rule of the last command is used. Without a command, the
+table is read from the standard input, e.g. the output of squery --format squery.
+
+The rules subcommands list the programs with rules and where the rule files are, print
+the rule files of a program, check all the rule files, or tell which rule matches the
+command and the schema it gives. Without a matching rule, explain runs the command and
+prints the schema guessed from its output as a rule.
+
+Options:
+    -f, --format FORMAT    The output format: table, stream, csv, tsv, json, ndjson,
+                           markdown, html or squery (default: table)
+    -r, --rules DIR        Search the rule files in DIR before ~/.config/squery/rules
+                           and the built-in rules
+    -s, --schema SCHEMA    The schema of the whitespace seperated output, e.g.
+                           \".pid:Int .name:String\", no rule is used then
+    -c, --shell COMMAND    Run the command with sh -c
+    -F, --follow           The command keeps running, e.g. vmstat 1 or tail -f, write
+                           each row as soon as it's read
+        --columns A,B,...  Only write the columns
+    -w, --where COND       Only write the rows meeting the condition, e.g. rss>1000,
+                           user=root or command~^/usr, which can be given many times
+        --window SIZE      Write the aggregate of the latest SIZE rows, or the rows in
+                           the latest SIZE period like 60s, 5m or 1h, after each row
+        --aggregate FUNC   The aggregate of the numeric columns in the window: avg,
+                           sum, min or max (default: avg)
+        --watch SECS       Run the command every SECS seconds and redraw the table
+        --diff             Highlight the rows added, changed or removed since the
+                           previous run in the watch mode
+        --key A,B,...      The columns that identify a row for --diff, the sort keys
+                           of the schema by default
+        --on-error POLICY  What to do if the command exits with a non-zero status:
+                           fail, warn or ignore (default: warn)
+    -h, --help             Print this message
+";
+
+impl Options {
+    /**
+     * @brief Parse the command line, the options end at the first argument which is not
+     *        an option, or at --
+     * @param args The arguments without the program name
+     * @return The options or the error message
+     **/
+    pub fn parse(args:&[String]) -> Result<Options, String>
+    {
+        let mut ret = Options {
+            help        : false,
+            format      : Format::Table,
+            rule_paths  : Vec::new(),
+            exit_policy : None,
+            shell       : None,
+            schema      : None,
+            follow      : false,
+            columns     : None,
+            conditions  : Vec::new(),
+            window      : None,
+            aggregate   : Aggregate::Avg,
+            watch       : None,
+            diff        : false,
+            keys        : None,
+            rules       : None,
+            command     : Vec::new()
+        };
+
+        let mut idx = 0;
+        let mut separated = false;
+
+        while idx < args.len()
+        {
+            let arg = &args[idx][..];
+            idx += 1;
+
+            let mut value = |name:&str| -> Result<String, String> {
+                idx += 1;
+                return args.get(idx - 1).cloned().ok_or(format!("missing value for {}", name));
+            };
+
+            match arg
+            {
+                "-h" | "--help"   => ret.help = true,
+                "-f" | "--format" => {
+                    let name = value(arg)?;
+                    ret.format = Format::from_name(&name).ok_or(format!("unknown format {}", name))?;
+                },
+                "-r" | "--rules"  => ret.rule_paths.push(value(arg)?),
+                "-s" | "--schema" => ret.schema = Some(value(arg)?),
+                "-c" | "--shell"  => ret.shell = Some(value(arg)?),
+                "-F" | "--follow" => ret.follow = true,
+                "--columns"       => ret.columns = Some(value(arg)?.split(',').map(|name| name.trim().to_string()).collect()),
+                "-w" | "--where"  => {
+                    let cond = value(arg)?;
+                    ret.conditions.push(Condition::parse(&cond).ok_or(format!("invalid condition {}", cond))?);
+                },
+                "--window"        => {
+                    let size = value(arg)?;
+                    ret.window = Some(WindowSize::parse(&size).ok_or(format!("invalid window size {}", size))?);
+                },
+                "--watch"         => {
+                    let secs = value(arg)?;
+                    let interval = secs.parse::<f64>().ok().filter(|secs| *secs > 0.0).ok_or(format!("invalid interval {}", secs))?;
+                    ret.watch = Some(Duration::from_millis((interval * 1000.0) as u64));
+                },
+                "--diff"          => ret.diff = true,
+                "--key"           => ret.keys = Some(value(arg)?.split(',').map(|name| name.trim().to_string()).collect()),
+                "--aggregate"     => {
+                    let name = value(arg)?;
+                    ret.aggregate = Aggregate::from_name(&name).ok_or(format!("unknown aggregate {}", name))?;
+                },
+                "--on-error"      => {
+                    let name = value(arg)?;
+                    ret.exit_policy = Some(ExitPolicy::from_name(&name).ok_or(format!("unknown policy {}", name))?);
+                },
+                "--"              => {
+                    separated = true;
+                    break;
+                },
+                _ if arg.starts_with('-') && arg.len() > 1 => return Err(format!("unknown option {}", arg)),
+                _                 => {
+                    idx -= 1;
+                    break;
+                }
+            }
+        }
+
+        ret.command = args[idx..].to_vec();
+
+        if ret.help
+        {
+            return Ok(ret);
+        }
+
+        /* squery -- rules runs the program named rules */
+        if !separated && ret.command.first().is_some_and(|arg| arg == "rules")
+        {
+            return Options::parse_rules(ret);
+        }
+
+        if ret.watch.is_some() && (ret.follow || ret.format != Format::Table)
+        {
+            return Err("--watch only works with the text table and without --follow".to_string());
+        }
+
+        match (&ret.shell, ret.command.len())
+        {
+            (&None, 0)    => {
+                if ret.watch.is_some()
+                {
+                    return Err("--watch needs a command to run again".to_string());
+                }
+            },
+            (&Some(_), 0) => {},
+            (&Some(_), _) => return Err("unexpected command after the shell command".to_string()),
+            (&None, _)    => {
+                if ret.stages().iter().any(|stage| stage.len() == 0)
+                {
+                    return Err("empty command in the pipeline".to_string());
+                }
+            }
+        }
+
+        return Ok(ret);
+    }
+
+    /**
+     * @brief Parse the rules subcommand, the command to explain is kept in the command field
+     * @param options The options parsed so far, the command starts with "rules"
+     * @return The options or the error message
+     **/
+    fn parse_rules(mut options:Options) -> Result<Options, String>
+    {
+        let args = options.command.split_off(1);
+        let name = args.first().map_or("", |name| &name[..]);
+
+        options.rules = Some(match (name, args.len())
+        {
+            ("list", 1)     => RulesCommand::List,
+            ("show", 2)     => RulesCommand::Show(args[1].clone()),
+            ("validate", 1) => RulesCommand::Validate,
+            ("explain", _)  => RulesCommand::Explain,
+            ("list", _) | ("show", _) | ("validate", _) => return Err(format!("wrong number of arguments for rules {}", name)),
+            ("", _)         => return Err("missing rules subcommand".to_string()),
+            _               => return Err(format!("unknown rules subcommand {}", name))
+        });
+
+        options.command = Vec::new();
+
+        if options.rules == Some(RulesCommand::Explain)
+        {
+            let command = &args[1..];
+            options.command = match command.first()
+            {
+                Some(arg) if arg == "--" => command[1..].to_vec(),
+                _                        => command.to_vec()
+            };
+
+            match (&options.shell, options.command.len())
+            {
+                (&None, 0)    => return Err("no command is given to explain".to_string()),
+                (&Some(_), 0) => {},
+                (&Some(_), _) => return Err("unexpected command after the shell command".to_string()),
+                (&None, _)    => {}
+            }
+        }
+
+        return Ok(options);
+    }
+
+    /**
+     * @brief Check if the table is read from the standard input, which is the case when
+     *        there's no command to run
+     * @return The check result
+     **/
+    pub fn reads_stdin(&self) -> bool
+    {
+        return self.shell.is_none() && self.command.is_empty();
+    }
+
+    /**
+     * @brief Split the command into the pipeline stages
+     * @return The program and the arguments of each stage
+     **/
+    pub fn stages(&self) -> Vec<Vec<String>>
+    {
+        if let Some(ref shell) = self.shell
+        {
+            return vec![vec!["sh".to_string(), "-c".to_string(), shell.clone()]];
+        }
+
+        return self.command.split(|arg| arg == "|").map(|stage| stage.to_vec()).collect();
+    }
+
+    /**
+     * @brief Get the last command of the pipeline, which decides the rule we use. For the
+     *        shell command, it's the words after the last "|" outside the quotes, without the
+     *        redirections
+     * @return The program and the arguments
+     **/
+    pub fn last_command(&self) -> Vec<String>
+    {
+        if let Some(ref shell) = self.shell
+        {
+            let words = shell_words(shell);
+            let last = words.rsplit(|word| word.is_none()).next().unwrap_or(&[]);
+
+            let mut ret = Vec::new();
+            let mut words = last.iter().flatten();
+            while let Some(word) = words.next()
+            {
+                let op = word.trim_start_matches(|c:char| c.is_ascii_digit());
+                if !op.starts_with(&['<', '>'][..])
+                {
+                    ret.push(word.clone());
+                    continue;
+                }
+
+                /* The target of the redirection is the next word unless it's in this word,
+                 * e.g. >out or 2>&1 */
+                if op.trim_start_matches(&['<', '>', '&'][..]).is_empty()
+                {
+                    words.next();
+                }
+            }
+            return ret;
+        }
+
+        return self.stages().pop().unwrap_or_default();
+    }
+}
+
+/**
+ * @brief Split the shell command into the words like sh does, the quotes and the backslashes
+ *        are removed. It's only used to find the program and the options of the last command,
+ *        so the expansions are kept as they are
+ * @param command The shell command
+ * @return The words, and None for each operator that ends a command, e.g. "|" or ";"
+ **/
+pub fn shell_words(command:&str) -> Vec<Option<String>>
+{
+    let mut ret = Vec::new();
+    let mut word:Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(ch) = chars.next()
+    {
+        /* The & in a redirection like 2>&1 doesn't end the command */
+        let redirect = word.as_ref().is_some_and(|word| word.ends_with(&['<', '>'][..]));
+
+        match ch
+        {
+            '\\'                              => {
+                if let Some(next) = chars.next()
+                {
+                    word.get_or_insert_with(String::new).push(next);
+                }
+            },
+            '\''                              => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.by_ref().take_while(|&c| c != '\''));
+            },
+            '"'                               => {
+                let word = word.get_or_insert_with(String::new);
+                while let Some(c) = chars.next()
+                {
+                    match (c, c == '\\')
+                    {
+                        ('"', _)  => break,
+                        (_, true) => match chars.next()
+                        {
+                            Some(next) if "$`\"\\".contains(next) => word.push(next),
+                            Some(next)                             => { word.push('\\'); word.push(next); },
+                            None                                   => word.push('\\')
+                        },
+                        (c, _)    => word.push(c)
+                    }
+                }
+            },
+            '&' if redirect                   => word.get_or_insert_with(String::new).push(ch),
+            '|' | ';' | '&' | '\n'             => {
+                ret.extend(word.take().map(Some));
+                ret.push(None);
+            },
+            c if c.is_whitespace()            => ret.extend(word.take().map(Some)),
+            c                                 => word.get_or_insert_with(String::new).push(c)
+        }
+    }
+
+    ret.extend(word.take().map(Some));
+
+    return ret;
+}
@@ -0,0 +1,313 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The rules subcommands, which look into the rules rather than running a command
+ */
+extern crate yaml_rust;
+
+use std::collections::BTreeMap;
+use ::cli::options::{Options, RulesCommand};
+use ::cli::runner::{schema_manager, infer_command_schema, user_rule_path};
+use ::schema::loader::{Parser, Records, RuleFile, RuleSource, SchemaQueryResult};
+use ::schema::validate::validate_rule_file;
+use ::table::schema::TableSchema;
+
+use self::yaml_rust::{YamlEmitter, YamlLoader};
+
+/**
+ * @brief Get the name of the rule file we show to the user
+ * @param file The rule file
+ * @return The path, or "built-in <program>.yml" for the built-in rule file
+ **/
+fn file_label(file:&RuleFile) -> String
+{
+    return match file.source
+    {
+        RuleSource::File(ref path) => path.clone(),
+        RuleSource::Builtin        => format!("built-in {}.yml", file.program)
+    };
+}
+
+/**
+ * @brief List the programs with rules, the rule files of each program are listed in the
+ *        order they are tried
+ * @param options The command line options
+ * @return The exit code
+ **/
+fn list(options:&Options) -> i32
+{
+    let mut programs:BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for file in schema_manager(options).rule_files()
+    {
+        programs.entry(file.program.clone()).or_default().push(file.source.to_string());
+    }
+
+    let width = programs.keys().map(|program| program.len()).max().unwrap_or(0);
+
+    for (program, sources) in &programs
+    {
+        println!("{:width$}  {}", program, sources.join(", "), width = width);
+    }
+
+    return 0;
+}
+
+/**
+ * @brief Print the rule files of the program in the order they are tried
+ * @param options The command line options
+ * @param program The program name
+ * @return The exit code
+ **/
+fn show(options:&Options, program:&str) -> i32
+{
+    let files = schema_manager(options).program_rule_files(program);
+
+    if files.is_empty()
+    {
+        eprintln!("squery: there's no rule for {}", program);
+        return 1;
+    }
+
+    for (idx, file) in files.iter().enumerate()
+    {
+        if idx > 0
+        {
+            println!();
+        }
+        println!("# {}", file_label(file));
+        print!("{}", file.content);
+
+        if !file.content.ends_with('\n')
+        {
+            println!();
+        }
+    }
+
+    return 0;
+}
+
+/**
+ * @brief Check all the rule files and print the errors
+ * @param options The command line options
+ * @return The exit code, which is 1 if any rule file is invalid
+ **/
+fn validate(options:&Options) -> i32
+{
+    let files = schema_manager(options).rule_files();
+    let mut invalid = 0;
+
+    for file in &files
+    {
+        let errors = validate_rule_file(&file.content);
+
+        for error in &errors
+        {
+            println!("{}: {}", file_label(file), error);
+        }
+
+        if errors.len() > 0
+        {
+            invalid += 1;
+        }
+    }
+
+    println!("{} rule files checked, {} invalid", files.len(), invalid);
+
+    return if invalid > 0 { 1 } else { 0 };
+}
+
+/**
+ * @brief Describe how the output is parsed
+ * @param result The query result
+ * @return The description
+ **/
+fn describe_parser(result:&SchemaQueryResult) -> &'static str
+{
+    if result.json.is_some()
+    {
+        return "JSON";
+    }
+
+    return match (&result.record_parser, &result.line_parser)
+    {
+        (&Some(Records::Block(_)), _)   => "multi-line records",
+        (&Some(Records::Pivot(_)), _)   => "key-value lines pivoted into one row per record",
+        (&None, &Parser::SepVal(_))     => "seperated values",
+        (&None, &Parser::FixedWidth(_)) => "fixed width columns",
+        (&None, &Parser::KeyValue(_))   => "key-value pairs",
+        (&None, &Parser::Regex(_))      => "regex with named groups"
+    };
+}
+
+/**
+ * @brief Print the columns of the schema
+ * @param spec The schema specification
+ **/
+fn print_schema(spec:&str)
+{
+    let schema = match TableSchema::from_spec(spec)
+    {
+        Some(schema) => schema,
+        None         => {
+            println!("    invalid schema \"{}\"", spec);
+            return;
+        }
+    };
+
+    let width = schema.types.iter().map(|&(ref name, _)| name.len()).max().unwrap_or(0);
+
+    for &(ref name, ref ptype) in &schema.types
+    {
+        println!("    {:width$}  {}", name, ptype.type_name(), width = width);
+    }
+
+    if schema.sort_keys.len() > 0
+    {
+        let keys:Vec<&str> = schema.sort_keys.iter().map(|&key| &schema.types[key].0[..]).collect();
+        println!("    {} by {}", if schema.sorted { "sorted" } else { "sort" }, keys.join(", "));
+    }
+}
+
+/**
+ * @brief Print the rule entry as YAML
+ * @param file The rule file
+ * @param idx The index of the rule in the file
+ **/
+fn print_rule(file:&RuleFile, idx:usize)
+{
+    let docs = YamlLoader::load_from_str(&file.content).unwrap_or_default();
+    let rule = match docs.iter().filter_map(|doc| doc.as_vec()).flat_map(|rules| rules.iter()).nth(idx)
+    {
+        Some(rule) => rule,
+        None       => return
+    };
+
+    let mut text = String::new();
+    if YamlEmitter::new(&mut text).dump(rule).is_ok()
+    {
+        for line in text.lines().filter(|line| *line != "---")
+        {
+            println!("    {}", line);
+        }
+    }
+}
+
+/**
+ * @brief Run the command and print the schema we guess from its output, and the rule entry
+ *        the user can save to keep it
+ * @param options The command line options
+ * @param program The program name
+ **/
+fn explain_inferred(options:&Options, program:&str)
+{
+    let inferred = match infer_command_schema(options)
+    {
+        Some(inferred) => inferred,
+        None           => {
+            println!("Schema:  can not be inferred");
+            return;
+        }
+    };
+
+    println!("Parser:  seperated values");
+    if inferred.has_header
+    {
+        println!("Schema:  the first line is the header");
+    }
+    else
+    {
+        println!("Schema:");
+    }
+    print_schema(&inferred.schema.to_spec());
+
+    match user_rule_path()
+    {
+        Some(dir) => println!("Save the rule to {}/{}.yml to use it:", dir, program),
+        None      => println!("Save the rule to {}.yml in a rule directory to use it:", program)
+    }
+    print!("{}", inferred.to_rule(" \t\r\n"));
+}
+
+/**
+ * @brief Tell which rule matches the command and the schema it gives. If there's no rule,
+ *        the command is run to guess the schema, which is printed as a rule entry
+ * @param options The command line options, the command to explain is in the command field
+ * @return The exit code, which is 1 if there's no matching rule
+ **/
+fn explain(options:&Options) -> i32
+{
+    let last = options.last_command();
+    let program = last.first().map_or("sh", |program| &program[..]);
+    let args:Vec<&str> = last.iter().skip(1).map(|arg| &arg[..]).collect();
+
+    match options.shell
+    {
+        Some(ref shell) => println!("Command: sh -c {}", shell),
+        None            => println!("Command: {}", options.command.join(" "))
+    }
+
+    if let Some(ref spec) = options.schema
+    {
+        println!("Rule:    none, the schema is given by --schema");
+        println!("Parser:  seperated values");
+        println!("Schema:");
+        print_schema(spec);
+        return 0;
+    }
+
+    let (file, idx, result) = match schema_manager(options).find(program, &args)
+    {
+        Some(found) => found,
+        None        => {
+            println!("Rule:    none, the schema is inferred from the output of {}", program);
+            explain_inferred(options, program);
+            return 1;
+        }
+    };
+
+    println!("Rule:    rule {} of {}", idx + 1, file_label(&file));
+    print_rule(&file, idx);
+
+    match options.shell
+    {
+        Some(_) => println!("Runs:    the arguments are not rewritten in the shell command"),
+        None    => println!("Runs:    {} {}", program, result.args.join(" "))
+    }
+
+    if result.skip > 0
+    {
+        println!("Skip:    {} line{}", result.skip, if result.skip > 1 { "s" } else { "" });
+    }
+
+    println!("Parser:  {}", describe_parser(&result));
+
+    if result.header
+    {
+        println!("Schema:  the columns come from the header line, the known columns are");
+    }
+    else
+    {
+        println!("Schema:");
+    }
+    print_schema(&result.schema);
+
+    return 0;
+}
+
+/**
+ * @brief Run the rules subcommand
+ * @param options The command line options
+ * @param command The subcommand
+ * @return The exit code
+ **/
+pub fn rules(options:&Options, command:&RulesCommand) -> i32
+{
+    return match command
+    {
+        &RulesCommand::List              => list(options),
+        &RulesCommand::Show(ref program) => show(options, program),
+        &RulesCommand::Validate          => validate(options),
+        &RulesCommand::Explain           => explain(options)
+    };
+}
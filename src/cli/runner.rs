@@ -0,0 +1,456 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * Run the command and write its output as a table
+ */
+use std::env;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use ::cli::options::{Options, Format};
+use ::reader::exec::{ExecReader, ExecOptions, ExecStatus, ExitPolicy};
+use ::reader::linetext::LineTextReader;
+use ::reader::svparser::SepValParser;
+use ::reader::squeryparser::SqueryParser;
+use ::schema::loader::SchemaManager;
+use ::schema::infer::InferredSchema;
+use ::table::input::Input;
+use ::table::filter::FilterInput;
+use ::table::window::WindowInput;
+use ::table::schema::TableSchema;
+use ::table::table::{Table, TableDataSource};
+use ::writer::tablewriter::{TableOutputer, TableResult, StreamTableOutputer};
+use ::writer::svwriter::{CsvOutputer, TsvOutputer};
+use ::writer::jsonwriter::{JsonOutputer, NdjsonOutputer};
+use ::writer::markdownwriter::MarkdownOutputer;
+use ::writer::htmlwriter::HtmlOutputer;
+use ::writer::squerywriter::SqueryOutputer;
+use ::writer::terminal::stdin_is_terminal;
+use ::writer::textstyle::{ColorHint, ColorMode};
+
+/// The number of lines we look at to guess the schema when there's no rule for the command
+const INFER_SAMPLE_LINES : usize = 100;
+
+/// The number of lines we look at to guess the schema in the follow mode, since the command
+/// may print a line every few seconds
+const FOLLOW_INFER_SAMPLE_LINES : usize = 3;
+
+/// The number of rows the streaming table looks at to decide the column widths
+const STREAM_SAMPLE_ROWS : usize = 20;
+
+/**
+ * @brief Write the table in the format
+ * @param format The output format
+ * @param schema The table schema
+ * @param input The input of the table
+ * @param follow If the command keeps running, in this case the rows are flushed as soon as
+ *        they are written
+ * @param hints The colour hints of the rule, which are only used by the text tables
+ * @return The text table if the format is buffered and we haven't printed it yet, or None
+ *         if we fail to write the table
+ **/
+fn write_table(format:Format, schema:&TableSchema, input:&mut dyn Input, follow:bool, hints:&[ColorHint]) -> Option<Option<TableResult>>
+{
+    /* We don't keep the rows, so the memory doesn't grow with a command that never stops */
+    let mut table = Table::empty(schema, TableDataSource::Parser(input, false));
+
+    let stream = |sample:usize| {
+        let mut output = StreamTableOutputer::create(io::stdout(), sample);
+        output.color_mode(ColorMode::Auto);
+        for hint in hints
+        {
+            output.add_color_hint(hint.clone());
+        }
+        return output;
+    };
+
+    let written = match (format, follow)
+    {
+        (Format::Table, false) => {
+            let mut output = TableOutputer::create();
+            output.color_mode(ColorMode::Auto);
+            for hint in hints
+            {
+                output.add_color_hint(hint.clone());
+            }
+            return table.dump(&mut output).map(Some);
+        },
+        (Format::Table, true)  => table.dump(&mut stream(1)).is_some(),
+        (Format::Stream, _)    => table.dump(&mut stream(if follow { 1 } else { STREAM_SAMPLE_ROWS })).is_some(),
+        (Format::Csv, _)      => table.dump(CsvOutputer::create(io::stdout(), true).follow(follow)).is_some(),
+        (Format::Tsv, _)      => table.dump(TsvOutputer::create(io::stdout(), true).follow(follow)).is_some(),
+        (Format::Json, _)     => table.dump(JsonOutputer::create(io::stdout()).follow(follow)).is_some(),
+        (Format::Ndjson, _)   => table.dump(NdjsonOutputer::create(io::stdout()).follow(follow)).is_some(),
+        (Format::Markdown, _) => table.dump(MarkdownOutputer::create(io::stdout()).follow(follow)).is_some(),
+        (Format::Html, _)     => table.dump(HtmlOutputer::create(io::stdout()).follow(follow)).is_some(),
+        (Format::Squery, _)   => table.dump(SqueryOutputer::create(io::stdout()).follow(follow)).is_some()
+    };
+
+    return if written { Some(None) } else { None };
+}
+
+/**
+ * @brief Aggregate the rows in the window if it's required, then pass the result to the
+ *        continuation
+ * @param options The command line options
+ * @param input The input of the table
+ * @param then The continuation, which reads the aggregated input
+ * @return The result of the continuation
+ **/
+fn aggregate<R, F>(options:&Options, input:&mut dyn Input, then:F) -> R
+    where F: FnOnce(&mut dyn Input) -> R
+{
+    if let Some(size) = options.window
+    {
+        return then(&mut WindowInput::create(input, size, options.aggregate));
+    }
+
+    return then(input);
+}
+
+/**
+ * @brief Select the rows and the columns, and aggregate the rows in the window if it's
+ *        required, then pass the result to the continuation
+ * @param options The command line options
+ * @param input The input of the table
+ * @param then The continuation, which reads the transformed input
+ * @return The result of the continuation, or the name of the selected or checked column
+ *         that isn't in the input
+ **/
+pub fn transform<R, F>(options:&Options, input:&mut dyn Input, then:F) -> Result<R, String>
+    where F: FnOnce(&mut dyn Input) -> R
+{
+    if options.columns.is_some() || options.conditions.len() > 0
+    {
+        let mut filter = FilterInput::create(input, options.columns.clone(), options.conditions.clone());
+        let ret = aggregate(options, &mut filter, then);
+
+        return match filter.unknown_column()
+        {
+            Some(name) => Err(name.to_string()),
+            None       => Ok(ret)
+        };
+    }
+
+    return Ok(aggregate(options, input, then));
+}
+
+/**
+ * @brief Transform the input and write the table
+ * @param options The command line options
+ * @param input The input of the table
+ * @param hints The colour hints of the rule
+ * @return None if the schema can not be determined, otherwise the result of write_table, or
+ *         the name of the unknown column
+ **/
+fn write_rows(options:&Options, input:&mut dyn Input, hints:&[ColorHint]) -> Result<Option<Option<Option<TableResult>>>, String>
+{
+    return transform(options, input, |input| {
+        let schema = input.determine_table_schema()?;
+        Some(write_table(options.format, &schema, input, options.follow, hints))
+    });
+}
+
+/**
+ * @brief Report how the command has finished and decide the exit code of squery
+ * @param program The program name
+ * @param status How the command has finished
+ * @param policy What we should do if the command fails
+ * @param pending The buffered text table which is not printed yet
+ * @return The exit code
+ **/
+fn finish(program:&str, status:ExecStatus, policy:ExitPolicy, pending:Option<TableResult>) -> i32
+{
+    let failed = !status.success() && policy != ExitPolicy::Ignore;
+
+    if !(failed && policy == ExitPolicy::Fail)
+    {
+        if let Some(table) = pending
+        {
+            table.print_terminal_table(0);
+        }
+    }
+
+    if policy != ExitPolicy::Ignore
+    {
+        let _ = io::stderr().write_all(status.stderr.as_bytes());
+    }
+
+    if !failed
+    {
+        return 0;
+    }
+
+    let code = status.exit_code();
+    if status.timed_out
+    {
+        eprintln!("squery: {} timed out", program);
+    }
+    else
+    {
+        eprintln!("squery: {} exited with status {}", program, code);
+    }
+    return code;
+}
+
+/**
+ * @brief Find the directory of the user's rule files, which is $XDG_CONFIG_HOME/squery/rules
+ *        or ~/.config/squery/rules
+ * @return The directory or None if neither variable is set
+ **/
+pub fn user_rule_path() -> Option<String>
+{
+    let config = match env::var("XDG_CONFIG_HOME")
+    {
+        Ok(ref dir) if !dir.is_empty() => dir.clone(),
+        _                              => env::var("HOME").ok()? + "/.config"
+    };
+
+    return Some(config + "/squery/rules");
+}
+
+/**
+ * @brief Create the schema manager which searches the rule directories given by the options,
+ *        then the user's rule directory and the built-in rules
+ * @param options The command line options
+ * @return The schema manager
+ **/
+pub fn schema_manager(options:&Options) -> SchemaManager
+{
+    let mut schema_man = SchemaManager::new();
+
+    for path in &options.rule_paths
+    {
+        schema_man.push_schema_path(path.clone());
+    }
+
+    /* The built-in rules are used when no rule file in these paths matches */
+    if let Some(path) = user_rule_path()
+    {
+        schema_man.push_schema_path(path);
+    }
+
+    return schema_man;
+}
+
+/**
+ * @brief Find the rule of the command and start it
+ * @param options The command line options
+ * @return The reader, the program name, what we should do if the command fails and the colour
+ *         hints of the rule, or the exit code if the command can not be started
+ **/
+pub fn start(options:&Options) -> Result<(ExecReader, String, ExitPolicy, Vec<ColorHint>), i32>
+{
+    let schema_man = schema_manager(options);
+
+    /* The rule is decided by the last command of the pipeline */
+    let last = options.last_command();
+    let program = last.first().map_or("sh", |program| &program[..]);
+    let args:Vec<&str> = last.iter().skip(1).map(|arg| &arg[..]).collect();
+
+    /* The explicit schema replaces the rule */
+    let query = match options.schema
+    {
+        Some(_) => None,
+        None    => schema_man.query(program, &args)
+    };
+
+    let policy = options.exit_policy
+        .or_else(|| query.as_ref().and_then(|query| query.exit_policy))
+        .unwrap_or(ExitPolicy::Warn);
+    let skip = query.as_ref().map_or(0, |query| query.skip);
+    let exec_options = query.as_ref().map_or_else(ExecOptions::default, |query| query.exec.clone());
+    let hints = query.as_ref().map_or_else(Vec::new, |query| query.highlight.clone());
+
+    let mut stages = options.stages();
+
+    /* The rule may rewrite the arguments to get the output it can parse, which is only
+     * possible when we run the last command ourselves */
+    if let (&None, Some(query)) = (&options.shell, query.as_ref())
+    {
+        if let Some(stage) = stages.last_mut()
+        {
+            stage.truncate(1);
+            stage.extend(query.args.iter().cloned());
+        }
+    }
+
+    let stages:Vec<Vec<&str>> = stages.iter().map(|stage| stage.iter().map(|arg| &arg[..]).collect()).collect();
+
+    let reader = ExecReader::spawn_pipeline_with(&stages, skip, &exec_options, |br| {
+        match (query, &options.schema)
+        {
+            (Some(query), _)        => query.create_reader(br),
+            (None, &Some(ref spec)) => {
+                let parser = SepValParser::create(" \t\r\n");
+                LineTextReader::create_parser(spec, br, parser).map(|lp| Box::new(lp) as Box<dyn Input>)
+            },
+            (None, &None)           => {
+                let parser = SepValParser::create(" \t\r\n");
+                let sample = if options.follow { FOLLOW_INFER_SAMPLE_LINES } else { INFER_SAMPLE_LINES };
+                LineTextReader::create_inferring_parser(br, parser, sample).map(|lp| Box::new(lp) as Box<dyn Input>)
+            }
+        }
+    });
+
+    return match reader
+    {
+        Some(reader) => Ok((reader, program.to_string(), policy, hints)),
+        None         => {
+            eprintln!("squery: can not run {}", program);
+            Err(127)
+        }
+    };
+}
+
+/**
+ * @brief Run the command and guess the schema from its output, as we do when there's no rule
+ *        for the command
+ * @param options The command line options
+ * @return The inferred schema or None if the command can not be started or the schema can not
+ *         be guessed
+ **/
+pub fn infer_command_schema(options:&Options) -> Option<InferredSchema>
+{
+    let stages = options.stages();
+    let stages:Vec<Vec<&str>> = stages.iter().map(|stage| stage.iter().map(|arg| &arg[..]).collect()).collect();
+
+    let mut inferred = None;
+
+    let mut reader = ExecReader::spawn_pipeline_with(&stages, 0, &ExecOptions::default(), |br| {
+        let parser = SepValParser::create(" \t\r\n");
+        let sample = if options.follow { FOLLOW_INFER_SAMPLE_LINES } else { INFER_SAMPLE_LINES };
+        let lp = LineTextReader::create_inferring_parser(br, parser, sample)?;
+        inferred = lp.inferred_schema().cloned();
+        Some(Box::new(lp) as Box<dyn Input>)
+    })?;
+
+    /* We don't read the rest of the output */
+    reader.finish();
+
+    return inferred;
+}
+
+/**
+ * @brief Create the reader of a table that is not the output of a command we run, e.g. the
+ *        standard input. If the first line is a schema specification, the input is the
+ *        output of squery --format squery and we read it back with the types. Otherwise the
+ *        schema is given by --schema or guessed from the first lines
+ * @param options The command line options
+ * @param fp The input
+ * @return The reader or None if the schema can not be determined
+ **/
+pub fn open_input<R:Read + 'static>(options:&Options, mut fp:BufReader<R>) -> Option<Box<dyn Input>>
+{
+    let mut first = String::new();
+    fp.read_line(&mut first).ok()?;
+
+    /* The first line is read again by the reader */
+    let self_explain = options.schema.is_none() && TableSchema::from_spec(&first).is_some();
+    let br = BufReader::new(Cursor::new(first.into_bytes()).chain(fp));
+
+    if self_explain
+    {
+        return Some(Box::new(LineTextReader::create_self_explain_parser(br, SqueryParser::create())));
+    }
+
+    let parser = SepValParser::create(" \t\r\n");
+
+    return match options.schema
+    {
+        Some(ref spec) => LineTextReader::create_parser(spec, br, parser).map(|lp| Box::new(lp) as Box<dyn Input>),
+        None           => {
+            let sample = if options.follow { FOLLOW_INFER_SAMPLE_LINES } else { INFER_SAMPLE_LINES };
+            LineTextReader::create_inferring_parser(br, parser, sample).map(|lp| Box::new(lp) as Box<dyn Input>)
+        }
+    };
+}
+
+/**
+ * @brief Read the table from the standard input and write it
+ * @param options The command line options
+ * @return The exit code of squery
+ **/
+fn run_stdin(options:&Options) -> i32
+{
+    if stdin_is_terminal()
+    {
+        eprintln!("squery: no command is given and the standard input is a terminal");
+        return 2;
+    }
+
+    let mut input = match open_input(options, BufReader::new(io::stdin()))
+    {
+        Some(input) => input,
+        None        => {
+            eprintln!("squery: can not determine the schema of the standard input");
+            return 1;
+        }
+    };
+
+    return match write_rows(options, &mut *input, &[])
+    {
+        Err(name)               => {
+            eprintln!("squery: unknown column {}", name);
+            2
+        },
+        Ok(Some(Some(pending))) => {
+            if let Some(table) = pending
+            {
+                table.print_terminal_table(0);
+            }
+            0
+        },
+        Ok(Some(None))          => 1,
+        Ok(None)                => {
+            eprintln!("squery: can not determine the schema of the standard input");
+            1
+        }
+    };
+}
+
+/**
+ * @brief Run the command described by the options
+ * @param options The command line options
+ * @return The exit code of squery
+ **/
+pub fn run(options:&Options) -> i32
+{
+    if options.reads_stdin()
+    {
+        return run_stdin(options);
+    }
+
+    let (mut reader, program, policy, hints) = match start(options)
+    {
+        Ok(started) => started,
+        Err(code)   => return code
+    };
+    let program = &program[..];
+
+    let pending = write_rows(options, &mut reader, &hints);
+
+    let status = reader.finish();
+
+    return match pending
+    {
+        Err(name)               => {
+            eprintln!("squery: unknown column {}", name);
+            2
+        },
+        Ok(Some(Some(pending))) => finish(program, status, policy, pending),
+        Ok(Some(None))          => {
+            let code = finish(program, status, policy, None);
+            if code == 0 { 1 } else { code }
+        },
+        Ok(None)                => {
+            /* If the command fails, its standard error tells why there's no output */
+            let succeeded = status.success();
+            let code = finish(program, status, policy, None);
+            if succeeded
+            {
+                eprintln!("squery: can not determine the schema of the output of {}", program);
+                return 1;
+            }
+            code
+        }
+    };
+}
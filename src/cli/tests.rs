@@ -0,0 +1,107 @@
+// Copyright (C) 2018, Hao Hou
+//
+// The command line tests, which parse the options without running any command
+use ::cli::options::{Options, shell_words};
+use ::cli::runner::run;
+
+/**
+ * @brief Parse the command line
+ * @param args The arguments without the program name
+ * @return The options or the error message
+ **/
+fn parse(args:&[&str]) -> Result<Options, String>
+{
+    return Options::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+}
+
+#[test]
+fn invalid_conditions_are_rejected()
+{
+    for cond in &["rss", "=1", "a~(", "a!1"]
+    {
+        match parse(&["-w", cond, "ps"])
+        {
+            Err(message) => assert_eq!(message, format!("invalid condition {}", cond)),
+            Ok(_)        => panic!("{} is accepted", cond)
+        }
+    }
+
+    let options = parse(&["-w", "rss>1000", "--where", "user == root", "ps", "aux"]).unwrap();
+    assert_eq!(options.conditions.len(), 2);
+    assert_eq!(options.conditions[1].column(), "user");
+    assert_eq!(options.command, vec!["ps", "aux"]);
+}
+
+#[test]
+fn exit_policy_decides_the_exit_code()
+{
+    let code = |policy:&str, command:&str| {
+        let options = parse(&["--on-error", policy, "-f", "csv", "-s", ".a:String", "-c", command]).unwrap();
+        run(&options)
+    };
+
+    assert_eq!(code("fail", "echo a; exit 3"), 3);
+    assert_eq!(code("warn", "echo a; exit 3"), 3);
+    assert_eq!(code("ignore", "echo a; exit 3"), 0);
+    assert_eq!(code("fail", "kill -TERM $$"), 128 + 15);
+    assert_eq!(code("ignore", "kill -TERM $$"), 0);
+
+    for policy in &["fail", "warn", "ignore"]
+    {
+        assert_eq!(code(policy, "echo a"), 0);
+        assert_eq!(code(policy, "yes | head -n 3"), 0);
+    }
+}
+
+#[test]
+fn unknown_columns_are_usage_errors()
+{
+    let code = |args:&[&str]| {
+        let mut args = args.to_vec();
+        args.extend_from_slice(&["-f", "csv", "-s", ".a:String", "-c", "echo x"]);
+        run(&parse(&args).unwrap())
+    };
+
+    assert_eq!(code(&["-w", "b=x"]), 2);
+    assert_eq!(code(&["--columns", "a,b"]), 2);
+    assert_eq!(code(&["-w", "a=x", "--columns", "a"]), 0);
+}
+
+/**
+ * @brief Get the last command of the shell command
+ * @param shell The shell command
+ * @return The program and the arguments
+ **/
+fn last_shell_command(shell:&str) -> Vec<String>
+{
+    return parse(&["-c", shell]).unwrap().last_command();
+}
+
+#[test]
+fn shell_words_follow_the_quotes()
+{
+    let words = |command:&str| shell_words(command).into_iter().map(|word| word.unwrap_or_else(|| "|".to_string())).collect::<Vec<_>>();
+
+    assert_eq!(words("ps  aux"), vec!["ps", "aux"]);
+    assert_eq!(words("grep 'a|b' \"c d\" e\\ f"), vec!["grep", "a|b", "c d", "e f"]);
+    assert_eq!(words("echo \"a\\\"b\\\\c\\d\" 'x\\y' a\"b\"'c'"), vec!["echo", "a\"b\\c\\d", "x\\y", "abc"]);
+    assert_eq!(words("a|b;c&&d"), vec!["a", "|", "b", "|", "c", "|", "|", "d"]);
+    assert_eq!(words("a 2>&1 | b"), vec!["a", "2>&1", "|", "b"]);
+    assert_eq!(words("echo '' \"\""), vec!["echo", "", ""]);
+}
+
+#[test]
+fn last_command_of_the_shell_command_ignores_the_quoted_pipes()
+{
+    assert_eq!(last_shell_command("ps aux | grep -E 'a|b'"), vec!["grep", "-E", "a|b"]);
+    assert_eq!(last_shell_command("cat f | awk '{print $1 \"|\" $2}' | sort -n"), vec!["sort", "-n"]);
+    assert_eq!(last_shell_command("echo \"a | b\""), vec!["echo", "a | b"]);
+    assert_eq!(last_shell_command("cd /tmp && ls -l"), vec!["ls", "-l"]);
+    assert_eq!(last_shell_command("df -h 2>&1"), vec!["df", "-h"]);
+    assert_eq!(last_shell_command("df -h 2> /dev/null </dev/null"), vec!["df", "-h"]);
+    assert_eq!(last_shell_command("ps aux |"), Vec::<String>::new());
+
+    /* Without the shell, the stages are split by the quoted "|" arguments */
+    let options = parse(&["ps", "aux", "|", "grep", "a|b"]).unwrap();
+    assert_eq!(options.last_command(), vec!["grep", "a|b"]);
+}
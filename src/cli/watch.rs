@@ -0,0 +1,254 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * Run the command again and again and redraw the table in place, like watch(1)
+ */
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use ::cli::options::Options;
+use ::cli::runner::{start, transform};
+use ::reader::exec::ExitPolicy;
+use ::table::datetime::{Timestamp, format_datetime};
+use ::table::diff::{RowChange, diff_rows};
+use ::table::input::Input;
+use ::table::primitive::PrimitiveData;
+use ::table::row::Row;
+use ::table::schema::TableSchema;
+use ::table::table::{Table, TableDataSource};
+use ::writer::tablewriter::TableOutputer;
+use ::writer::textstyle::{Color, ColorHint, ColorMode};
+
+/**
+ * @brief The rows of one run
+ **/
+struct Snapshot {
+    /// The schema of the rows
+    schema : TableSchema,
+    /// The values of each row
+    rows   : Vec<Vec<PrimitiveData>>,
+    /// The colour hints of the rule
+    hints  : Vec<ColorHint>
+}
+
+/**
+ * @brief The input that gives the rows we have kept in memory
+ **/
+struct SnapshotInput<'a> {
+    /// The schema, which is None once it has been passed out
+    schema : Option<TableSchema>,
+    /// The rows to give
+    rows   : ::std::vec::IntoIter<&'a Vec<PrimitiveData>>
+}
+
+impl <'a> Input for SnapshotInput<'a> {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        return self.schema.take();
+    }
+
+    fn parse_next_row<'b>(&mut self, schema:&'b TableSchema) -> Option<Row<'b>>
+    {
+        let values = self.rows.next()?;
+        let mut row = Row::empty(schema);
+
+        for (idx, value) in values.iter().enumerate()
+        {
+            row.set_value(idx, value.clone());
+        }
+
+        return Some(row);
+    }
+}
+
+/**
+ * @brief Run the command once and read all the rows
+ * @param options The command line options
+ * @return The snapshot, which is None if the output can not be parsed, and the message
+ *         about the failure of the command, or the exit code if the command can not be started
+ *         or a column is unknown
+ **/
+fn take_snapshot(options:&Options) -> Result<(Option<Snapshot>, Option<String>), i32>
+{
+    let (mut reader, program, policy, hints) = start(options)?;
+
+    let snapshot = match transform(options, &mut reader, |input| {
+        let schema = input.determine_table_schema()?;
+        let mut rows = Vec::new();
+
+        while let Some(row) = input.parse_next_row(&schema)
+        {
+            rows.push((0..schema.num_columns()).map(|col| row.value_at(col).clone()).collect());
+        }
+
+        Some(Snapshot {
+            schema : schema,
+            rows   : rows,
+            hints  : hints
+        })
+    })
+    {
+        Ok(snapshot) => snapshot,
+        Err(name)    => {
+            reader.finish();
+            eprintln!("squery: unknown column {}", name);
+            return Err(2);
+        }
+    };
+
+    let status = reader.finish();
+
+    if status.success() || policy == ExitPolicy::Ignore
+    {
+        return Ok((snapshot, None));
+    }
+
+    let message = match status.timed_out
+    {
+        true  => format!("{} timed out", program),
+        false => format!("{} exited with status {}", program, status.exit_code())
+    };
+
+    return Ok((snapshot, Some(message + "\n" + status.stderr.trim_end())));
+}
+
+/**
+ * @brief Find the columns that identify a row
+ * @param options The command line options
+ * @param schema The table schema
+ * @return The key columns, which is empty if the whole row is the key, or the name of the
+ *         key column that is not in the schema
+ **/
+fn key_columns(options:&Options, schema:&TableSchema) -> Result<Vec<usize>, String>
+{
+    return match options.keys
+    {
+        Some(ref names) => names.iter().map(|name| schema.types.iter().position(|&(ref column, _)| column == name).ok_or_else(|| name.clone())).collect(),
+        None            => Ok(schema.sort_keys.clone())
+    };
+}
+
+/**
+ * @brief Clear the screen and draw the snapshot
+ * @param options The command line options
+ * @param snapshot The rows of this run
+ * @param previous The rows of the previous run, which are compared with this run
+ * @param keys The key columns of the snapshot
+ * @param message The message about the failure of the command
+ **/
+fn draw(options:&Options, snapshot:&Snapshot, previous:Option<&Snapshot>, keys:&[usize], message:&Option<String>)
+{
+    let command = match options.shell
+    {
+        Some(ref shell) => shell.clone(),
+        None            => options.command.join(" ")
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs() as i64);
+
+    print!("\x1b[H\x1b[2J");
+    println!("Every {}s: {}    {}", options.watch.unwrap_or_default().as_secs_f64(), command, format_datetime(Timestamp(now)));
+
+    if let Some(ref message) = *message
+    {
+        println!("{}", message);
+    }
+    println!();
+
+    /* The rows are only compared if the schema doesn't change */
+    let (changes, removed) = match (options.diff, previous)
+    {
+        (true, Some(previous)) if previous.schema == snapshot.schema => {
+            diff_rows(&previous.rows, &snapshot.rows, keys)
+        },
+        _ => (vec![RowChange::Unchanged; snapshot.rows.len()], Vec::new())
+    };
+
+    let mut rows:Vec<&Vec<PrimitiveData>> = snapshot.rows.iter().collect();
+    let mut changes = changes;
+
+    if let Some(previous) = previous
+    {
+        rows.extend(removed.iter().map(|&idx| &previous.rows[idx]));
+        changes.extend(removed.iter().map(|_| RowChange::Removed));
+    }
+
+    let mut input = SnapshotInput {
+        schema : None,
+        rows   : rows.into_iter()
+    };
+
+    let mut output = TableOutputer::create();
+    output.color_mode(ColorMode::Auto);
+    for hint in snapshot.hints.iter()
+    {
+        output.add_color_hint(hint.clone());
+    }
+
+    if let Some(mut result) = Table::empty(&snapshot.schema, TableDataSource::Parser(&mut input, false)).dump(&mut output)
+    {
+        /* The unchanged rows keep the colour of the highlight rules */
+        for (idx, change) in changes.iter().enumerate()
+        {
+            match change
+            {
+                &RowChange::Unchanged => {},
+                &RowChange::Added     => result.set_row_color(idx, Some(Color::Green)),
+                &RowChange::Changed   => result.set_row_color(idx, Some(Color::Yellow)),
+                &RowChange::Removed   => result.set_row_color(idx, Some(Color::Red))
+            }
+        }
+        result.print_terminal_table(0);
+    }
+
+    let _ = io::stdout().flush();
+}
+
+/**
+ * @brief Run the command every interval and redraw the table until squery is interrupted
+ * @param options The command line options
+ * @param interval The interval between two runs
+ * @return The exit code, if the command can not be started or a column is unknown
+ **/
+pub fn watch(options:&Options, interval:Duration) -> i32
+{
+    let mut previous:Option<Snapshot> = None;
+
+    loop
+    {
+        let begin = Instant::now();
+
+        let (snapshot, message) = match take_snapshot(options)
+        {
+            Ok(result) => result,
+            Err(code)  => return code
+        };
+
+        match snapshot
+        {
+            Some(snapshot) => {
+                let keys = match key_columns(options, &snapshot.schema)
+                {
+                    Ok(keys)  => keys,
+                    Err(name) => {
+                        eprintln!("squery: unknown key column {}", name);
+                        return 2;
+                    }
+                };
+                draw(options, &snapshot, previous.as_ref(), &keys, &message);
+                previous = Some(snapshot);
+            },
+            None           => {
+                /* Keep the previous rows to compare with the next successful run */
+                print!("\x1b[H\x1b[2J");
+                println!("squery: can not determine the schema of the output");
+                if let Some(message) = message
+                {
+                    println!("{}", message);
+                }
+                let _ = io::stdout().flush();
+            }
+        }
+
+        thread::sleep(interval.saturating_sub(begin.elapsed()));
+    }
+}
@@ -1,18 +1,42 @@
+// The code base favours explicit returns, field-by-field struct initialization, reference
+// patterns and len() comparisons, so the clippy lints against these idioms are turned off here.
+#![allow(clippy::needless_return,
+         clippy::redundant_field_names,
+         clippy::len_zero,
+         clippy::match_ref_pats,
+         clippy::needless_borrowed_reference)]
 
 #[macro_use]
 mod reader;
 mod writer;
 mod table;
 mod schema;
+mod cli;
 
-use schema::loader::SchemaManager;
-
-
+use std::env;
+use std::process;
+use cli::options::{Options, USAGE};
 
 fn main() {
-    let mut schema_man = SchemaManager::new();
+    let args:Vec<String> = env::args().skip(1).collect();
 
-    schema_man.push_schema_path("./data".to_string());
+    let code = match Options::parse(&args)
+    {
+        Ok(ref options) if options.help => {
+            print!("{}", USAGE);
+            0
+        },
+        Ok(options) => match (&options.rules, options.watch)
+        {
+            (&Some(ref command), _) => cli::rules::rules(&options, command),
+            (&None, Some(interval)) => cli::watch::watch(&options, interval),
+            (&None, None)           => cli::runner::run(&options)
+        },
+        Err(msg)    => {
+            eprint!("squery: {}\n{}", msg, USAGE);
+            2
+        }
+    };
 
-    schema_man.query("wc", &["-l"]);
+    process::exit(code);
 }
@@ -3,66 +3,635 @@
  *
  * The line reader that makes the standard output as a structured table
  */
-use std::process::{Command, ChildStdout, Stdio};
-use std::io::{BufReader, BufRead};
+use std::process::{Command, Child, ChildStdout, ExitStatus, Stdio};
+use std::io::{self, BufReader, BufRead, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use reader::linetext::{LineTextReader, LineParser};
 use table::input::Input;
 use table::schema::TableSchema;
 use table::row::Row;
 
+/// How long we wait for a child after we have stopped reading its output or killed it
+const REAP_GRACE_PERIOD : Duration = Duration::from_millis(100);
+
+/**
+ * What we should do when the command exits with a non-zero status
+ **/
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExitPolicy {
+    /// Report the error and discard the output that is not written yet
+    Fail,
+    /// Keep the output but report the error, the exit code is still propagated
+    Warn,
+    /// Treat the command as successful and drop its standard error
+    Ignore
+}
+
+impl ExitPolicy {
+    /**
+     * Parse the policy name
+     *
+     * * `name`: The policy name, which is fail, warn or ignore
+     *
+     * Returns the policy or None if the name is unknown
+     **/
+    #[allow(dead_code)]
+    pub fn from_name(name:&str) -> Option<ExitPolicy>
+    {
+        match name
+        {
+            "fail"   => Some(ExitPolicy::Fail),
+            "warn"   => Some(ExitPolicy::Warn),
+            "ignore" => Some(ExitPolicy::Ignore),
+            _        => None
+        }
+    }
+}
+
+/**
+ * How the command is executed
+ **/
+#[derive(Clone, Debug)]
+pub struct ExecOptions {
+    /// The extra environment variables, which override the inherited ones
+    pub env          : Vec<(String, String)>,
+    /// The value of LC_ALL, "C" by default since the localised numbers and dates can not be
+    /// parsed. None to inherit the locale of squery
+    pub locale       : Option<String>,
+    /// The working directory, None to use the current one
+    pub cwd          : Option<String>,
+    /// How long we wait for the next piece of the output before we give up
+    pub read_timeout : Option<Duration>,
+    /// How long the command is allowed to run before it's killed
+    pub timeout      : Option<Duration>
+}
+
+impl Default for ExecOptions {
+    fn default() -> ExecOptions
+    {
+        return ExecOptions {
+            env          : Vec::new(),
+            locale       : Some("C".to_string()),
+            cwd          : None,
+            read_timeout : None,
+            timeout      : None
+        };
+    }
+}
+
+impl ExecOptions {
+    /**
+     * Create the command described by the options
+     *
+     * * `program`: The program we want to call
+     * * `args`: The arguments
+     *
+     * Returns the command which is not started yet
+     **/
+    fn command(&self, program:&str, args:&[&str]) -> Command
+    {
+        let mut ret = Command::new(program);
+        ret.args(args);
+
+        if let Some(ref locale) = self.locale
+        {
+            ret.env("LC_ALL", locale);
+        }
+
+        for &(ref name, ref value) in &self.env
+        {
+            ret.env(name, value);
+        }
+
+        if let Some(ref cwd) = self.cwd
+        {
+            ret.current_dir(cwd);
+        }
+
+        return ret;
+    }
+}
+
 /**
- * The reader object that executes a command
+ * How the command has finished
  **/
-pub struct ExecReader<TParser : LineParser> {
-    /// The actual line reader we used to parse the result
-    line_reader: LineTextReader<ChildStdout , TParser>
+pub struct ExecStatus {
+    /// The exit status, None if we can not wait for the child
+    pub status    : Option<ExitStatus>,
+    /// If the command is killed because of the timeout
+    pub timed_out : bool,
+    /// If the command is killed by SIGPIPE because we have stopped reading its output, or
+    /// killed by us because it keeps running after that, which is not a failure
+    pub broken_pipe : bool,
+    /// Everything the command has written to the standard error
+    pub stderr    : String
 }
 
-impl <TParser:LineParser> ExecReader<TParser> {
+impl ExecStatus {
+    /**
+     * Check if the command exited successfully
+     *
+     * Returns the check result
+     **/
+    pub fn success(&self) -> bool
+    {
+        return !self.timed_out && (self.broken_pipe || self.status.is_some_and(|status| status.success()));
+    }
+
+    /**
+     * Get the exit code we should propagate, a command killed by a signal gets 128 + the
+     * signal number like the shell does, and a command that times out gets 124 like timeout(1)
+     *
+     * Returns the exit code
+     **/
+    pub fn exit_code(&self) -> i32
+    {
+        #[cfg(unix)]
+        fn signal_code(status:&ExitStatus) -> Option<i32>
+        {
+            use std::os::unix::process::ExitStatusExt;
+            return status.signal().map(|sig| 128 + sig);
+        }
+
+        #[cfg(not(unix))]
+        fn signal_code(_status:&ExitStatus) -> Option<i32>
+        {
+            return None;
+        }
+
+        if self.timed_out
+        {
+            return 124;
+        }
+
+        if self.broken_pipe
+        {
+            return 0;
+        }
+
+        match self.status
+        {
+            Some(ref status) => status.code().or_else(|| signal_code(status)).unwrap_or(1),
+            None             => 1
+        }
+    }
+}
+
+/**
+ * Where the output comes from
+ **/
+enum OutputSource {
+    /// Read the pipe directly, when there's no timeout
+    Pipe(ChildStdout),
+    /// The chunks read by another thread, so that we can stop waiting
+    Chunks(Receiver<Vec<u8>>)
+}
+
+/**
+ * The standard output of the command, the read fails with TimedOut once the timeout is
+ * reached, which ends the input for the readers
+ **/
+pub struct ExecOutput {
+    /// Where the output comes from
+    source       : OutputSource,
+    /// The chunk we are reading
+    chunk        : Vec<u8>,
+    /// How much of the chunk has been read
+    pos          : usize,
+    /// How long we wait for the next chunk
+    read_timeout : Option<Duration>,
+    /// When the command should be killed
+    deadline     : Option<Instant>,
+    /// Set when either timeout is reached
+    timed_out    : Arc<AtomicBool>
+}
+
+impl ExecOutput {
+    /**
+     * Wait for the next chunk of the output
+     *
+     * Returns false at the end of the output
+     **/
+    fn next_chunk(&mut self) -> io::Result<bool>
+    {
+        let now = Instant::now();
+
+        let mut wait = self.read_timeout;
+
+        if let Some(deadline) = self.deadline
+        {
+            let left = deadline.saturating_duration_since(now);
+            wait = Some(wait.map_or(left, |wait| wait.min(left)));
+        }
+
+        let chunk = match (&self.source, wait)
+        {
+            (&OutputSource::Chunks(ref chunks), Some(wait)) => chunks.recv_timeout(wait),
+            (&OutputSource::Chunks(ref chunks), None)       => chunks.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            (&OutputSource::Pipe(_), _)                     => return Ok(false)
+        };
+
+        match chunk
+        {
+            Ok(chunk)                           => {
+                self.chunk = chunk;
+                self.pos = 0;
+                return Ok(true);
+            },
+            Err(RecvTimeoutError::Disconnected) => return Ok(false),
+            Err(RecvTimeoutError::Timeout)      => {
+                self.timed_out.store(true, Ordering::SeqCst);
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "the command timed out"));
+            }
+        }
+    }
+}
+
+impl Read for ExecOutput {
+    fn read(&mut self, buf:&mut [u8]) -> io::Result<usize>
+    {
+        if let OutputSource::Pipe(ref mut pipe) = self.source
+        {
+            return pipe.read(buf);
+        }
+
+        if self.pos >= self.chunk.len() && !self.next_chunk()?
+        {
+            return Ok(0);
+        }
+
+        let size = buf.len().min(self.chunk.len() - self.pos);
+        buf[..size].copy_from_slice(&self.chunk[self.pos..self.pos + size]);
+        self.pos += size;
+
+        return Ok(size);
+    }
+}
+
+/**
+ * The reader object that executes a command, or a pipeline of commands
+ **/
+pub struct ExecReader {
+    /// The actual reader we used to parse the result, which is dropped once the command is finished
+    reader    : Option<Box<dyn Input>>,
+    /// The child processes of the pipeline stages, which are removed once they have been reaped
+    children  : Vec<Child>,
+    /// The threads collecting the standard error of the children
+    stderr    : Vec<JoinHandle<Vec<u8>>>,
+    /// When the command should be killed
+    deadline  : Option<Instant>,
+    /// Set when either timeout is reached
+    timed_out : Arc<AtomicBool>,
+    /// If we have read all the rows, otherwise the output is closed before the command ends
+    drained   : bool
+}
+
+/**
+ * Check if the process is killed by SIGPIPE, which is how the earlier stages of a pipeline
+ * normally end when a later stage stops reading, e.g. head
+ *
+ * * `status`: The exit status
+ *
+ * Returns the check result
+ **/
+#[cfg(unix)]
+fn is_broken_pipe(status:&ExitStatus) -> bool
+{
+    use std::os::unix::process::ExitStatusExt;
+    return status.signal() == Some(13);
+}
+
+#[cfg(not(unix))]
+fn is_broken_pipe(_status:&ExitStatus) -> bool
+{
+    return false;
+}
+
+impl ExecReader {
+    /**
+     * Start the program and skip the leading lines of its output
+     *
+     * * `program`: The program we want to call
+     * * `args`: The arguments
+     * * `skip`: How many lines we want to skip
+     * * `options`: How the command is executed
+     * * `build`: The function that creates the reader from the standard output
+     *
+     * Returns the newly created execution reader or None if the program can not be started.
+     * If the reader can not be created, e.g. the command writes nothing, we still keep the
+     * child so that its exit status can be collected, but there is no table schema
+     **/
+    #[allow(dead_code)]
+    pub fn spawn_with<F>(program:&str, args:&[&str], skip:usize, options:&ExecOptions, build:F) -> Option<ExecReader>
+        where F: FnOnce(BufReader<ExecOutput>) -> Option<Box<dyn Input>>
+    {
+        let mut stage = vec![program];
+        stage.extend_from_slice(args);
+        return ExecReader::spawn_pipeline_with(&[stage], skip, options, build);
+    }
+
+    /**
+     * Start the pipeline, the standard output of each stage is the standard input of the next
+     * one, and we read the output of the last stage. Like the pipefail option of bash, the
+     * pipeline fails if any stage fails, but an earlier stage killed by SIGPIPE is fine, and
+     * so is the last stage if we have stopped reading before it ends
+     *
+     * * `stages`: The program and the arguments of each stage
+     * * `skip`: How many lines we want to skip
+     * * `options`: How the commands are executed
+     * * `build`: The function that creates the reader from the standard output
+     *
+     * Returns the newly created execution reader or None if any stage can not be started
+     **/
+    pub fn spawn_pipeline_with<F>(stages:&[Vec<&str>], skip:usize, options:&ExecOptions, build:F) -> Option<ExecReader>
+        where F: FnOnce(BufReader<ExecOutput>) -> Option<Box<dyn Input>>
+    {
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+        let mut ret = ExecReader {
+            reader    : None,
+            children  : Vec::new(),
+            stderr    : Vec::new(),
+            deadline  : deadline,
+            timed_out : Arc::new(AtomicBool::new(false)),
+            drained   : false
+        };
+
+        let mut stdout:Option<ChildStdout> = None;
+
+        for stage in stages
+        {
+            let (program, args) = stage.split_first()?;
+            let mut command = options.command(program, args);
+
+            if let Some(prev) = stdout.take()
+            {
+                command.stdin(Stdio::from(prev));
+            }
+
+            /* If a stage can not be started, the stages started already are killed when ret
+             * is dropped */
+            let mut child = command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()?;
+
+            /* The standard error is collected in another thread, otherwise the child may block
+             * on a full stderr pipe while we are waiting for its standard output */
+            if let Some(mut pipe) = child.stderr.take()
+            {
+                ret.stderr.push(thread::spawn(move || {
+                    let mut buf = Vec::new();
+                    let _ = pipe.read_to_end(&mut buf);
+                    buf
+                }));
+            }
+
+            stdout = child.stdout.take();
+            ret.children.push(child);
+        }
+
+        let source = match (stdout?, options.read_timeout.is_some() || deadline.is_some())
+        {
+            (pipe, false)    => OutputSource::Pipe(pipe),
+            (mut pipe, true) => {
+                /* The pipe is read in another thread, so that we can stop waiting once the
+                 * timeout is reached. The thread stops when we don't want the output any more */
+                let (sender, receiver) = mpsc::channel();
+                thread::spawn(move || {
+                    let mut buf = [0u8; 4096];
+                    while let Ok(size) = pipe.read(&mut buf)
+                    {
+                        if size == 0 || sender.send(buf[..size].to_vec()).is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+                OutputSource::Chunks(receiver)
+            }
+        };
+
+        let mut br = BufReader::new(ExecOutput {
+            source       : source,
+            chunk        : Vec::new(),
+            pos          : 0,
+            read_timeout : options.read_timeout,
+            deadline     : deadline,
+            timed_out    : ret.timed_out.clone()
+        });
+
+        for _ in 0..skip
+        {
+            if br.read_line(&mut String::new()).is_err()
+            {
+                return Some(ret);
+            }
+        }
+
+        ret.reader = build(br);
+
+        return Some(ret);
+    }
+
     /**
      * Create a new execute reader
      *
      * * `program`: The program we want to call
-     * * `param`: args The arguments 
+     * * `param`: args The arguments
      * * `param` skip How many lines we want to skip before parsing
      * * `param` parser The line parser instance
      *
      * Returns the newly created execution reader
      **/
     #[allow(dead_code)]
-    pub fn create<'x, 'y, 'z>(program:&'x str, args:&'y[&'y str], skip:usize, schema:&'z String, parser:TParser) -> Option<ExecReader<TParser>>
+    pub fn create<'y, TParser:LineParser + 'static>(program:&str, args:&'y[&'y str], skip:usize, schema:&str, parser:TParser) -> Option<ExecReader>
+    {
+        return ExecReader::spawn_with(program, args, skip, &ExecOptions::default(), |br| {
+            LineTextReader::create_parser(schema, br, parser).map(|lp| Box::new(lp) as Box<dyn Input>)
+        });
+    }
+
+    /**
+     * Create a new execute reader whose columns come from the header line of the output
+     *
+     * * `program`: The program we want to call
+     * * `args`: The arguments
+     * * `skip`: How many lines we want to skip before the header line
+     * * `rule`: The rule schema which gives the types of the known columns
+     * * `parser`: The line parser instance
+     *
+     * Returns the newly created execution reader
+     **/
+    #[allow(dead_code)]
+    pub fn create_with_header<TParser:LineParser + 'static>(program:&str, args:&[&str], skip:usize, rule:&str, parser:TParser) -> Option<ExecReader>
+    {
+        return ExecReader::spawn_with(program, args, skip, &ExecOptions::default(), |br| {
+            LineTextReader::create_header_parser(rule, br, parser).map(|lp| Box::new(lp) as Box<dyn Input>)
+        });
+    }
+
+    /**
+     * Wait for the child, it's killed if we have timed out, or if it's still running when the
+     * timeout is reached
+     *
+     * * `child`: The child process
+     * * `patient`: If we wait for the child as long as it runs, otherwise it's killed if it
+     *   doesn't exit in the grace period
+     *
+     * Returns the exit status or None if we can not wait for the child, and if we have killed
+     * the child because we have run out of patience
+     **/
+    fn reap(&self, child:&mut Child, patient:bool) -> (Option<ExitStatus>, bool)
     {
-        if let Ok(child) = Command::new(program).args(args).stdout(Stdio::piped()).spawn()
+        let give_up = Instant::now() + REAP_GRACE_PERIOD;
+
+        let abandoned = loop
         {
-            if let Some(stdout) = child.stdout
+            if self.timed_out.load(Ordering::SeqCst)
             {
-                let mut br  = BufReader::new(stdout);
+                break false;
+            }
+
+            match (child.try_wait(), self.deadline)
+            {
+                (Ok(Some(status)), _)                                   => return (Some(status), false),
+                (Ok(None), _) if !patient && Instant::now() >= give_up  => break true,
+                (Ok(None), Some(deadline)) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+                (Ok(None), Some(_))                                     => self.timed_out.store(true, Ordering::SeqCst),
+                (Ok(None), None) if patient                             => return (child.wait().ok(), false),
+                (Ok(None), None)                                        => thread::sleep(Duration::from_millis(10)),
+                (Err(_), _)                                             => return (None, false)
+            }
+        };
+
+        /* The killed child normally exits at once, but we don't hang on the one that doesn't */
+        let _ = child.kill();
+        let give_up = Instant::now() + REAP_GRACE_PERIOD;
+
+        loop
+        {
+            match child.try_wait()
+            {
+                Ok(Some(status))                      => return (Some(status), abandoned),
+                Ok(None) if Instant::now() < give_up  => thread::sleep(Duration::from_millis(10)),
+                _                                     => return (None, abandoned)
+            }
+        }
+    }
+
+    /**
+     * Stop reading the output, reap the children and collect their standard error. If a child
+     * is still writing, it gets a broken pipe since we don't read its output any more, which
+     * is not a failure
+     *
+     * Returns how the command has finished
+     **/
+    pub fn finish(&mut self) -> ExecStatus
+    {
+        /* The last stage only gets a broken pipe from us if we close the output before it ends */
+        let closed = !self.drained;
+        return self.stop(closed);
+    }
+
+    /**
+     * Close the output, reap the children and collect their standard error
+     *
+     * * `closed`: If we close the output before it ends, in this case we don't wait for the
+     *   children that keep running
+     *
+     * Returns how the command has finished
+     **/
+    fn stop(&mut self, closed:bool) -> ExecStatus
+    {
+        self.reader = None;
 
-                let mut skipped = 0;
+        let children = ::std::mem::take(&mut self.children);
+        let last = children.len().saturating_sub(1);
 
-                while skip > skipped
+        let statuses:Vec<_> = children.into_iter().map(|mut child| self.reap(&mut child, !closed)).collect();
+
+        let failed = |idx:usize, &(ref status, abandoned):&(Option<ExitStatus>, bool)| {
+            match status
+            {
+                _ if abandoned    => false,
+                &Some(ref status) => !(status.success() || ((idx < last || closed) && is_broken_pipe(status))),
+                &None             => true
+            }
+        };
+
+        /* The status of the last failed stage, or the last stage if nothing fails */
+        let (status, broken_pipe) = match statuses.iter().enumerate().rev().find(|&(idx, status)| failed(idx, status))
+        {
+            Some((_, &(status, _))) => (status, false),
+            None                    => {
+                let (status, abandoned) = statuses.last().cloned().unwrap_or((None, false));
+                (status, abandoned || status.is_some_and(|status| is_broken_pipe(&status)))
+            }
+        };
+
+        let timed_out = self.timed_out.load(Ordering::SeqCst);
+        let killed = timed_out || statuses.iter().any(|&(_, abandoned)| abandoned);
+
+        let mut stderr = Vec::new();
+
+        for handle in self.stderr.drain(..)
+        {
+            /* The processes started by the killed child may still hold the standard error, so
+             * we don't wait for them after the kill */
+            if killed
+            {
+                let give_up = Instant::now() + REAP_GRACE_PERIOD;
+                while !handle.is_finished() && Instant::now() < give_up
                 {
-                    match br.read_line(&mut String::new()) 
-                    {
-                        Err(_) => return None,
-                        Ok(_)  => skipped += 1
-                    }
+                    thread::sleep(Duration::from_millis(10));
                 }
-
-                if let Some(lp) = LineTextReader::create_parser(schema, br, parser)
+                if !handle.is_finished()
                 {
-                    return Some(ExecReader {
-                        line_reader : lp
-                    });
+                    continue;
                 }
             }
+
+            if let Ok(buf) = handle.join()
+            {
+                stderr.extend(buf);
+            }
         }
-        return None;
+
+        return ExecStatus {
+            status      : status,
+            timed_out   : timed_out,
+            broken_pipe : broken_pipe,
+            stderr      : String::from_utf8_lossy(&stderr).into_owned()
+        };
     }
 }
 
-impl <TParser : LineParser> Input for ExecReader<TParser> {
-    fn determine_table_schema(&mut self) -> Option<TableSchema> { self.line_reader.determine_table_schema() }
-    fn parse_next_row<'a>(&mut self, schema:&'a TableSchema) -> Option<Row<'a>> { self.line_reader.parse_next_row(schema) }
+impl Drop for ExecReader {
+    fn drop(&mut self)
+    {
+        /* If the caller doesn't finish the reader, we don't care about the children any more,
+         * but we still need to reap them without waiting for them */
+        for child in &mut self.children
+        {
+            let _ = child.kill();
+        }
+        self.stop(true);
+    }
 }
 
+impl Input for ExecReader {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        return self.reader.as_mut()?.determine_table_schema();
+    }
+
+    fn parse_next_row<'a>(&mut self, schema:&'a TableSchema) -> Option<Row<'a>>
+    {
+        let ret = self.reader.as_mut()?.parse_next_row(schema);
+        self.drained = ret.is_none();
+        return ret;
+    }
+}
@@ -0,0 +1,191 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The parser for the column aligned text, for example the output of ps aux or df
+ */
+use std::borrow::Cow;
+use reader::linetext::{LineParser, Field};
+use ::table::schema::TableSchema;
+
+/**
+ * @brief How the column boundaries are determined
+ **/
+#[derive(Clone, Copy, PartialEq)]
+enum Boundary {
+    /// The spans are the exact character ranges given by the rule
+    Exact,
+    /// The spans are the titles in the header line, the actual cut is the blank between
+    /// two titles, since the values may be wider than the titles
+    Header
+}
+
+/**
+ * @brief The fixed-width column parser. The last column takes the rest of the line when
+ *        the columns are learned from the header or its end is open, so the values with
+ *        spaces, like the command line, are kept
+ **/
+#[allow(dead_code)]
+pub struct FixedWidthParser {
+    /// The character ranges of the columns, the end of the last column may be open
+    spans    : Vec<(usize, Option<usize>)>,
+    /// How we use the spans
+    boundary : Boundary
+}
+
+impl FixedWidthParser {
+    /**
+     * @brief Create a parser with the explicit column ranges
+     * @param spans The list of (begin, end) character offsets, the end is exclusive and None
+     *        means the column takes the rest of the line
+     * @return The newly created parser
+     **/
+    #[allow(dead_code)]
+    pub fn create(spans:Vec<(usize, Option<usize>)>) -> FixedWidthParser
+    {
+        return FixedWidthParser {
+            spans    : spans,
+            boundary : Boundary::Exact
+        };
+    }
+
+    /**
+     * @brief Create a parser that learns the column boundaries from the header line,
+     *        see LineParser::learn_header
+     * @return The newly created parser
+     **/
+    #[allow(dead_code)]
+    pub fn from_header() -> FixedWidthParser
+    {
+        return FixedWidthParser {
+            spans    : Vec::new(),
+            boundary : Boundary::Header
+        };
+    }
+
+    /**
+     * @brief Parse the range list in the rule file, for example "0-8 9-15 16-"
+     * @param spec The range list
+     * @return The parser or None if the range list is invalid
+     **/
+    #[allow(dead_code)]
+    pub fn from_spec(spec:&str) -> Option<FixedWidthParser>
+    {
+        let mut spans = Vec::new();
+
+        for range in spec.split(&[' ', '\t', ','][..]).filter(|s| s.len() > 0)
+        {
+            let mut parts = range.splitn(2, '-');
+            let begin = parts.next()?.parse::<usize>().ok()?;
+            let end = match parts.next()?
+            {
+                ""  => None,
+                end => Some(end.parse::<usize>().ok()?)
+            };
+
+            if end.is_some_and(|end| end < begin)
+            {
+                return None;
+            }
+
+            spans.push((begin, end));
+        }
+
+        if spans.len() == 0
+        {
+            return None;
+        }
+
+        return Some(FixedWidthParser::create(spans));
+    }
+
+    /**
+     * @brief Find where the line should be cut between two columns learned from the header.
+     *        It's the first blank between the end of the left title and the beginning of the
+     *        right title, or the first blank after the left title if the left value overflows.
+     *        If the previous column has already overflowed into this one, the value of this
+     *        column begins at the first non-blank character after the previous cut
+     * @param chars The characters of the line
+     * @param left The span of the left title
+     * @param right The span of the right title
+     * @param from The previous cut
+     * @return The cut position
+     **/
+    fn find_cut(chars:&[(usize, char)], left:(usize, Option<usize>), right:(usize, Option<usize>), from:usize) -> usize
+    {
+        let mut begin = left.1.unwrap_or(left.0);
+
+        if from >= begin
+        {
+            begin = (from..chars.len()).find(|&pos| !chars[pos].1.is_whitespace()).unwrap_or(chars.len());
+        }
+
+        let end = right.0.max(begin);
+
+        if let Some(pos) = (begin..chars.len().min(end + 1)).find(|&pos| chars[pos].1.is_whitespace())
+        {
+            return pos;
+        }
+
+        return (begin..chars.len()).find(|&pos| chars[pos].1.is_whitespace()).unwrap_or(chars.len());
+    }
+}
+
+impl LineParser for FixedWidthParser {
+    fn learn_header(&mut self, header:&str)
+    {
+        if self.boundary != Boundary::Header
+        {
+            return;
+        }
+
+        self.spans.clear();
+
+        let mut begin = None;
+        for (pos, ch) in header.chars().chain(Some(' ')).enumerate()
+        {
+            match (begin, ch.is_whitespace())
+            {
+                (None, false)       => begin = Some(pos),
+                (Some(start), true) => { self.spans.push((start, Some(pos))); begin = None; },
+                _                   => {}
+            }
+        }
+    }
+
+    fn parse_next_line<'text>(&self, line:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        let text = line.trim_end_matches(&['\r', '\n'][..]);
+        let chars:Vec<(usize, char)> = text.char_indices().collect();
+        let count = self.spans.len().min(schema.num_columns());
+
+        let offset = |pos:usize| if pos < chars.len() { chars[pos].0 } else { text.len() };
+
+        let mut ret = Vec::<Field>::new();
+        let mut cut = 0;
+
+        for idx in 0..count
+        {
+            let last = idx + 1 == self.spans.len();
+
+            let (begin, end) = match self.boundary
+            {
+                Boundary::Exact  => {
+                    let (begin, end) = self.spans[idx];
+                    (begin, end.unwrap_or(if last { usize::MAX } else { self.spans[idx + 1].0 }))
+                },
+                Boundary::Header => {
+                    let begin = cut;
+                    cut = if last { usize::MAX } else { FixedWidthParser::find_cut(&chars, self.spans[idx], self.spans[idx + 1], cut) };
+                    (begin, cut)
+                }
+            };
+
+            let (begin, end) = (offset(begin), offset(end));
+            let value = if begin < end { text[begin..end].trim() } else { "" };
+
+            ret.push(if value.len() > 0 { Some(Cow::Borrowed(value)) } else { None });
+        }
+
+        return Some(ret);
+    }
+}
@@ -0,0 +1,400 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * A minimal JSON parser used by the JSON input reader
+ */
+
+/// The max nesting level of the arrays and objects, so that a deeply nested text doesn't
+/// overflow the stack
+const MAX_DEPTH : usize = 256;
+
+/**
+ * @brief A JSON value
+ **/
+#[derive(Debug)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    /// The number is kept as it is in the text, so that we don't lose precision for integers
+    Number(String),
+    Str(String),
+    Array(Vec<JsonValue>),
+    /// The object members in the order of the text
+    Object(Vec<(String, JsonValue)>)
+}
+
+/**
+ * @brief The reason why we can not parse the text
+ **/
+#[derive(Debug, PartialEq)]
+pub enum JsonError {
+    /// The text ends before the value is completed, more text is needed
+    Incomplete,
+    /// The text is not a valid JSON
+    Invalid
+}
+
+/**
+ * @brief Append a JSON string literal to the buffer, only the quote, the backslash and the
+ *        control characters are escaped. This is shared by the parser and the JSON writers
+ * @param buf The buffer
+ * @param text The string to quote
+ **/
+pub fn push_json_string(buf:&mut String, text:&str)
+{
+    buf.push('"');
+    for ch in text.chars()
+    {
+        match ch
+        {
+            '"'  => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            c if (c as u32) < 0x20 => buf.push_str(&format!("\\u{:04x}", c as u32)),
+            c    => buf.push(c)
+        }
+    }
+    buf.push('"');
+}
+
+impl JsonValue {
+    /**
+     * @brief Get the member of an object or the element of an array
+     * @param key The member name or the array index
+     * @return The child value or None if it doesn't exist
+     **/
+    pub fn child(&self, key:&str) -> Option<&JsonValue>
+    {
+        match self
+        {
+            &JsonValue::Object(ref members) => members.iter().find(|&&(ref name, _)| name == key).map(|&(_, ref value)| value),
+            &JsonValue::Array(ref elements) => key.parse::<usize>().ok().and_then(|idx| elements.get(idx)),
+            _                               => None
+        }
+    }
+
+    /**
+     * @brief Get the value by a dotted path, e.g. Config.Image or Mounts.0.Source
+     * @param path The dotted path
+     * @return The value or None if it doesn't exist
+     **/
+    pub fn lookup(&self, path:&str) -> Option<&JsonValue>
+    {
+        let mut ret = self;
+        for key in path.split('.').filter(|key| key.len() > 0)
+        {
+            ret = ret.child(key)?;
+        }
+        return Some(ret);
+    }
+
+    /**
+     * @brief Format the value as the compact JSON text
+     * @return The JSON text
+     **/
+    pub fn to_json(&self) -> String
+    {
+        fn push_value(buf:&mut String, value:&JsonValue)
+        {
+            match value
+            {
+                &JsonValue::Null              => buf.push_str("null"),
+                &JsonValue::Bool(b)           => buf.push_str(if b { "true" } else { "false" }),
+                &JsonValue::Number(ref n)     => buf.push_str(n),
+                &JsonValue::Str(ref s)        => push_json_string(buf, s),
+                &JsonValue::Array(ref elements) => {
+                    buf.push('[');
+                    for (idx, element) in elements.iter().enumerate()
+                    {
+                        if idx > 0 { buf.push(','); }
+                        push_value(buf, element);
+                    }
+                    buf.push(']');
+                },
+                &JsonValue::Object(ref members) => {
+                    buf.push('{');
+                    for (idx, &(ref name, ref member)) in members.iter().enumerate()
+                    {
+                        if idx > 0 { buf.push(','); }
+                        push_json_string(buf, name);
+                        buf.push(':');
+                        push_value(buf, member);
+                    }
+                    buf.push('}');
+                }
+            }
+        }
+
+        let mut ret = String::new();
+        push_value(&mut ret, self);
+        return ret;
+    }
+}
+
+/**
+ * @brief The recursive descent JSON parser
+ **/
+struct Parser<'a> {
+    /// The text to parse
+    text : &'a [u8],
+    /// The current position
+    pos  : usize,
+    /// The nesting level of the value we are parsing
+    depth : usize
+}
+
+impl <'a> Parser<'a> {
+    fn skip_ws(&mut self)
+    {
+        while self.pos < self.text.len() && (self.text[self.pos] as char).is_ascii_whitespace()
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Result<u8, JsonError>
+    {
+        self.skip_ws();
+        return self.text.get(self.pos).cloned().ok_or(JsonError::Incomplete);
+    }
+
+    fn expect(&mut self, what:&str) -> Result<(), JsonError>
+    {
+        for &ch in what.as_bytes()
+        {
+            match self.text.get(self.pos)
+            {
+                None                 => return Err(JsonError::Incomplete),
+                Some(&c) if c == ch  => self.pos += 1,
+                Some(_)              => return Err(JsonError::Invalid)
+            }
+        }
+        return Ok(());
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, JsonError>
+    {
+        if self.pos + 4 > self.text.len()
+        {
+            return Err(JsonError::Incomplete);
+        }
+
+        let hex = ::std::str::from_utf8(&self.text[self.pos..self.pos + 4]).map_err(|_| JsonError::Invalid)?;
+        self.pos += 4;
+        return u32::from_str_radix(hex, 16).map_err(|_| JsonError::Invalid);
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError>
+    {
+        self.expect("\"")?;
+
+        let mut buf = Vec::<u8>::new();
+
+        loop
+        {
+            let ch = *self.text.get(self.pos).ok_or(JsonError::Incomplete)?;
+            self.pos += 1;
+
+            match ch
+            {
+                b'"'  => return String::from_utf8(buf).map_err(|_| JsonError::Invalid),
+                b'\\' => {
+                    let esc = *self.text.get(self.pos).ok_or(JsonError::Incomplete)?;
+                    self.pos += 1;
+                    let decoded = match esc
+                    {
+                        b'"'  => '"',
+                        b'\\' => '\\',
+                        b'/'  => '/',
+                        b'b'  => '\u{8}',
+                        b'f'  => '\u{c}',
+                        b'n'  => '\n',
+                        b'r'  => '\r',
+                        b't'  => '\t',
+                        b'u'  => {
+                            let mut code = self.parse_hex4()?;
+                            if (0xd800..0xdc00).contains(&code)
+                            {
+                                self.expect("\\u")?;
+                                let low = self.parse_hex4()?;
+                                if !(0xdc00..0xe000).contains(&low)
+                                {
+                                    return Err(JsonError::Invalid);
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            ::std::char::from_u32(code).unwrap_or('\u{fffd}')
+                        },
+                        _     => return Err(JsonError::Invalid)
+                    };
+                    let mut tmp = [0u8; 4];
+                    buf.extend_from_slice(decoded.encode_utf8(&mut tmp).as_bytes());
+                },
+                _     => buf.push(ch)
+            }
+        }
+    }
+
+    /**
+     * @brief Skip the digits
+     * @return The number of digits we have skipped
+     **/
+    fn skip_digits(&mut self) -> usize
+    {
+        let begin = self.pos;
+        while self.pos < self.text.len() && self.text[self.pos].is_ascii_digit()
+        {
+            self.pos += 1;
+        }
+        return self.pos - begin;
+    }
+
+    /**
+     * @brief Parse the number in the JSON grammar, which is -?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?
+     * @return The number text or the error, which is Incomplete if the text ends where a
+     *         digit is expected
+     **/
+    fn parse_number(&mut self) -> Result<String, JsonError>
+    {
+        let begin = self.pos;
+
+        let expect_digits = |this:&mut Parser| -> Result<(), JsonError> {
+            match (this.skip_digits(), this.pos == this.text.len())
+            {
+                (0, true)  => Err(JsonError::Incomplete),
+                (0, false) => Err(JsonError::Invalid),
+                _          => Ok(())
+            }
+        };
+
+        if self.text.get(self.pos) == Some(&b'-')
+        {
+            self.pos += 1;
+        }
+
+        match self.text.get(self.pos)
+        {
+            Some(&b'0') => self.pos += 1,
+            _           => expect_digits(self)?
+        }
+
+        if self.text.get(self.pos) == Some(&b'.')
+        {
+            self.pos += 1;
+            expect_digits(self)?;
+        }
+
+        if let Some(&b'e') | Some(&b'E') = self.text.get(self.pos)
+        {
+            self.pos += 1;
+            if let Some(&b'+') | Some(&b'-') = self.text.get(self.pos)
+            {
+                self.pos += 1;
+            }
+            expect_digits(self)?;
+        }
+
+        return ::std::str::from_utf8(&self.text[begin..self.pos]).map(|number| number.to_string()).map_err(|_| JsonError::Invalid);
+    }
+
+    /**
+     * @brief Parse the elements of an array or the members of an object, the nesting level
+     *        is limited
+     * @param parse The function that parses the content after the opening bracket
+     * @return The value or the error
+     **/
+    fn parse_nested<F>(&mut self, parse:F) -> Result<JsonValue, JsonError>
+        where F: FnOnce(&mut Parser<'a>) -> Result<JsonValue, JsonError>
+    {
+        if self.depth >= MAX_DEPTH
+        {
+            return Err(JsonError::Invalid);
+        }
+
+        self.pos += 1;
+        self.depth += 1;
+        let ret = parse(self);
+        self.depth -= 1;
+
+        return ret;
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError>
+    {
+        match self.peek()?
+        {
+            b'n' => { self.expect("null")?; Ok(JsonValue::Null) },
+            b't' => { self.expect("true")?; Ok(JsonValue::Bool(true)) },
+            b'f' => { self.expect("false")?; Ok(JsonValue::Bool(false)) },
+            b'"' => Ok(JsonValue::Str(self.parse_string()?)),
+            b'[' => self.parse_nested(|this| {
+                let mut elements = Vec::new();
+                if this.peek()? == b']'
+                {
+                    this.pos += 1;
+                    return Ok(JsonValue::Array(elements));
+                }
+                loop
+                {
+                    elements.push(this.parse_value()?);
+                    match this.peek()?
+                    {
+                        b',' => this.pos += 1,
+                        b']' => { this.pos += 1; return Ok(JsonValue::Array(elements)); },
+                        _    => return Err(JsonError::Invalid)
+                    }
+                }
+            }),
+            b'{' => self.parse_nested(|this| {
+                let mut members = Vec::new();
+                if this.peek()? == b'}'
+                {
+                    this.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                loop
+                {
+                    if this.peek()? != b'"'
+                    {
+                        return Err(JsonError::Invalid);
+                    }
+                    let name = this.parse_string()?;
+                    if this.peek()? != b':'
+                    {
+                        return Err(JsonError::Invalid);
+                    }
+                    this.pos += 1;
+                    members.push((name, this.parse_value()?));
+                    match this.peek()?
+                    {
+                        b',' => this.pos += 1,
+                        b'}' => { this.pos += 1; return Ok(JsonValue::Object(members)); },
+                        _    => return Err(JsonError::Invalid)
+                    }
+                }
+            }),
+            b'-' | b'0'..=b'9' => Ok(JsonValue::Number(self.parse_number()?)),
+            _    => Err(JsonError::Invalid)
+        }
+    }
+}
+
+/**
+ * @brief Parse the first JSON value in the text, the arrays and objects can be nested at
+ *        most MAX_DEPTH levels
+ * @param text The text to parse
+ * @return The value and the number of bytes consumed, or the error
+ **/
+pub fn parse_json(text:&str) -> Result<(JsonValue, usize), JsonError>
+{
+    let mut parser = Parser {
+        text  : text.as_bytes(),
+        pos   : 0,
+        depth : 0
+    };
+
+    let value = parser.parse_value()?;
+    return Ok((value, parser.pos));
+}
@@ -0,0 +1,270 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The reader for the JSON output, for example docker inspect, kubectl -o json or ip -j
+ */
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::mem::swap;
+use reader::json::{JsonValue, parse_json};
+use reader::linetext::fill_field;
+use ::table::input::Input;
+use ::table::schema::TableSchema;
+use ::table::row::Row;
+
+/// The max number of characters of the invalid JSON value we print in the warning
+const MAX_REPORTED_CHARS : usize = 60;
+
+/**
+ * @brief Shorten the text for the message, the line breaks are folded
+ * @param text The text
+ * @return The shortened text
+ **/
+fn abbreviate(text:&str) -> String
+{
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    return match text.char_indices().nth(MAX_REPORTED_CHARS)
+    {
+        Some((pos, _)) => format!("{}...", &text[..pos]),
+        None           => text
+    };
+}
+
+/**
+ * @brief Describe where the records are and how the columns are mapped to the JSON values
+ **/
+#[derive(Default)]
+pub struct JsonLayout {
+    /// The dotted path to the records, e.g. items for kubectl. If the value there is an
+    /// array, each element is a record, otherwise the value itself is a record
+    pub root  : Option<String>,
+    /// The dotted path of the column, the column which is not listed here uses its name as
+    /// the path
+    pub paths : Vec<(String, String)>
+}
+
+/**
+ * @brief The JSON reader, the input is either a JSON array of records, or the records one by
+ *        one like NDJSON. Each record is read as soon as it's completed, so the NDJSON is
+ *        streamed. The JSON values are coerced into the column type, the null and the
+ *        values that can not be converted are Nothing, the arrays and objects are kept as the
+ *        JSON text in the string columns.
+ **/
+pub struct JsonReader<T:Read> {
+    /// The file we want to read
+    fp       : BufReader<T>,
+    /// The schema, which is None once it has been passed out
+    schema   : Option<TableSchema>,
+    /// The dotted path to the records
+    root     : Option<String>,
+    /// The dotted path of each column
+    paths    : Vec<String>,
+    /// The text we have read but not parsed yet
+    buffer   : String,
+    /// How much of the buffer has been scanned for the end of the value
+    scanned  : usize,
+    /// The nesting level at the end of the scanned text
+    depth    : usize,
+    /// If the scanned text ends inside a string
+    in_str   : bool,
+    /// If the scanned text ends with a backslash inside a string
+    escaped  : bool,
+    /// The fields of the records we have parsed but not returned
+    records  : VecDeque<Vec<Option<String>>>
+}
+
+impl <T:Read> JsonReader<T> {
+    /**
+     * @brief Create a new JSON reader
+     * @param schema The schema string
+     * @param fp The file pointer
+     * @param layout How the columns are mapped to the JSON values
+     * @return The newly created reader or None if the schema is invalid
+     **/
+    #[allow(dead_code)]
+    pub fn create(schema:&str, fp:BufReader<T>, layout:JsonLayout) -> Option<JsonReader<T>>
+    {
+        let schema = TableSchema::from_spec(schema)?;
+
+        let paths = schema.types.iter().map(|&(ref name, _)| {
+            match layout.paths.iter().find(|&&(ref column, _)| column == name)
+            {
+                Some(&(_, ref path)) => path.clone(),
+                None                 => name.clone()
+            }
+        }).collect();
+
+        return Some(JsonReader {
+            fp      : fp,
+            schema  : Some(schema),
+            root    : layout.root,
+            paths   : paths,
+            buffer  : String::new(),
+            scanned : 0,
+            depth   : 0,
+            in_str  : false,
+            escaped : false,
+            records : VecDeque::new()
+        });
+    }
+
+    /**
+     * @brief Read the next top-level JSON value. We track the nesting level of the text we
+     *        have read, so the value is parsed only once it's completed
+     * @return The value or None at the end of input
+     **/
+    fn next_value(&mut self) -> Option<JsonValue>
+    {
+        loop
+        {
+            while self.scanned < self.buffer.len()
+            {
+                let ch = self.buffer.as_bytes()[self.scanned];
+                self.scanned += 1;
+
+                if self.in_str
+                {
+                    match ch
+                    {
+                        _ if self.escaped => self.escaped = false,
+                        b'\\'             => self.escaped = true,
+                        b'"'              => self.in_str = false,
+                        _                 => {}
+                    }
+                    continue;
+                }
+
+                match ch
+                {
+                    b'"'        => self.in_str = true,
+                    b'{' | b'[' => {
+                        if self.depth == 0
+                        {
+                            /* Drop the text between two top-level values */
+                            self.buffer.drain(..self.scanned - 1);
+                            self.scanned = 1;
+                        }
+                        self.depth += 1;
+                    },
+                    b'}' | b']' => {
+                        self.depth = self.depth.saturating_sub(1);
+
+                        if self.depth == 0
+                        {
+                            let text:String = self.buffer.drain(..self.scanned).collect();
+                            self.scanned = 0;
+
+                            /* The text which is not a valid JSON value is skipped, but we tell
+                             * the user, otherwise the missing rows are not noticed */
+                            match parse_json(&text)
+                            {
+                                Ok((value, _)) => return Some(value),
+                                Err(_)         => eprintln!("squery: skip the invalid JSON value {}", abbreviate(&text))
+                            }
+                        }
+                    },
+                    _           => {}
+                }
+            }
+
+            match self.fp.read_line(&mut self.buffer)
+            {
+                Ok(0) | Err(_) => return None,
+                Ok(_)          => {}
+            }
+        }
+    }
+
+    /**
+     * @brief Get the fields of the record
+     * @param record The record
+     * @return The text of each column, None if the value is missing or null
+     **/
+    fn record_fields(&self, record:&JsonValue) -> Vec<Option<String>>
+    {
+        return self.paths.iter().map(|path| {
+            match record.lookup(path)
+            {
+                None | Some(&JsonValue::Null)  => None,
+                Some(&JsonValue::Bool(b))      => Some(b.to_string()),
+                Some(&JsonValue::Number(ref n)) => Some(n.clone()),
+                Some(&JsonValue::Str(ref s))   => Some(s.clone()),
+                Some(other)                    => Some(other.to_json())
+            }
+        }).collect();
+    }
+
+    /**
+     * @brief Read the next top-level value and queue the records in it
+     * @return false at the end of input
+     **/
+    fn read_records(&mut self) -> bool
+    {
+        let value = match self.next_value()
+        {
+            Some(value) => value,
+            None        => return false
+        };
+
+        let root = match self.root
+        {
+            Some(ref path) => value.lookup(path),
+            None           => Some(&value)
+        };
+
+        match root
+        {
+            Some(&JsonValue::Array(ref elements)) => {
+                for element in elements
+                {
+                    let fields = self.record_fields(element);
+                    self.records.push_back(fields);
+                }
+            },
+            Some(record @ &JsonValue::Object(_)) => {
+                let fields = self.record_fields(record);
+                self.records.push_back(fields);
+            },
+            _                                    => {}
+        }
+
+        return true;
+    }
+}
+
+impl <T:Read> Input for JsonReader<T> {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        let mut ret = None;
+        swap(&mut ret, &mut self.schema);
+        return ret;
+    }
+
+    fn parse_next_row<'a>(&mut self, schema:&'a TableSchema) -> Option<Row<'a>>
+    {
+        while self.records.len() == 0
+        {
+            if !self.read_records()
+            {
+                return None;
+            }
+        }
+
+        let fields = self.records.pop_front()?;
+        let mut row = Row::empty(schema);
+
+        for (idx, field) in fields.iter().enumerate()
+        {
+            if let &Some(ref text) = field
+            {
+                /* The value can not be converted to the column type is left as Nothing */
+                fill_field(&mut row, idx, text, schema);
+            }
+        }
+
+        return Some(row);
+    }
+}
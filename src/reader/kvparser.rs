@@ -0,0 +1,244 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The parser for the key-value text, for example env, sysctl -a, git config -l or logfmt logs
+ */
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use reader::linetext::{LineParser, Field};
+use reader::record::{RecordParser, LineKind};
+use ::table::schema::TableSchema;
+use ::schema::infer::normalize_column_name;
+
+/**
+ * @brief The key-value parser, the fields are picked by the key rather than the position.
+ *        A key matches the column with the same name, or the normalized name, e.g. the key
+ *        net.ipv4.ip_forward matches the column net_ipv4_ip_forward. The keys that are not in
+ *        the line are Nothing.
+ *
+ *        The parser can also be used as a record parser, which pivots the one-pair-per-line
+ *        input into one row per record. A record ends at a blank line, or when a key that is
+ *        already in the record appears again.
+ **/
+#[allow(dead_code)]
+pub struct KeyValueParser {
+    /// The characters seperating the key and the value
+    delim      : HashSet<char>,
+    /// If a line has many key=value tokens seperated by blanks, like logfmt, otherwise the
+    /// whole line is a single pair and the value is the rest of the line
+    tokens     : bool,
+    /// The keys in the lines of the current record we have looked at, so that each line is
+    /// split only once when the record is pivoted
+    seen_keys  : RefCell<HashSet<String>>,
+    /// The number of lines of the current record whose keys are in seen_keys
+    seen_lines : Cell<usize>
+}
+
+impl KeyValueParser {
+    /**
+     * @brief Create a new key-value parser which treats each line as a single pair
+     * @param delim The characters seperating the key and the value
+     * @return The newly created parser
+     **/
+    #[allow(dead_code)]
+    pub fn create(delim:&str) -> KeyValueParser
+    {
+        return KeyValueParser {
+            delim      : delim.chars().collect(),
+            tokens     : false,
+            seen_keys  : RefCell::new(HashSet::new()),
+            seen_lines : Cell::new(0)
+        };
+    }
+
+    /**
+     * @brief Set if the line has many key=value tokens, the value can be double quoted
+     *        if it contains blanks, e.g. level=info msg="hello world"
+     * @param value If the line is tokenized
+     * @return The parser itself
+     **/
+    #[allow(dead_code)]
+    pub fn tokens(&mut self, value:bool) -> &mut KeyValueParser
+    {
+        self.tokens = value;
+        return self;
+    }
+
+    /**
+     * @brief Read a double quoted value
+     * @param s The text after the opening quote
+     * @return The value and the remaining text
+     **/
+    fn parse_quoted(s:&str) -> (Cow<'_, str>, &str)
+    {
+        let mut value = String::new();
+        let mut chars = s.char_indices();
+
+        while let Some((pos, ch)) = chars.next()
+        {
+            match ch
+            {
+                '"'  => return (Cow::Owned(value), &s[pos + 1..]),
+                '\\' => if let Some((_, next)) = chars.next() { value.push(next); },
+                _    => value.push(ch)
+            }
+        }
+
+        return (Cow::Owned(value), "");
+    }
+
+    /**
+     * @brief Split the line into the key-value pairs
+     * @param line The line to split
+     * @return The list of pairs
+     **/
+    fn parse_pairs<'text>(&self, line:&'text str) -> Vec<(&'text str, Cow<'text, str>)>
+    {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        let mut ret = Vec::new();
+
+        if !self.tokens
+        {
+            if let Some(pos) = line.find(|c| self.delim.contains(&c))
+            {
+                let (key, value) = line.split_at(pos);
+                let value = &value[value.chars().next().map_or(0, |c| c.len_utf8())..];
+                ret.push((key.trim(), Cow::Borrowed(value.trim())));
+            }
+            return ret;
+        }
+
+        let mut to_parse = line;
+        loop
+        {
+            to_parse = to_parse.trim_start();
+
+            if to_parse.len() == 0
+            {
+                return ret;
+            }
+
+            let end = to_parse.find(|c:char| c.is_whitespace() || self.delim.contains(&c)).unwrap_or(to_parse.len());
+            let (key, rem) = to_parse.split_at(end);
+
+            let sep = match rem.chars().next()
+            {
+                Some(c) if self.delim.contains(&c) => c,
+                _                                  => {
+                    /* A bare key without value, we just ignore it */
+                    to_parse = rem;
+                    continue;
+                }
+            };
+
+            let rem = &rem[sep.len_utf8()..];
+
+            if let Some(quoted) = rem.strip_prefix('"')
+            {
+                let (value, next) = KeyValueParser::parse_quoted(quoted);
+                ret.push((key, value));
+                to_parse = next;
+            }
+            else
+            {
+                let end = rem.find(char::is_whitespace).unwrap_or(rem.len());
+                ret.push((key, Cow::Borrowed(&rem[..end])));
+                to_parse = &rem[end..];
+            }
+        }
+    }
+
+    /**
+     * @brief Find the column for the key
+     * @param key The key
+     * @param schema The table schema
+     * @return The column index or None if the key is not in the schema
+     **/
+    fn find_column(key:&str, schema:&TableSchema) -> Option<usize>
+    {
+        if let Some(idx) = schema.types.iter().position(|&(ref name, _)| name == key)
+        {
+            return Some(idx);
+        }
+
+        let normalized = normalize_column_name(key, 0);
+        return schema.types.iter().position(|&(ref name, _)| name == &normalized);
+    }
+
+    /**
+     * @brief Put the pairs in the lines into the fields, the first value of a key is used
+     * @param lines The lines
+     * @param schema The table schema
+     * @return The fields or None if no key matches any column
+     **/
+    fn collect_fields<'text, I>(&self, lines:I, schema:&TableSchema) -> Option<Vec<Field<'text>>>
+        where I: Iterator<Item = &'text str>
+    {
+        let mut ret:Vec<Field> = vec![None; schema.num_columns()];
+        let mut found = false;
+
+        for line in lines
+        {
+            for (key, value) in self.parse_pairs(line)
+            {
+                if let Some(idx) = KeyValueParser::find_column(key, schema)
+                {
+                    if ret[idx].is_none() && value.len() > 0
+                    {
+                        ret[idx] = Some(value);
+                        found = true;
+                    }
+                }
+            }
+        }
+
+        if !found
+        {
+            return None;
+        }
+
+        return Some(ret);
+    }
+}
+
+impl LineParser for KeyValueParser {
+    fn parse_next_line<'text>(&self, line:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        return self.collect_fields(Some(line).into_iter(), schema);
+    }
+}
+
+impl RecordParser for KeyValueParser {
+    fn classify_line(&self, line:&str, record:&[String]) -> LineKind
+    {
+        if line.trim().len() == 0
+        {
+            return LineKind::Separator;
+        }
+
+        let mut seen = self.seen_keys.borrow_mut();
+
+        /* The record only grows until it ends, and the next one starts with at most one line */
+        if record.len() <= 1 || record.len() < self.seen_lines.get()
+        {
+            seen.clear();
+            self.seen_lines.set(0);
+        }
+
+        for prev in &record[self.seen_lines.get()..]
+        {
+            seen.extend(self.parse_pairs(prev).into_iter().map(|(key, _)| key.to_string()));
+        }
+        self.seen_lines.set(record.len());
+
+        let repeated = self.parse_pairs(line).iter().any(|&(key, _)| seen.contains(key));
+
+        return if repeated { LineKind::Begin } else { LineKind::Continue };
+    }
+
+    fn parse_record<'text>(&self, lines:&'text [String], schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        return self.collect_fields(lines.iter().map(|line| &line[..]), schema);
+    }
+}
@@ -1,14 +1,21 @@
 /*
  * Copyright (C) 2018, Hao Hou
  */
+extern crate regex;
+
+use self::regex::Regex;
+use std::borrow::Cow;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Read;
 use std::mem::swap;
+use std::collections::VecDeque;
 use ::table::input::Input;
 use ::table::schema::{TableSchema, PrimitiveSchema};
 use ::table::row::Row;
 use ::table::primitive::PrimitiveValueT;
+use ::table::datetime::parse_datetime;
+use ::schema::infer::{infer_schema, probe_schema, schema_from_header, InferredSchema};
 
 /**
  * Represent the schema staus of this parser
@@ -29,12 +36,65 @@ impl SchemaStatus {
      **/
     fn should_determine_schema(&self) -> bool 
     {
-        match self 
+        return matches!(self, &SchemaStatus::Undeterminend);
+    }
+}
+
+/**
+ * A single field produced by the line parser, None means the value is missing
+ **/
+pub type Field<'text> = Option<Cow<'text, str>>;
+
+/**
+ * @brief Convert the text into the typed value of the column and put it into the row
+ * @param row The row to fill
+ * @param field_idx The column index
+ * @param field_str The text of the field
+ * @param schema The schema of the table
+ * @return If the text is valid for the column type
+ **/
+pub fn fill_field(row:&mut Row, field_idx:usize, field_str:&str, schema:&TableSchema) -> bool
+{
+    fn try_parse<T,U>(row:&mut Row, idx:usize, parse:&dyn Fn() -> Result<T,U>) -> bool
+        where T: PrimitiveValueT<T>
+    {
+        if let Ok(value) = parse() 
+        {
+            return row.set(idx, value) 
+        }
+        return false;
+    }
+
+    match schema.field_type(field_idx){
+        &PrimitiveSchema::Int    => try_parse(row, field_idx, &| | {field_str.parse::<i64>()}),
+        &PrimitiveSchema::Float  => try_parse(row, field_idx, &| | {field_str.parse::<f64>()}),
+        &PrimitiveSchema::Str    => try_parse::<String, ()>(row, field_idx, &| | {Ok(field_str.to_string())}),
+        &PrimitiveSchema::DateTime => try_parse(row, field_idx, &| | {parse_datetime(field_str).ok_or(())})
+    }
+}
+
+/**
+ * @brief Convert the fields into the typed values and put them into the row, the missing
+ *        fields are left as Nothing
+ * @param row The row to fill
+ * @param fields The fields produced by the parser
+ * @param schema The schema of the table
+ * @return If all the fields are valid for the column type
+ **/
+pub fn fill_row(row:&mut Row, fields:&[Field], schema:&TableSchema) -> bool
+{
+    for (field_idx, field) in fields.iter().enumerate()
+    {
+        if let &Some(ref field_str) = field
         {
-            &SchemaStatus::Undeterminend   => true,
-            _                              => false
+            if !fill_field(row, field_idx, field_str, schema)
+            {
+                return false;
+            }
         }
     }
+
+    return true;
 }
 
 pub trait LineParser {
@@ -44,7 +104,14 @@ pub trait LineParser {
      * @param schema The schema we are using for current table
      * @return The parsed vector or None
      **/
-    fn parse_next_line<'text, 'schema>(&self, s:&'text String, schema:&'schema TableSchema) -> Option<Vec<&'text str>>;
+    fn parse_next_line<'text>(&self, s:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>;
+
+    /**
+     * @brief Look at the header line before it's parsed, so that the parser can learn the
+     *        layout of the table, for example the column positions
+     * @param header The header line
+     **/
+    fn learn_header(&mut self, _header:&str) {}
 }
 
 /**
@@ -57,7 +124,16 @@ pub struct LineTextReader<T:Read, P: LineParser> {
     /// The schema 
     schema   : SchemaStatus,
     /// The line parser
-    parser   : P
+    parser   : P,
+    /// The lines we have read ahead for the schema inference but not parsed yet
+    pending  : VecDeque<String>,
+    /// The inferred schema if the schema is guessed from the input
+    inferred : Option<InferredSchema>,
+    /// The header line, the commands like vmstat print it again every few rows and the
+    /// copies are skipped
+    header   : Option<String>,
+    /// The lines matching any of the regexes are skipped
+    ignore   : Vec<Regex>
 }
 
 impl <T:Read, P: LineParser> LineTextReader<T, P> {
@@ -68,7 +144,7 @@ impl <T:Read, P: LineParser> LineTextReader<T, P> {
     fn parse_schema_from_input(&mut self) -> bool
     {
         let mut schema_line = String::new();
-        if let Ok(_) = self.fp.read_line(&mut schema_line)
+        if self.fp.read_line(&mut schema_line).is_ok()
         {
             if let Some(schema) = TableSchema::from_spec(&schema_line) 
             {
@@ -90,6 +166,10 @@ impl <T:Read, P: LineParser> LineTextReader<T, P> {
         return LineTextReader {
             fp        : fp,
             parser    : parser,
+            pending   : VecDeque::new(),
+            inferred  : None,
+            header    : None,
+            ignore    : Vec::new(),
             schema    : SchemaStatus::Determined(TableSchema {
                 sort_keys : Vec::new(),
                 sorted    : false,
@@ -111,12 +191,15 @@ impl <T:Read, P: LineParser> LineTextReader<T, P> {
      * @param fp The file we want to read from
      * @return The newly created reader
      **/
-    #[allow(dead_code)]
     pub fn create_self_explain_parser(fp: BufReader<T>, parser:P) -> LineTextReader<T, P>
     {
         let ret = LineTextReader {
             fp         : fp,
             parser     : parser,
+            pending    : VecDeque::new(),
+            inferred   : None,
+            header     : None,
+            ignore     : Vec::new(),
             schema     : SchemaStatus::Undeterminend 
         };
         return ret;
@@ -128,28 +211,159 @@ impl <T:Read, P: LineParser> LineTextReader<T, P> {
      * @return The newly created parser
      **/
     #[allow(dead_code)]
-    pub fn create_parser(schema:&String, fp: BufReader<T>, parser:P) -> Option<LineTextReader<T, P>>
+    pub fn create_parser(schema:&str, fp: BufReader<T>, parser:P) -> Option<LineTextReader<T, P>>
     {
         if let Some(schema) = TableSchema::from_spec(schema)
         {
             return Some(LineTextReader {
                 fp         : fp,
                 parser     : parser,
+                pending    : VecDeque::new(),
+                inferred   : None,
+                header     : None,
+                ignore     : Vec::new(),
+                schema     : SchemaStatus::Determined(schema)
+            });
+        }
+        return None;
+    }
+    /**
+     * @brief Create a line text parser which guesses the schema from the first lines of the input.
+     *        The sample lines are still parsed as the table rows, except the header line
+     * @param fp The file pointer
+     * @param parser The line parser
+     * @param sample The number of lines we use for the inference
+     * @return The newly created parser or None if we can not guess the schema
+     **/
+    #[allow(dead_code)]
+    pub fn create_inferring_parser(mut fp: BufReader<T>, parser:P, sample:usize) -> Option<LineTextReader<T, P>>
+    {
+        let mut pending = VecDeque::<String>::new();
+        while pending.len() < sample
+        {
+            let mut line = String::new();
+            match fp.read_line(&mut line)
+            {
+                Ok(0) | Err(_) => break,
+                Ok(_)          => if line != "\n" { pending.push_back(line); }
+            }
+        }
+
+        let inferred = infer_schema(pending.make_contiguous(), &parser)?;
+
+        let header = if inferred.has_header { pending.pop_front() } else { None };
+
+        return Some(LineTextReader {
+            fp         : fp,
+            parser     : parser,
+            pending    : pending,
+            schema     : SchemaStatus::Determined(inferred.schema.clone()),
+            inferred   : Some(inferred),
+            header     : header,
+            ignore     : Vec::new()
+        });
+    }
+    /**
+     * @brief Create a line text parser whose columns come from the header line of the input.
+     *        The header names are normalized and the types are looked up from the rule schema
+     * @param rule The rule schema string, which gives the types of the known columns
+     * @param fp The file pointer
+     * @param parser The line parser
+     * @return The newly created parser or None if the rule or the header is invalid
+     **/
+    #[allow(dead_code)]
+    pub fn create_header_parser(rule:&str, mut fp: BufReader<T>, mut parser:P) -> Option<LineTextReader<T, P>>
+    {
+        let rule = TableSchema::from_spec(rule)?;
+
+        let mut header = String::new();
+        while { header.clear(); fp.read_line(&mut header).ok()? > 0 }
+        {
+            if header.trim().len() == 0
+            {
+                continue;
+            }
+
+            parser.learn_header(&header);
+
+            let schema = schema_from_header(&parser.parse_next_line(&header, &probe_schema())?, &rule);
+
+            return Some(LineTextReader {
+                fp         : fp,
+                parser     : parser,
+                pending    : VecDeque::new(),
+                inferred   : None,
+                header     : Some(header),
+                ignore     : Vec::new(),
                 schema     : SchemaStatus::Determined(schema)
             });
         }
+
         return None;
     }
+    /**
+     * @brief Skip the lines matching the regex, e.g. the banner lines a command prints again
+     *        and again when it keeps running
+     * @param re The regex
+     * @return The reader itself
+     **/
+    #[allow(dead_code)]
+    pub fn ignore_line(&mut self, re:Regex) -> &mut LineTextReader<T, P>
+    {
+        self.ignore.push(re);
+        return self;
+    }
+    /**
+     * @brief Check if the line should be skipped, which is either a copy of the header line
+     *        or a line matching any of the ignore regexes
+     * @param line The line to check
+     * @return The check result
+     **/
+    fn should_ignore(&self, line:&str) -> bool
+    {
+        let text = line.trim_end_matches(&['\r', '\n'][..]);
+
+        if let Some(ref header) = self.header
+        {
+            if text.trim() == header.trim()
+            {
+                return true;
+            }
+        }
+
+        return self.ignore.iter().any(|re| re.is_match(text));
+    }
+    /**
+     * @brief Get the schema we have guessed from the input
+     * @return The inferred schema or None if the schema is not inferred
+     **/
+    pub fn inferred_schema(&self) -> Option<&InferredSchema>
+    {
+        return self.inferred.as_ref();
+    }
+    /**
+     * @brief Read the next line, the pending lines are returned first
+     * @param line The buffer for the line
+     * @return The number of bytes we have read
+     **/
+    fn read_line(&mut self, line:&mut String) -> ::std::io::Result<usize>
+    {
+        if let Some(pending) = self.pending.pop_front()
+        {
+            *line = pending;
+            return Ok(line.len());
+        }
+        return self.fp.read_line(line);
+    }
 
 }
 
 impl <T:Read, P: LineParser> Input for LineTextReader<T, P> {
     fn determine_table_schema(&mut self) -> Option<TableSchema>
     {
-        if self.schema.should_determine_schema() && self.parse_schema_from_input()
+        if self.schema.should_determine_schema()
         {
-            self.schema = SchemaStatus::Passed;
-            return None;
+            self.parse_schema_from_input();
         }
 
         let mut ret = SchemaStatus::Passed;
@@ -165,51 +379,37 @@ impl <T:Read, P: LineParser> Input for LineTextReader<T, P> {
 
     fn parse_next_row<'a>(&mut self, schema:&'a TableSchema) -> Option<Row<'a>>
     {
-        fn try_parse<T,U>(row:&mut Row, idx:usize, parse:&Fn() -> Result<T,U>) -> bool
-            where T: PrimitiveValueT<T>
-        {
-            if let Ok(value) = parse() 
-            {
-                return row.set(idx, value) 
-            }
-            return false;
-        }
-
         let mut line = String::new();
         let mut row = Row::empty(schema);
-        while let Ok(size) = self.fp.read_line(&mut line)
+        loop
         {
-            if line == "\n" 
+            line.clear();
+
+            /* The input ends at the read error, e.g. the command has timed out */
+            match self.read_line(&mut line)
             {
-                continue;
+                Ok(0) | Err(_) => return None,
+                Ok(_)          => {}
             }
 
-            if size == 0
+            if line == "\n" || self.should_ignore(&line)
             {
-                return None;
+                continue;
             }
 
-            let mut invalid = false;
-
-            if let Some(result) = self.parser.parse_next_line(&line, schema)
+            /* If the parser finds nothing in this line, we skip it */
+            let valid = match self.parser.parse_next_line(&line, schema)
             {
-                for (field_idx, field_str) in result.iter().enumerate()
-                {
-                    if !match schema.field_type(field_idx){
-                        &PrimitiveSchema::Int    => try_parse(&mut row, field_idx, &| | {field_str.parse::<i64>()}),
-                        &PrimitiveSchema::Float  => try_parse(&mut row, field_idx, &| | {field_str.parse::<f64>()}),
-                        &PrimitiveSchema::Str    => try_parse::<String, ()>(&mut row, field_idx, &| | {Ok(field_str.to_string())})
-                    }
-                    {
-                        invalid = true;
-                    }
-                }
-            } 
+                Some(result) => fill_row(&mut row, &result, schema),
+                None         => false
+            };
 
-            if !invalid
+            if valid
             {
                 break;
             }
+
+            row = Row::empty(schema);
         }
         return Some(row);
     }
@@ -1,3 +1,13 @@
 pub mod linetext;
 pub mod svparser;
 pub mod exec;
+pub mod squeryparser;
+pub mod fixedwidth;
+pub mod record;
+pub mod kvparser;
+pub mod regparser;
+pub mod json;
+pub mod jsonreader;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,292 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The reader for the block structured text, where a record spans multiple lines,
+ * for example ip addr, systemctl show, git log or /proc/cpuinfo
+ */
+extern crate regex;
+
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Read;
+use std::mem::swap;
+use reader::linetext::{Field, fill_row};
+use ::table::input::Input;
+use ::table::schema::TableSchema;
+use ::table::row::Row;
+use ::schema::infer::normalize_column_name;
+
+use self::regex::Regex;
+
+/**
+ * @brief How a line is related to the record we are reading
+ **/
+#[derive(PartialEq)]
+pub enum LineKind {
+    /// The line belongs to the current record
+    Continue,
+    /// The line begins a new record
+    Begin,
+    /// The line ends the current record and it's not a part of any record
+    Separator
+}
+
+pub trait RecordParser {
+    /**
+     * @brief Decide how the line is related to the record we are reading
+     * @param line The line
+     * @param record The lines we have collected for the current record
+     * @return The line kind
+     **/
+    fn classify_line(&self, line:&str, record:&[String]) -> LineKind;
+
+    /**
+     * @brief Parse the lines of a record into a vector of fields
+     * @param lines The lines of the record
+     * @param schema The schema we are using for current table
+     * @return The parsed vector or None
+     **/
+    fn parse_record<'text>(&self, lines:&'text [String], schema:&TableSchema) -> Option<Vec<Field<'text>>>;
+}
+
+/**
+ * @brief How the lines are grouped into records
+ **/
+#[allow(dead_code)]
+pub enum RecordBoundary {
+    /// The records are seperated by the blank lines
+    Blank,
+    /// A record begins with a line that is not indented, the indented lines below belong to it
+    Indent,
+    /// A record begins with the line matching the regex
+    Start(Regex),
+    /// The lines matching the regex seperate the records
+    Separator(Regex)
+}
+
+/**
+ * @brief How the fields are extracted from the lines of a record
+ **/
+#[allow(dead_code)]
+pub enum FieldExtractor {
+    /// Each line is a key and a value seperated by any of the characters, e.g. "model name : xxx".
+    /// The key is normalized and matched against the column names
+    KeyValue(String),
+    /// The named groups of the regexes are matched against the column names
+    Regex(Vec<Regex>)
+}
+
+/**
+ * @brief The record parser that groups lines by the boundary and extracts fields with the
+ *        extractor, for the same column the first non-empty value in the record is used
+ **/
+#[allow(dead_code)]
+pub struct BlockParser {
+    /// How the lines are grouped
+    boundary  : RecordBoundary,
+    /// How the fields are extracted
+    extractor : FieldExtractor
+}
+
+impl BlockParser {
+    #[allow(dead_code)]
+    pub fn create(boundary:RecordBoundary, extractor:FieldExtractor) -> BlockParser
+    {
+        return BlockParser {
+            boundary  : boundary,
+            extractor : extractor
+        };
+    }
+}
+
+impl RecordParser for BlockParser {
+    fn classify_line(&self, line:&str, record:&[String]) -> LineKind
+    {
+        let blank = line.trim().len() == 0;
+        let text = line.trim_end_matches(&['\r', '\n'][..]);
+
+        match &self.boundary
+        {
+            &RecordBoundary::Blank             => if blank { LineKind::Separator } else { LineKind::Continue },
+            &RecordBoundary::Indent            => {
+                if blank
+                {
+                    LineKind::Separator
+                }
+                else if line.starts_with(&[' ', '\t'][..]) && record.len() > 0
+                {
+                    LineKind::Continue
+                }
+                else
+                {
+                    LineKind::Begin
+                }
+            },
+            &RecordBoundary::Start(ref re)     => if re.is_match(text) { LineKind::Begin } else { LineKind::Continue },
+            &RecordBoundary::Separator(ref re) => if re.is_match(text) { LineKind::Separator } else { LineKind::Continue }
+        }
+    }
+
+    fn parse_record<'text>(&self, lines:&'text [String], schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        let mut ret:Vec<Field> = vec![None; schema.num_columns()];
+
+        let mut assign = |name:&str, value:&'text str| {
+            if value.len() == 0
+            {
+                return;
+            }
+
+            if let Some(idx) = schema.types.iter().position(|&(ref col, _)| col == name)
+            {
+                if ret[idx].is_none()
+                {
+                    ret[idx] = Some(Cow::Borrowed(value));
+                }
+            }
+        };
+
+        for line in lines
+        {
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+
+            match &self.extractor
+            {
+                &FieldExtractor::KeyValue(ref delim) => {
+                    if let Some(pos) = line.find(|c| delim.contains(c))
+                    {
+                        let (key, value) = line.split_at(pos);
+                        let value = value[value.chars().next().map_or(0, |c| c.len_utf8())..].trim();
+                        assign(&normalize_column_name(key, 0), value);
+                    }
+                },
+                &FieldExtractor::Regex(ref patterns) => {
+                    for re in patterns
+                    {
+                        if let Some(caps) = re.captures(line)
+                        {
+                            for name in re.capture_names().flatten()
+                            {
+                                if let Some(value) = caps.name(name)
+                                {
+                                    assign(name, value.as_str());
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if ret.iter().all(|field| field.is_none())
+        {
+            return None;
+        }
+
+        return Some(ret);
+    }
+}
+
+/**
+ * @brief The reader that groups the lines into records and makes each record a row
+ **/
+pub struct RecordTextReader<T:Read, P:RecordParser> {
+    /// The file we want to read
+    fp     : BufReader<T>,
+    /// The schema, which is None once it has been passed out
+    schema : Option<TableSchema>,
+    /// The record parser
+    parser : P,
+    /// The line that begins the next record, which we have read ahead
+    next   : Option<String>
+}
+
+impl <T:Read, P:RecordParser> RecordTextReader<T, P> {
+    /**
+     * @brief Create a record reader
+     * @param schema The schema string
+     * @param fp The file pointer
+     * @param parser The record parser
+     * @return The newly created reader or None if the schema is invalid
+     **/
+    #[allow(dead_code)]
+    pub fn create_parser(schema:&str, fp: BufReader<T>, parser:P) -> Option<RecordTextReader<T, P>>
+    {
+        return Some(RecordTextReader {
+            fp     : fp,
+            schema : Some(TableSchema::from_spec(schema)?),
+            parser : parser,
+            next   : None
+        });
+    }
+
+    /**
+     * @brief Read the lines of the next record
+     * @return The lines, an empty vector means the end of input
+     **/
+    fn read_record(&mut self) -> Vec<String>
+    {
+        let mut record = Vec::<String>::new();
+
+        if let Some(line) = self.next.take()
+        {
+            record.push(line);
+        }
+
+        loop
+        {
+            let mut line = String::new();
+            match self.fp.read_line(&mut line)
+            {
+                Ok(0) | Err(_) => return record,
+                Ok(_)          => {}
+            }
+
+            match self.parser.classify_line(&line, &record)
+            {
+                LineKind::Continue  => record.push(line),
+                LineKind::Separator => if record.len() > 0 { return record; },
+                LineKind::Begin     => {
+                    if record.len() > 0
+                    {
+                        self.next = Some(line);
+                        return record;
+                    }
+                    record.push(line);
+                }
+            }
+        }
+    }
+}
+
+impl <T:Read, P:RecordParser> Input for RecordTextReader<T, P> {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        let mut ret = None;
+        swap(&mut ret, &mut self.schema);
+        return ret;
+    }
+
+    fn parse_next_row<'a>(&mut self, schema:&'a TableSchema) -> Option<Row<'a>>
+    {
+        loop
+        {
+            let record = self.read_record();
+
+            if record.len() == 0
+            {
+                return None;
+            }
+
+            if let Some(fields) = self.parser.parse_record(&record, schema)
+            {
+                let mut row = Row::empty(schema);
+                if fill_row(&mut row, &fields, schema)
+                {
+                    return Some(row);
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,52 @@
 /*
  * Copyright (C) 2018, Hao Hou
+ *
+ * The parser for the lines that can only be described by a regex, for example uptime,
+ * mount or ls -l
  */
+extern crate regex;
 
-// TODO
+use std::borrow::Cow;
+use reader::linetext::{LineParser, Field};
+use ::table::schema::TableSchema;
+
+use self::regex::Regex;
+
+/**
+ * @brief The regex parser, the fields are the named groups of the regex and a group fills
+ *        the column with the same name. The lines that don't match are skipped and the
+ *        groups that don't participate in the match are Nothing
+ **/
+pub struct RegexParser {
+    /// The regex with the named groups
+    regex : Regex
+}
+
+impl RegexParser {
+    /**
+     * @brief Create a new regex parser
+     * @param regex The regex with the named groups
+     * @return The newly created parser
+     **/
+    pub fn create(regex:Regex) -> RegexParser
+    {
+        return RegexParser {
+            regex : regex
+        };
+    }
+}
+
+impl LineParser for RegexParser {
+    fn parse_next_line<'text>(&self, line:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        let caps = self.regex.captures(line.trim_end_matches(&['\r', '\n'][..]))?;
+        let mut ret:Vec<Field> = vec![None; schema.num_columns()];
+
+        for (idx, &(ref name, _)) in schema.types.iter().enumerate()
+        {
+            ret[idx] = caps.name(name).map(|value| Cow::Borrowed(value.as_str()));
+        }
+
+        return Some(ret);
+    }
+}
@@ -0,0 +1,71 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The parser for the rows produced by the squery output, see writer::squerywriter
+ */
+use std::borrow::Cow;
+use reader::linetext::{LineParser, Field};
+use ::table::schema::TableSchema;
+
+/**
+ * @brief The parser for the squery format. Each field is seperated by exactly one tab,
+ *        the tab, line breaks and backslash in a field are escaped with backslash
+ *        and a missing value is written as \N
+ **/
+pub struct SqueryParser {}
+
+impl SqueryParser {
+    pub fn create() -> SqueryParser
+    {
+        return SqueryParser {};
+    }
+
+    /**
+     * @brief Unescape a single field
+     * @param field The field text
+     * @return The unescaped field
+     **/
+    fn unescape(field:&str) -> Field<'_>
+    {
+        if field == "\\N"
+        {
+            return None;
+        }
+
+        if !field.contains('\\')
+        {
+            return Some(Cow::Borrowed(field));
+        }
+
+        let mut ret = String::with_capacity(field.len());
+        let mut chars = field.chars();
+        while let Some(ch) = chars.next()
+        {
+            if ch != '\\'
+            {
+                ret.push(ch);
+                continue;
+            }
+
+            match chars.next()
+            {
+                Some('t') => ret.push('\t'),
+                Some('n') => ret.push('\n'),
+                Some('r') => ret.push('\r'),
+                Some(c)   => ret.push(c),
+                None      => ret.push('\\')
+            }
+        }
+
+        return Some(Cow::Owned(ret));
+    }
+}
+
+impl LineParser for SqueryParser {
+    fn parse_next_line<'text>(&self, line:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        let line = line.trim_end_matches(&['\n', '\r'][..]);
+
+        return Some(line.split('\t').take(schema.num_columns()).map(SqueryParser::unescape).collect());
+    }
+}
@@ -4,21 +4,28 @@
  * The parser that is used to parse the seperated value, for example CSV or TSV
  */
 use std::collections::HashSet;
-use reader::linetext::LineParser;
+use std::borrow::Cow;
+use reader::linetext::{LineParser, Field};
 use ::table::schema::TableSchema;
 
 #[allow(dead_code)]
 pub struct SepValParser {
     /// The set of field seperators
-    field_sep: HashSet<char>
+    field_sep: HashSet<char>,
+    /// If the last column takes the rest of the line verbatim
+    rest_of_line: bool,
+    /// The max number of splits, the field after the last split takes the rest of the line
+    max_split: Option<usize>
 }
 
 impl SepValParser {
     #[allow(dead_code)]
-    pub fn create(delim:&String) -> SepValParser 
+    pub fn create(delim:&str) -> SepValParser 
     {
         let mut ret = SepValParser {
-            field_sep : HashSet::<char>::new()
+            field_sep    : HashSet::<char>::new(),
+            rest_of_line : false,
+            max_split    : None
         };
 
         for ch in delim.chars()
@@ -28,51 +35,74 @@ impl SepValParser {
 
         return ret;
     }
+
+    /**
+     * @brief Make the last column of the schema take the rest of the line verbatim, instead
+     *        of the next field only, e.g. the args column of ps -eo pid,args
+     * @param value If we want to keep the rest of the line
+     * @return The parser itself
+     **/
+    #[allow(dead_code)]
+    pub fn rest_of_line(&mut self, value:bool) -> &mut SepValParser
+    {
+        self.rest_of_line = value;
+        return self;
+    }
+
+    /**
+     * @brief Limit the number of splits, so that a line is split into at most n + 1 fields
+     *        and the last one takes the rest of the line verbatim
+     * @param n The max number of splits, None for unlimited
+     * @return The parser itself
+     **/
+    #[allow(dead_code)]
+    pub fn max_split(&mut self, n:Option<usize>) -> &mut SepValParser
+    {
+        self.max_split = n;
+        return self;
+    }
 }
 
 
 impl LineParser for SepValParser {
 
-    fn parse_next_line<'text, 'schema>(&self, line:&'text String, schema:&'schema TableSchema) -> Option<Vec<&'text str>>
+    fn parse_next_line<'text>(&self, line:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>
     {
-        let mut ret = Vec::<&str>::new(); 
+        let mut ret = Vec::<Field>::new();
         let mut to_parse = &line[0..];
         let mut field_idx = 0;
-        while field_idx < schema.types.len() && to_parse.len() > 0
+
+        let (limit, keep_rest) = match self.max_split
+        {
+            Some(n) if n < schema.types.len() => (n + 1, true),
+            _                                 => (schema.types.len(), self.rest_of_line)
+        };
+
+        while field_idx < limit && to_parse.len() > 0
         {
-            while to_parse.len() > 0
+            to_parse = to_parse.trim_start_matches(|c| self.field_sep.contains(&c));
+
+            if to_parse.len() == 0
             {
-                let (head, tail) = to_parse.split_at(1);
-                if !self.field_sep.contains(&head.chars().next().unwrap())
-                {
-                    break;
-                }
-                to_parse = tail
+                break;
             }
 
-            let mut idx = 0;
-            let mut field = None as Option<&str>;
-            while to_parse.len() >= idx
+            if keep_rest && field_idx + 1 == limit
             {
-                let (first, rem) = to_parse.split_at(idx);
-                if rem.len() == 0 || self.field_sep.contains(&rem.chars().next().unwrap())
+                let rest = to_parse.trim_end_matches(&['\r', '\n'][..]);
+                if rest.len() > 0
                 {
-                    field = Some(first);
-                    to_parse = rem;
-                    break;
+                    ret.push(Some(Cow::Borrowed(rest)));
                 }
-                idx += 1
-            }
-
-            if let Some(field_value) = field
-            {
-                ret.push(field_value);
-                field_idx += 1;
-            }
-            else
-            {
                 break;
             }
+
+            let end = to_parse.find(|c| self.field_sep.contains(&c)).unwrap_or(to_parse.len());
+            let (field_value, rem) = to_parse.split_at(end);
+
+            ret.push(Some(Cow::Borrowed(field_value)));
+            field_idx += 1;
+            to_parse = rem;
         }
 
         return Some(ret);
@@ -0,0 +1,615 @@
+// Copyright (C) 2018, Hao Hou
+//
+// The reader tests, which parse the text in memory with each parser and check the rows
+extern crate regex;
+
+use std::io::{BufReader, Cursor};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+use ::reader::exec::{ExecReader, ExecOptions, ExecStatus};
+use ::reader::linetext::{LineTextReader, LineParser};
+use ::reader::svparser::SepValParser;
+use ::reader::fixedwidth::FixedWidthParser;
+use ::reader::record::{RecordTextReader, BlockParser, RecordBoundary, FieldExtractor};
+use ::reader::kvparser::KeyValueParser;
+use ::reader::json::{JsonValue, JsonError, parse_json};
+use ::reader::jsonreader::{JsonReader, JsonLayout};
+use ::table::input::Input;
+use ::table::primitive::PrimitiveData;
+
+use self::regex::Regex;
+
+/**
+ * @brief Read all the rows from the input
+ * @param input The input
+ * @return The text of each cell, None for the missing value
+ **/
+fn read_all(input:&mut dyn Input) -> Vec<Vec<Option<String>>>
+{
+    let schema = input.determine_table_schema().expect("no schema");
+    let mut rows = Vec::new();
+
+    while let Some(row) = input.parse_next_row(&schema)
+    {
+        rows.push((0..schema.num_columns()).map(|col| match row.value_at(col)
+        {
+            &PrimitiveData::Nothing() => None,
+            value                     => Some(value.to_human_readable())
+        }).collect());
+    }
+
+    return rows;
+}
+
+/**
+ * @brief Make the expected row
+ * @param cells The text of each cell, "-" for the missing value
+ * @return The row
+ **/
+fn row(cells:&[&str]) -> Vec<Option<String>>
+{
+    return cells.iter().map(|&cell| if cell == "-" { None } else { Some(cell.to_string()) }).collect();
+}
+
+/**
+ * @brief Read the lines with the line parser
+ * @param schema The schema specification
+ * @param text The input text
+ * @param parser The line parser
+ * @return The rows
+ **/
+fn lines<P:LineParser>(schema:&str, text:&str, parser:P) -> Vec<Vec<Option<String>>>
+{
+    let fp = BufReader::new(Cursor::new(text.as_bytes().to_vec()));
+    let mut reader = LineTextReader::create_parser(schema, fp, parser).expect("invalid schema");
+    return read_all(&mut reader);
+}
+
+#[test]
+fn fixed_width_ranges_are_validated()
+{
+    for spec in &["0-3 4-8 9-", "0-3,4-8", "  0-0\t1-  "]
+    {
+        assert!(FixedWidthParser::from_spec(spec).is_some(), "{} is rejected", spec);
+    }
+
+    for spec in &["", " , ", "5", "3-1", "a-3", "1-b", "1-2-3", "-4"]
+    {
+        assert!(FixedWidthParser::from_spec(spec).is_none(), "{} is accepted", spec);
+    }
+}
+
+#[test]
+fn fixed_width_ranges_count_the_characters()
+{
+    let parser = FixedWidthParser::from_spec("0-3 4-8 9-").unwrap();
+    let text = "abc 12   x y z\n\u{e9}\u{20ac}\u{fc} 7    \u{fc}\nab\n\nabc zz   x\n";
+
+    /* The short line has the missing values and the line with an invalid number is skipped */
+    assert_eq!(lines(".a:String .b:Int .c:String", text, parser), vec![
+        row(&["abc", "12", "x y z"]),
+        row(&["\u{e9}\u{20ac}\u{fc}", "7", "\u{fc}"]),
+        row(&["ab", "-", "-"])
+    ]);
+
+    /* The open range of a middle column ends at the next range, the extra ranges are ignored */
+    let parser = FixedWidthParser::from_spec("0- 4-6 7-").unwrap();
+    assert_eq!(lines(".a:String .b:String", "abc de fg\n", parser), vec![row(&["abc", "de"])]);
+}
+
+#[test]
+fn fixed_width_columns_are_learned_from_the_header()
+{
+    let text = "USER       PID  %CPU COMMAND\n\
+                root         1   0.0 /sbin/init splash\n\
+                averylongusername 2 1.5 bash -c x\n\
+                USER       PID  %CPU COMMAND\n\
+                bob         30  12.0\n";
+    let reader = || {
+        let fp = BufReader::new(Cursor::new(text.as_bytes().to_vec()));
+        LineTextReader::create_header_parser(".pid:Int .cpu_pct:Float", fp, FixedWidthParser::from_header()).unwrap()
+    };
+
+    let schema = reader().determine_table_schema().unwrap();
+    assert_eq!(schema.to_spec(), ".user:String .pid:Int .cpu_pct:Float .command:String");
+
+    /* The overflowed value pushes the cut to the next blank and the repeated header is skipped */
+    assert_eq!(read_all(&mut reader()), vec![
+        row(&["root", "1", "0", "/sbin/init splash"]),
+        row(&["averylongusername", "2", "1.5", "bash -c x"]),
+        row(&["bob", "30", "12", "-"])
+    ]);
+}
+
+/**
+ * @brief Create the seperated value parser
+ * @param delim The seperators
+ * @param rest If the last column takes the rest of the line
+ * @param max_split The max number of splits
+ * @return The parser
+ **/
+fn sepval(delim:&str, rest:bool, max_split:Option<usize>) -> SepValParser
+{
+    let mut parser = SepValParser::create(delim);
+    parser.rest_of_line(rest).max_split(max_split);
+    return parser;
+}
+
+#[test]
+fn seperators_can_be_any_character()
+{
+    /* The adjacent seperators are merged and the missing fields at the end are missing values */
+    let text = "\u{e9}t\u{e9}\u{2502}1\u{2502}\u{2502}x y\n\u{2502}\u{6f22}\u{2502}2\n";
+    assert_eq!(lines(".a:String .b:Int .c:String", text, sepval("\u{2502}\n", false, None)), vec![
+        row(&["\u{e9}t\u{e9}", "1", "x y"]),
+        row(&["\u{6f22}", "2", "-"])
+    ]);
+
+    /* The extra fields are ignored */
+    assert_eq!(lines(".a:String .b:String", "a  b\tc d\r\n", sepval(" \t\r\n", false, None)), vec![row(&["a", "b"])]);
+}
+
+#[test]
+fn rest_of_line_is_kept_verbatim()
+{
+    let text = "1  sh -c 'a  b'  \r\n2\n3   \n";
+    assert_eq!(lines(".pid:Int .args:String", text, sepval(" \t\r\n", true, None)), vec![
+        row(&["1", "sh -c 'a  b'  "]),
+        row(&["2", "-"]),
+        row(&["3", "-"])
+    ]);
+}
+
+#[test]
+fn max_split_limits_the_fields()
+{
+    let text = "a b c d\na\n";
+
+    /* The field after the last split takes the rest of the line */
+    assert_eq!(lines(".x:String .y:String .z:String", text, sepval(" \n", false, Some(1))), vec![
+        row(&["a", "b c d", "-"]),
+        row(&["a", "-", "-"])
+    ]);
+    assert_eq!(lines(".x:String .y:String .z:String", text, sepval(" \n", false, Some(0))), vec![
+        row(&["a b c d", "-", "-"]),
+        row(&["a", "-", "-"])
+    ]);
+
+    /* The limit that is not less than the number of columns has no effect */
+    assert_eq!(lines(".x:String .y:String", text, sepval(" \n", false, Some(2))), vec![row(&["a", "b"]), row(&["a", "-"])]);
+    assert_eq!(lines(".x:String .y:String", text, sepval(" \n", true, Some(5))), vec![row(&["a", "b c d"]), row(&["a", "-"])]);
+}
+
+/**
+ * @brief Read the records with the block parser
+ * @param schema The schema specification
+ * @param text The input text
+ * @param boundary How the lines are grouped
+ * @param extractor How the fields are extracted
+ * @return The rows
+ **/
+fn records(schema:&str, text:&str, boundary:RecordBoundary, extractor:FieldExtractor) -> Vec<Vec<Option<String>>>
+{
+    let fp = BufReader::new(Cursor::new(text.as_bytes().to_vec()));
+    let mut reader = RecordTextReader::create_parser(schema, fp, BlockParser::create(boundary, extractor)).expect("invalid schema");
+    return read_all(&mut reader);
+}
+
+#[test]
+fn blank_lines_seperate_the_key_value_records()
+{
+    let text = "\n\nprocessor\t: 0\nmodel name\t: Intel: Core\nprocessor : 9\nflags\t:\n\n\n\
+                flags : fpu\n\n\
+                processor\t: x\n\n\
+                unknown : 1\n\n\
+                processor\t: 1\r\nmodel name : Arm";
+
+    /* The first non-empty value of a key is used, the records with an invalid value are
+     * skipped, and the last record needs no line break */
+    assert_eq!(records(".processor:Int .model_name:String .flags:String", text, RecordBoundary::Blank, FieldExtractor::KeyValue(":".to_string())), vec![
+        row(&["0", "Intel: Core", "-"]),
+        row(&["-", "-", "fpu"]),
+        row(&["1", "Arm", "-"])
+    ]);
+
+    /* Any of the delimiters ends the key */
+    let text = "Name\u{ff1a}\u{592a}\u{90ce}\nage=3\n";
+    assert_eq!(records(".name:String .age:Int", text, RecordBoundary::Blank, FieldExtractor::KeyValue("\u{ff1a}=".to_string())), vec![
+        row(&["\u{592a}\u{90ce}", "3"])
+    ]);
+}
+
+#[test]
+fn indented_lines_belong_to_the_record_above()
+{
+    let text = "  orphan 9\n\
+                1: lo mtu 65536\n    inet 127.0.0.1/8\n\
+                2: eth0 mtu 1500\n    link/ether aa:bb\n    inet 10.0.0.2/24\n    inet 10.0.0.3/24\n\
+                3: wlan0 mtu 1500\n";
+    let extractor = FieldExtractor::Regex(vec![
+        Regex::new(r"^(?P<idx>\d+): (?P<name>\S+) mtu (?P<mtu>\d+)").unwrap(),
+        Regex::new(r"^\s+inet (?P<addr>\S+)").unwrap()
+    ]);
+
+    /* The indented line before any record begins its own record */
+    assert_eq!(records(".idx:Int .name:String .mtu:Int .addr:String", text, RecordBoundary::Indent, extractor), vec![
+        row(&["1", "lo", "65536", "127.0.0.1/8"]),
+        row(&["2", "eth0", "1500", "10.0.0.2/24"]),
+        row(&["3", "wlan0", "1500", "-"])
+    ]);
+}
+
+#[test]
+fn records_begin_or_end_at_the_matching_lines()
+{
+    let extractor = || FieldExtractor::Regex(vec![
+        Regex::new(r"^commit (?P<hash>\w+)").unwrap(),
+        Regex::new(r"^Author: (?P<author>.+)$").unwrap()
+    ]);
+
+    /* The lines before the first start line are a record of their own */
+    let text = "Author: nobody\ncommit abc\nAuthor: A\n\n    msg\ncommit def\n\nAuthor: B\n";
+    assert_eq!(records(".hash:String .author:String", text, RecordBoundary::Start(Regex::new("^commit ").unwrap()), extractor()), vec![
+        row(&["-", "nobody"]),
+        row(&["abc", "A"]),
+        row(&["def", "B"])
+    ]);
+
+    /* The seperator lines are not a part of any record, the regex is matched without the
+     * line break */
+    let text = "---\ncommit abc\n---\n---\ncommit def\nAuthor: B\n---\n";
+    assert_eq!(records(".hash:String .author:String", text, RecordBoundary::Separator(Regex::new("^-+$").unwrap()), extractor()), vec![
+        row(&["abc", "-"]),
+        row(&["def", "B"])
+    ]);
+}
+
+/**
+ * @brief Create the key-value parser
+ * @param delim The characters seperating the key and the value
+ * @param tokens If a line has many key=value tokens
+ * @return The parser
+ **/
+fn keyvalue(delim:&str, tokens:bool) -> KeyValueParser
+{
+    let mut parser = KeyValueParser::create(delim);
+    parser.tokens(tokens);
+    return parser;
+}
+
+#[test]
+fn key_value_lines_are_matched_by_the_key()
+{
+    /* The value takes the rest of the line, the lines without a known key are skipped */
+    let text = "net.ipv4.ip_forward = 1\nkernel.hostname=a=b\nno delimiter\nunknown = 3\nkernel.hostname =\r\n";
+    assert_eq!(lines(".net_ipv4_ip_forward:Int .kernel_hostname:String", text, keyvalue("=", false)), vec![
+        row(&["1", "-"]),
+        row(&["-", "a=b"])
+    ]);
+
+    let text = "Name\u{ff1a} \u{592a}\u{90ce} \n";
+    assert_eq!(lines(".name:String", text, keyvalue("\u{ff1a}", false)), vec![row(&["\u{592a}\u{90ce}"])]);
+}
+
+#[test]
+fn key_value_tokens_can_be_quoted()
+{
+    let text = "level=info msg=\"hello \\\"world\\\"\" bare n=1 n=2\n\
+                level=warn msg= n=x\n\
+                level=error msg=\"unterminated value\n";
+
+    /* The bare keys are ignored, the first value of a key is used, and the line with an
+     * invalid number is skipped */
+    assert_eq!(lines(".level:String .msg:String .n:Int", text, keyvalue("=", true)), vec![
+        row(&["info", "hello \"world\"", "1"]),
+        row(&["error", "unterminated value", "-"])
+    ]);
+}
+
+#[test]
+fn key_value_records_are_pivoted()
+{
+    /* A record ends at a blank line or a repeated key, the unknown keys are ignored and the
+     * record with an invalid number is skipped */
+    let text = "name: a\nsize: 1\nname: b\n\n\nsize: 2\nnote: x\n\nname: c\nsize: y\n";
+    let fp = BufReader::new(Cursor::new(text.as_bytes().to_vec()));
+    let mut reader = RecordTextReader::create_parser(".name:String .size:Int", fp, keyvalue(":", false)).unwrap();
+
+    assert_eq!(read_all(&mut reader), vec![
+        row(&["a", "1"]),
+        row(&["b", "-"]),
+        row(&["-", "2"])
+    ]);
+}
+
+#[test]
+fn key_value_records_of_any_length_are_pivoted()
+{
+    /* Each record starts over, even if it has as many lines as the previous one */
+    let pivot = |text:&str| {
+        let fp = BufReader::new(Cursor::new(text.as_bytes().to_vec()));
+        let mut reader = RecordTextReader::create_parser(".name:String .size:Int", fp, keyvalue(":", false)).unwrap();
+        read_all(&mut reader)
+    };
+
+    assert_eq!(pivot("name: a
+name: b
+size: 2
+name: c
+"), vec![
+        row(&["a", "-"]),
+        row(&["b", "2"]),
+        row(&["c", "-"])
+    ]);
+    assert_eq!(pivot("size: 1
+name: b
+size: 2
+name: c
+name: d
+"), vec![
+        row(&["b", "1"]),
+        row(&["c", "2"]),
+        row(&["d", "-"])
+    ]);
+
+    /* A long record is split at the first repeated key */
+    let text:String = (0..1000).map(|n| format!("k{}: {}\n", n, n)).collect::<String>() + "name: a\nname: b\n";
+    assert_eq!(pivot(&text), vec![row(&["a", "-"]), row(&["b", "-"])]);
+}
+
+/**
+ * @brief Parse the JSON text, which should be a single value
+ * @param text The JSON text
+ * @return The value in the compact JSON text
+ **/
+fn json(text:&str) -> Result<String, JsonError>
+{
+    let (value, used) = parse_json(text)?;
+    assert_eq!(used, text.len(), "{} is not consumed", text);
+    return Ok(value.to_json());
+}
+
+#[test]
+fn json_strings_are_unescaped()
+{
+    assert_eq!(json(r#""a\"b\\c\/d\b\f\n\r\t""#), Ok(r#""a\"b\\c/d\u0008\u000c\n\r\t""#.to_string()));
+    assert_eq!(json(r#""\u0041\u00e9\u6F22""#), Ok("\"A\u{e9}\u{6f22}\"".to_string()));
+
+    /* A surrogate pair is decoded into the one character outside the BMP */
+    match parse_json(r#""\ud83d\ude00""#)
+    {
+        Ok((JsonValue::Str(s), _)) => assert_eq!(s, "\u{1f600}"),
+        other                      => panic!("unexpected {:?}", other)
+    }
+
+    assert_eq!(json(r#""\ud83d""#), Err(JsonError::Invalid));
+    assert_eq!(json(r#""\ud83dA""#), Err(JsonError::Invalid));
+    assert_eq!(json(r#""\x""#), Err(JsonError::Invalid));
+    assert_eq!(json(r#""\u12g4""#), Err(JsonError::Invalid));
+}
+
+#[test]
+fn json_numbers_follow_the_grammar()
+{
+    for text in &["0", "-0", "1", "-12", "1.5", "0.25", "1e5", "1E+5", "-1.5e-3", "12345678901234567890"]
+    {
+        assert_eq!(json(text), Ok(text.to_string()));
+    }
+
+    for text in &["+1", "-.5", ".5", "1.e3", "1.a", "-a", "1e+a", "[1.]", "[1e+]", "[-]"]
+    {
+        assert_eq!(parse_json(text).map(|(value, _)| value.to_json()), Err(JsonError::Invalid), "{} is accepted", text);
+    }
+
+    /* The leading zero ends the number */
+    assert_eq!(parse_json("01").map(|(value, used)| (value.to_json(), used)), Ok(("0".to_string(), 1)));
+    assert_eq!(parse_json("[01]").map(|(value, _)| value.to_json()), Err(JsonError::Invalid));
+}
+
+#[test]
+fn json_incomplete_text_is_told_from_invalid_text()
+{
+    for text in &["", "  ", "\"abc", "\"a\\", "\"\\u00", "[1,", "{\"a\"", "{\"a\":", "[1", "tru", "-", "1.", "1e", "1e+", "{\"a\":1,"]
+    {
+        assert_eq!(parse_json(text).map(|(value, _)| value.to_json()), Err(JsonError::Incomplete), "{:?}", text);
+    }
+
+    for text in &["x", "[1 2]", "{1:2}", "{\"a\" 1}", "{\"a\":1 \"b\":2}", "nul!", "[1,]", "}"]
+    {
+        assert_eq!(parse_json(text).map(|(value, _)| value.to_json()), Err(JsonError::Invalid), "{:?}", text);
+    }
+}
+
+#[test]
+fn json_nesting_is_limited()
+{
+    let nested = |depth:usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+    assert_eq!(json(&nested(256)), Ok(nested(256)));
+    assert_eq!(json(&nested(257)), Err(JsonError::Invalid));
+    assert_eq!(parse_json(&"[{\"a\":".repeat(100000)).map(|(value, _)| value.to_json()), Err(JsonError::Invalid));
+}
+
+#[test]
+fn json_objects_keep_the_member_order()
+{
+    let (value, _) = parse_json(r#" {"b": [1, {"c": null}], "a": true, "d": "x"} "#).unwrap();
+
+    assert_eq!(value.to_json(), r#"{"b":[1,{"c":null}],"a":true,"d":"x"}"#);
+    assert_eq!(value.lookup("b.1.c").map(|value| value.to_json()), Some("null".to_string()));
+    assert_eq!(value.lookup("b.0").map(|value| value.to_json()), Some("1".to_string()));
+    assert!(value.lookup("b.2").is_none());
+    assert!(value.lookup("a.x").is_none());
+}
+
+/**
+ * @brief Read the records from the JSON text
+ * @param text The JSON text
+ * @param root The dotted path to the records
+ * @return The rows of .name:String .n:Int .tags:String
+ **/
+fn json_rows(text:&str, root:Option<&str>) -> Vec<Vec<Option<String>>>
+{
+    let layout = JsonLayout {
+        root  : root.map(|root| root.to_string()),
+        paths : vec![("tags".to_string(), "meta.tags".to_string())]
+    };
+    let fp = BufReader::new(Cursor::new(text.as_bytes().to_vec()));
+    let mut reader = JsonReader::create(".name:String .n:Int .tags:String", fp, layout).unwrap();
+
+    return read_all(&mut reader);
+}
+
+#[test]
+fn json_reader_splits_ndjson_and_arrays()
+{
+    let expected = vec![row(&["a", "1", "-"]), row(&["b", "-", "[\"x\"]"])];
+
+    let ndjson = "{\"name\":\"a\",\"n\":1}\n{\"name\":\"b\",\"n\":null,\"meta\":{\"tags\":[\"x\"]}}\n";
+    assert_eq!(json_rows(ndjson, None), expected);
+
+    let array = "[\n  {\"name\": \"a\", \"n\": 1},\n  {\n    \"name\": \"b\",\n    \"meta\": {\"tags\": [\"x\"]}\n  }\n]\n";
+    assert_eq!(json_rows(array, None), expected);
+
+    /* The values are not seperated by line breaks */
+    assert_eq!(json_rows("{\"name\":\"a\",\"n\":1} {\"name\":\"b\",\"meta\":{\"tags\":[\"x\"]}}", None), expected);
+
+    let rooted = "{\"items\": [{\"name\": \"a\", \"n\": 1}, {\"name\": \"b\", \"meta\": {\"tags\": [\"x\"]}}]}";
+    assert_eq!(json_rows(rooted, Some("items")), expected);
+}
+
+#[test]
+fn json_reader_ignores_brackets_in_strings()
+{
+    let text = "{\"name\":\"}{ [\\\"]\",\"n\":2}\n{\"name\":\"\\\\\",\"n\":3}\n";
+    assert_eq!(json_rows(text, None), vec![row(&["}{ [\"]", "2", "-"]), row(&["\\", "3", "-"])]);
+}
+
+#[test]
+fn json_reader_skips_invalid_values()
+{
+    /* The value that can not be converted to the column type is missing */
+    assert_eq!(json_rows("{\"name\":1,\"n\":\"x\"}", None), vec![row(&["1", "-", "-"])]);
+
+    /* The invalid value is skipped and the next value is still read */
+    let text = "{\"name\":\"a\",\"n\":1.}\n{\"name\":\"b\",\"n\":2}\n";
+    assert_eq!(json_rows(text, None), vec![row(&["b", "2", "-"])]);
+}
+
+/**
+ * @brief Run the shell command and read the rows of .line:String
+ * @param command The shell command
+ * @param limit The max number of rows we read before we close the output
+ * @param timeout How long the command is allowed to run
+ * @return The number of rows we have read and how the command has finished
+ **/
+fn run_shell(command:&str, limit:usize, timeout:Option<Duration>) -> (usize, ExecStatus)
+{
+    let options = ExecOptions {
+        timeout : timeout,
+        ..ExecOptions::default()
+    };
+    let mut reader = ExecReader::spawn_with("sh", &["-c", command], 0, &options, |br| {
+        LineTextReader::create_parser(".line:String", br, SepValParser::create("\n")).map(|lp| Box::new(lp) as Box<dyn Input>)
+    }).expect("sh can not be started");
+
+    let schema = reader.determine_table_schema().expect("no schema");
+    let mut count = 0;
+    while count < limit && reader.parse_next_row(&schema).is_some()
+    {
+        count += 1;
+    }
+
+    return (count, reader.finish());
+}
+
+#[cfg(unix)]
+#[test]
+fn exit_code_follows_the_shell()
+{
+    use std::os::unix::process::ExitStatusExt;
+
+    let status = |raw:Option<i32>, timed_out:bool| ExecStatus {
+        status      : raw.map(ExitStatus::from_raw),
+        timed_out   : timed_out,
+        broken_pipe : false,
+        stderr      : String::new()
+    };
+
+    assert!(status(Some(0), false).success());
+    assert_eq!(status(Some(0), false).exit_code(), 0);
+    assert_eq!(status(Some(3 << 8), false).exit_code(), 3);
+    assert_eq!(status(Some(9), false).exit_code(), 128 + 9);
+    assert_eq!(status(Some(13), false).exit_code(), 128 + 13);
+    assert_eq!(status(Some(9), true).exit_code(), 124);
+    assert!(!status(Some(0), true).success());
+    assert_eq!(status(None, false).exit_code(), 1);
+
+    let closed = ExecStatus { broken_pipe : true, ..status(Some(13), false) };
+    assert!(closed.success());
+    assert_eq!(closed.exit_code(), 0);
+}
+
+#[test]
+fn commands_are_killed_at_the_timeout()
+{
+    let begin = Instant::now();
+    let (count, status) = run_shell("echo a; exec sleep 10", usize::MAX, Some(Duration::from_millis(200)));
+
+    assert_eq!(count, 1);
+    assert!(status.timed_out);
+    assert_eq!(status.exit_code(), 124);
+    assert!(begin.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn broken_pipe_is_a_failure_only_if_we_have_read_all()
+{
+    /* We close the output before yes ends */
+    let (count, status) = run_shell("exec yes", 3, None);
+    assert_eq!(count, 3);
+    assert!(status.success(), "exit code {}", status.exit_code());
+
+    /* The earlier stage gets a broken pipe when head exits */
+    let (count, status) = run_shell("yes | head -n 2", usize::MAX, None);
+    assert_eq!(count, 2);
+    assert!(status.success(), "exit code {}", status.exit_code());
+
+    /* The output is read to the end, so the broken pipe is not caused by us */
+    let (count, status) = run_shell("echo a; kill -PIPE $$", usize::MAX, None);
+    assert_eq!(count, 1);
+    assert!(!status.success());
+    assert_eq!(status.exit_code(), 128 + 13);
+}
+
+#[test]
+fn commands_are_not_waited_for_after_we_stop_reading()
+{
+    /* sleep never writes, so it doesn't get the broken pipe and is killed */
+    let begin = Instant::now();
+    let (count, status) = run_shell("echo a; echo b; exec sleep 10", 1, None);
+    assert_eq!(count, 1);
+    assert!(status.success(), "exit code {}", status.exit_code());
+    assert!(begin.elapsed() < Duration::from_secs(5));
+
+    /* Neither is the reader that isn't finished */
+    let begin = Instant::now();
+    let reader = ExecReader::spawn_with("sh", &["-c", "exec sleep 10"], 0, &ExecOptions::default(), |br| {
+        LineTextReader::create_parser(".line:String", br, SepValParser::create("\n")).map(|lp| Box::new(lp) as Box<dyn Input>)
+    }).expect("sh can not be started");
+    drop(reader);
+    assert!(begin.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn failures_keep_the_exit_code_and_the_standard_error()
+{
+    let (count, status) = run_shell("echo a; echo oops >&2; exit 3", usize::MAX, None);
+    assert_eq!(count, 1);
+    assert!(!status.success());
+    assert_eq!(status.exit_code(), 3);
+    assert_eq!(status.stderr, "oops\n");
+
+    let (_, status) = run_shell("kill -TERM $$", usize::MAX, None);
+    assert_eq!(status.exit_code(), 128 + 15);
+}
@@ -0,0 +1,38 @@
+// Copyright (C) 2018, Hao Hou
+//
+// The rule files built into the binary, so the common commands work without any rule
+// directory. A rule file with the same name in the search path overrides the built-in one
+
+/// The built-in rule files, the program name and the content of <program>.yml
+pub const BUILTIN_RULES : &[(&str, &str)] = &[
+    ("df",      include_str!("../../data/df.yml")),
+    ("du",      include_str!("../../data/du.yml")),
+    ("env",     include_str!("../../data/env.yml")),
+    ("free",    include_str!("../../data/free.yml")),
+    ("id",      include_str!("../../data/id.yml")),
+    ("ip",      include_str!("../../data/ip.yml")),
+    ("last",    include_str!("../../data/last.yml")),
+    ("ls",      include_str!("../../data/ls.yml")),
+    ("lsblk",   include_str!("../../data/lsblk.yml")),
+    ("lsof",    include_str!("../../data/lsof.yml")),
+    ("mount",   include_str!("../../data/mount.yml")),
+    ("netstat", include_str!("../../data/netstat.yml")),
+    ("ps",      include_str!("../../data/ps.yml")),
+    ("ss",      include_str!("../../data/ss.yml")),
+    ("stat",    include_str!("../../data/stat.yml")),
+    ("uptime",  include_str!("../../data/uptime.yml")),
+    ("vmstat",  include_str!("../../data/vmstat.yml")),
+    ("w",       include_str!("../../data/w.yml")),
+    ("wc",      include_str!("../../data/wc.yml")),
+    ("who",     include_str!("../../data/who.yml"))
+];
+
+/**
+ * @brief Find the built-in rule file of the program
+ * @param program The program name
+ * @return The content of the rule file or None if there's no built-in rule
+ **/
+pub fn builtin_rule(program:&str) -> Option<&'static str>
+{
+    return BUILTIN_RULES.iter().find(|&&(name, _)| name == program).map(|&(_, content)| content);
+}
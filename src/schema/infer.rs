@@ -0,0 +1,301 @@
+// Copyright (C) 2018, Hao Hou
+//
+// Guess the table schema from the sample lines
+use ::reader::linetext::{LineParser, Field};
+use ::table::schema::{TableSchema, PrimitiveSchema};
+use ::table::datetime::parse_datetime;
+
+/// The max number of columns we are looking for when we probe the sample lines
+const MAX_PROBE_COLUMNS : usize = 256;
+
+/**
+ * The schema we have guessed from the sample lines
+ **/
+#[derive(Clone)]
+pub struct InferredSchema {
+    /// The guessed schema
+    pub schema : TableSchema,
+    /// If the first sample line is the header, which should be skipped
+    pub has_header : bool
+}
+
+impl InferredSchema {
+    /**
+     * @brief Format the inferred schema as a rule entry, so that the user can save it
+     *        to the rule file
+     * @param sepval The field seperators we used to parse the sample
+     * @return The rule text in YAML
+     **/
+    pub fn to_rule(&self, sepval:&str) -> String
+    {
+        let mut ret = String::from("-\n    arguments: .*\n");
+
+        ret.push_str(&format!("    schema: {}\n", self.schema.to_spec()));
+        ret.push_str(&format!("    sepval: \"{}\"\n", sepval.escape_default()));
+
+        if self.has_header
+        {
+            ret.push_str("    skip: 1\n");
+        }
+
+        return ret;
+    }
+}
+
+/**
+ * @brief Turn the header text into a name that can be used in the schema spec. The name is
+ *        lower cased, the percent sign becomes a _pct suffix and other punctuations become
+ *        underscores, for example %CPU => cpu_pct, Use% => use_pct, 1K-blocks => 1k_blocks
+ * @param text The header text
+ * @param idx The column index, which is used when the header text is empty
+ * @return The column name
+ **/
+pub fn normalize_column_name(text:&str, idx:usize) -> String
+{
+    let mut ret = String::new();
+    let mut percent = false;
+
+    for ch in text.trim().chars()
+    {
+        if ch.is_alphanumeric()
+        {
+            ret.extend(ch.to_lowercase());
+        }
+        else if ch == '%'
+        {
+            percent = true;
+        }
+        else if !ret.ends_with('_')
+        {
+            ret.push('_');
+        }
+    }
+
+    let mut ret = ret.trim_matches('_').to_string();
+
+    if percent
+    {
+        ret.push_str(if ret.len() > 0 { "_pct" } else { "pct" });
+    }
+
+    if ret.len() == 0
+    {
+        return format!("field_{}", idx);
+    }
+
+    return ret;
+}
+
+/**
+ * @brief Normalize all the names in the header line, the duplicated names get a serial
+ *        number suffix, for example time, time_2
+ * @param header The header fields
+ * @return The column names
+ **/
+fn header_names(header:&Vec<Field>) -> Vec<String>
+{
+    let mut names = Vec::<String>::new();
+    for (idx, field) in header.iter().enumerate()
+    {
+        let base = normalize_column_name(field.as_ref().map_or("", |text| &text[..]), idx);
+        let mut name = base.clone();
+        let mut serial = 1;
+        while names.contains(&name)
+        {
+            serial += 1;
+            name = format!("{}_{}", base, serial);
+        }
+        names.push(name);
+    }
+    return names;
+}
+
+/**
+ * @brief Build the table schema from the header line. The column types are looked up from
+ *        the rule schema by the normalized name, the columns not mentioned by the rule are
+ *        strings, and the rule columns missing from the header are dropped. So the rule still
+ *        works when the user reorders or selects the columns, e.g. ps -o pid,%cpu
+ * @param header The header fields
+ * @param rule The schema in the rule, which gives the type of each known column
+ * @return The table schema
+ **/
+pub fn schema_from_header(header:&Vec<Field>, rule:&TableSchema) -> TableSchema
+{
+    let types:Vec<(String, PrimitiveSchema)> = header_names(header).into_iter().map(|name| {
+        let ptype = match rule.types.iter().find(|&&(ref rule_name, _)| rule_name == &name)
+        {
+            Some(&(_, ref ptype)) => ptype.clone(),
+            None                  => PrimitiveSchema::Str
+        };
+        (name, ptype)
+    }).collect();
+
+    let sort_keys = rule.sort_keys.iter().filter_map(|&key| {
+        types.iter().position(|&(ref name, _)| name == &rule.types[key].0)
+    }).collect();
+
+    return TableSchema {
+        sort_keys : sort_keys,
+        sorted    : rule.sorted,
+        types     : types
+    };
+}
+
+/**
+ * @brief Create a schema that takes as many string columns as possible, which is used to
+ *        split a line before we know the actual schema
+ * @return The probe schema
+ **/
+pub fn probe_schema() -> TableSchema
+{
+    return TableSchema {
+        sort_keys : Vec::new(),
+        sorted    : false,
+        types     : (0..MAX_PROBE_COLUMNS).map(|i| (format!("field_{}", i), PrimitiveSchema::Str)).collect()
+    };
+}
+
+/**
+ * @brief Get the narrowest type that can hold the value
+ * @param value The value text
+ * @return The type
+ **/
+fn narrowest_type(value:&str) -> PrimitiveSchema
+{
+    if value.parse::<i64>().is_ok()
+    {
+        return PrimitiveSchema::Int;
+    }
+
+    if value.parse::<f64>().is_ok()
+    {
+        return PrimitiveSchema::Float;
+    }
+
+    if parse_datetime(value).is_some()
+    {
+        return PrimitiveSchema::DateTime;
+    }
+
+    return PrimitiveSchema::Str;
+}
+
+/**
+ * @brief Merge two column types into the narrowest type that can hold both
+ * @param a The first type
+ * @param b The second type
+ * @return The merged type
+ **/
+fn merge_type(a:PrimitiveSchema, b:PrimitiveSchema) -> PrimitiveSchema
+{
+    match (a, b)
+    {
+        (PrimitiveSchema::Int, PrimitiveSchema::Int)                => PrimitiveSchema::Int,
+        (PrimitiveSchema::Int, PrimitiveSchema::Float)     |
+        (PrimitiveSchema::Float, PrimitiveSchema::Int)     |
+        (PrimitiveSchema::Float, PrimitiveSchema::Float)            => PrimitiveSchema::Float,
+        (PrimitiveSchema::DateTime, PrimitiveSchema::DateTime)      => PrimitiveSchema::DateTime,
+        _                                                           => PrimitiveSchema::Str
+    }
+}
+
+/**
+ * @brief Check if the sample line looks like a header line, which means no field
+ *        in it is a number or a date time
+ * @param fields The fields of the line
+ * @return The check result
+ **/
+fn looks_like_header(fields:&Vec<Field>) -> bool
+{
+    return fields.len() > 0 && fields.iter().all(|field| match field {
+        &Some(ref text) => narrowest_type(text) == PrimitiveSchema::Str,
+        &None           => false
+    });
+}
+
+/**
+ * @brief Guess the table schema from the sample lines. The number of columns is the number of
+ *        fields in the header, or the max number of fields in a line if there's no header.
+ *        The type of each column is the narrowest one in Int, Float, DateTime and String that
+ *        can hold all the values in the column.
+ * @note The first line is treated as the header if it has no numbers and either some column
+ *       below is not a string or all the names in it are upper case, like "PID TTY TIME CMD"
+ * @param lines The sample lines
+ * @param parser The line parser we are using
+ * @return The inferred schema or None if no field is found in the sample
+ **/
+pub fn infer_schema<P:LineParser>(lines:&[String], parser:&P) -> Option<InferredSchema>
+{
+    let probe = probe_schema();
+
+    let parsed:Vec<Vec<Field>> = lines.iter().filter_map(|line| parser.parse_next_line(line, &probe)).collect();
+
+    if parsed.len() == 0
+    {
+        return None;
+    }
+
+    let column_types = |rows:&[Vec<Field>]| {
+        let mut ret = Vec::<Option<PrimitiveSchema>>::new();
+        for row in rows
+        {
+            for (idx, field) in row.iter().enumerate()
+            {
+                if ret.len() <= idx
+                {
+                    ret.resize_with(idx + 1, || None);
+                }
+
+                if let &Some(ref text) = field
+                {
+                    let this_type = narrowest_type(text);
+                    ret[idx] = Some(match ret[idx].take() {
+                        Some(prev) => merge_type(prev, this_type),
+                        None       => this_type
+                    });
+                }
+            }
+        }
+        return ret;
+    };
+
+    let mut has_header = false;
+
+    if parsed.len() > 1 && looks_like_header(&parsed[0])
+    {
+        let body_types = column_types(&parsed[1..]);
+        let typed_body = body_types.iter().any(|t| !matches!(t, &Some(PrimitiveSchema::Str) | &None));
+        let upper_case = parsed[0].iter().all(|field| match field {
+            &Some(ref text) => !text.chars().any(|c| c.is_lowercase()),
+            &None           => false
+        });
+        has_header = typed_body || upper_case;
+    }
+
+    let (names, body) = if has_header
+    {
+        (header_names(&parsed[0]), &parsed[1..])
+    }
+    else
+    {
+        let count = parsed.iter().map(|row| row.len()).max().unwrap_or(0);
+        ((0..count).map(|i| format!("field_{}", i)).collect(), &parsed[..])
+    };
+
+    if names.len() == 0
+    {
+        return None;
+    }
+
+    let mut types = column_types(body);
+    types.resize_with(names.len(), || None);
+
+    return Some(InferredSchema {
+        schema     : TableSchema {
+            sort_keys : Vec::new(),
+            sorted    : false,
+            types     : names.into_iter().zip(types).map(|(name, t)| (name, t.unwrap_or(PrimitiveSchema::Str))).collect()
+        },
+        has_header : has_header
+    });
+}
@@ -2,12 +2,30 @@
 //
 // The schema loader
 extern crate regex;
+extern crate yaml_rust;
 
 use ::reader::svparser::SepValParser;
+use ::reader::fixedwidth::FixedWidthParser;
+use ::reader::linetext::{LineParser, LineTextReader, Field};
+use ::reader::record::{RecordParser, RecordTextReader, LineKind, BlockParser, RecordBoundary, FieldExtractor};
+use ::reader::kvparser::KeyValueParser;
+use ::reader::regparser::RegexParser;
+use ::reader::jsonreader::{JsonReader, JsonLayout};
+use ::reader::exec::{ExitPolicy, ExecOptions};
+use ::schema::builtin::{BUILTIN_RULES, builtin_rule};
+use ::table::input::Input;
+use ::table::filter::Condition;
+use ::table::schema::TableSchema;
+use ::writer::textstyle::{Color, ColorHint};
 
-use std::fs::File;
+use std::fmt;
+use std::fs::{self, File};
 use std::path::Path;
-use std::io::Read;
+use std::io::{Read, BufReader};
+use std::time::Duration;
+
+use self::regex::Regex;
+use self::yaml_rust::{Yaml, YamlLoader};
 
 
 /**
@@ -18,12 +36,104 @@ pub struct SchemaManager{
     schema_path_list : Vec<String>
 }
 
+/**
+ * @brief Where a rule file comes from
+ **/
+#[derive(Clone, PartialEq, Debug)]
+pub enum RuleSource {
+    /// The rule file in the search path
+    File(String),
+    /// The rule file built into the binary
+    Builtin
+}
+
+impl fmt::Display for RuleSource {
+    fn fmt(&self, f:&mut fmt::Formatter) -> fmt::Result
+    {
+        match self
+        {
+            &RuleSource::File(ref path) => write!(f, "{}", path),
+            &RuleSource::Builtin        => write!(f, "built-in")
+        }
+    }
+}
+
+/**
+ * @brief A rule file, which is <program>.yml
+ **/
+pub struct RuleFile {
+    /// The program the rules are for
+    pub program : String,
+    /// Where the rule file comes from
+    pub source  : RuleSource,
+    /// The content of the rule file
+    pub content : String
+}
+
 /**
  * The line parser we are using to parse the result
  **/
 #[allow(dead_code)]
 pub enum Parser {
-    SepVal(SepValParser)
+    SepVal(SepValParser),
+    FixedWidth(FixedWidthParser),
+    KeyValue(KeyValueParser),
+    Regex(RegexParser)
+}
+
+impl LineParser for Parser {
+    fn parse_next_line<'text>(&self, s:&'text str, schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        match self
+        {
+            &Parser::SepVal(ref parser)     => parser.parse_next_line(s, schema),
+            &Parser::FixedWidth(ref parser) => parser.parse_next_line(s, schema),
+            &Parser::KeyValue(ref parser)   => parser.parse_next_line(s, schema),
+            &Parser::Regex(ref parser)      => parser.parse_next_line(s, schema)
+        }
+    }
+
+    fn learn_header(&mut self, header:&str)
+    {
+        match self
+        {
+            &mut Parser::SepVal(ref mut parser)     => parser.learn_header(header),
+            &mut Parser::FixedWidth(ref mut parser) => parser.learn_header(header),
+            &mut Parser::KeyValue(ref mut parser)   => parser.learn_header(header),
+            &mut Parser::Regex(ref mut parser)      => parser.learn_header(header)
+        }
+    }
+}
+
+/**
+ * The record parser we are using when a record spans multiple lines
+ **/
+#[allow(dead_code)]
+pub enum Records {
+    /// The records are grouped by the boundary and the fields are extracted from the lines
+    Block(BlockParser),
+    /// The key-value lines are pivoted into one row per record
+    Pivot(KeyValueParser)
+}
+
+impl RecordParser for Records {
+    fn classify_line(&self, line:&str, record:&[String]) -> LineKind
+    {
+        match self
+        {
+            &Records::Block(ref parser) => parser.classify_line(line, record),
+            &Records::Pivot(ref parser) => parser.classify_line(line, record)
+        }
+    }
+
+    fn parse_record<'text>(&self, lines:&'text [String], schema:&TableSchema) -> Option<Vec<Field<'text>>>
+    {
+        match self
+        {
+            &Records::Block(ref parser) => parser.parse_record(lines, schema),
+            &Records::Pivot(ref parser) => parser.parse_record(lines, schema)
+        }
+    }
 }
 
 /**
@@ -31,10 +141,355 @@ pub enum Parser {
  **/
 #[allow(dead_code)]
 pub struct SchemaQueryResult {
-    /// The schema of the command output
-    schema : String,
+    /// The schema of the command output. When header is true, this only gives the types of the
+    /// known columns and the actual columns come from the header line
+    pub schema : String,
     /// The line parser we should use
-    line_parser: Parser
+    pub line_parser: Parser,
+    /// How many lines we should skip before parsing
+    pub skip : usize,
+    /// If the column names come from the header line
+    pub header : bool,
+    /// The record parser if a record spans multiple lines, in this case the line parser is unused
+    pub record_parser : Option<Records>,
+    /// The JSON layout if the output is JSON, in this case the line parser is unused
+    pub json : Option<JsonLayout>,
+    /// What we should do if the command exits with a non-zero status
+    pub exit_policy : Option<ExitPolicy>,
+    /// How the command should be executed
+    pub exec : ExecOptions,
+    /// The effective arguments we should run the command with, after the rule has rewritten
+    /// the arguments the user typed
+    pub args : Vec<String>,
+    /// The lines that should be skipped, e.g. the banner vmstat prints again every few rows
+    pub ignore : Vec<Regex>,
+    /// The colour hints for the text table, e.g. the zombie processes in red
+    pub highlight : Vec<ColorHint>
+}
+
+impl SchemaQueryResult {
+    /**
+     * @brief Parse a rule entry in the rule file. The line parser is the fixed-width parser if
+     *        there's a fixed key, which is either "header" to learn the column positions from
+     *        the header line, or a list of character ranges like "0-8 9-15 16-", otherwise it's
+     *        the seperated value parser with the seperators in the sepval key. For the seperated
+     *        value parser, "rest: true" makes the last column take the rest of the line and
+     *        "maxsplit: n" splits the line at most n times. If there's a keyvalue key without a
+     *        record key, it's the key-value parser with the delimiters in the keyvalue key,
+     *        "tokens: true" for many pairs in a line and "pivot: true" for one row per record.
+     *        If there's a regex key, the columns are filled by the named groups of the regex
+     * @param rule The rule entry
+     * @param args The arguments the user typed
+     * @return The query result described by the rule, None if the rule is invalid
+     **/
+    pub fn from_rule(rule:&Yaml, args:&[&str]) -> Option<SchemaQueryResult>
+    {
+        let schema = rule["schema"].as_str()?;
+        let line_parser = match (rule["fixed"].as_str(), rule["sepval"].as_str())
+        {
+            (None, None) if rule["regex"].as_str().is_some() => {
+                Parser::Regex(RegexParser::create(Regex::new(rule["regex"].as_str()?).ok()?))
+            },
+            (None, _) if rule["keyvalue"].as_str().is_some() && rule["record"].is_badvalue() => {
+                Parser::KeyValue(SchemaQueryResult::keyvalue_parser_from_rule(rule))
+            },
+            (Some("header"), _) => Parser::FixedWidth(FixedWidthParser::from_header()),
+            (Some(ranges), _)   => Parser::FixedWidth(FixedWidthParser::from_spec(ranges)?),
+            (None, sep)         => {
+                let mut parser = SepValParser::create(sep.unwrap_or(" \t\r\n"));
+                parser.rest_of_line(rule["rest"].as_bool().unwrap_or(false))
+                      .max_split(rule["maxsplit"].as_i64().filter(|n| *n >= 0).map(|n| n as usize));
+                Parser::SepVal(parser)
+            }
+        };
+
+        return Some(SchemaQueryResult {
+            schema        : schema.to_string(),
+            line_parser   : line_parser,
+            skip          : rule["skip"].as_i64().filter(|n| *n >= 0).unwrap_or(0) as usize,
+            header        : rule["header"].as_bool().unwrap_or(false),
+            record_parser : SchemaQueryResult::record_parser_from_rule(rule)?,
+            json          : SchemaQueryResult::json_layout_from_rule(rule),
+            exit_policy   : match rule["on_error"].as_str()
+            {
+                Some(name) => Some(ExitPolicy::from_name(name)?),
+                None       => None
+            },
+            exec          : SchemaQueryResult::exec_options_from_rule(rule)?,
+            args          : SchemaQueryResult::rewrite_args(rule, args)?,
+            ignore        : match rule["ignore"].as_vec()
+            {
+                Some(patterns) => patterns.iter().map(|re| Regex::new(re.as_str()?).ok()).collect::<Option<Vec<_>>>()?,
+                None           => Vec::new()
+            },
+            highlight     : SchemaQueryResult::highlight_from_rule(rule)?
+        });
+    }
+
+    /**
+     * @brief Parse the colour hints in the rule entry. The highlight key is a mapping or a
+     *        list of mappings, each has a when key with the condition on a column, which is
+     *        written as the --where option, and a color key with the colour name, e.g.
+     *        "highlight: {when: state == Z, color: red}"
+     * @param rule The rule entry
+     * @return The colour hints or None if the settings are invalid
+     **/
+    fn highlight_from_rule(rule:&Yaml) -> Option<Vec<ColorHint>>
+    {
+        fn color_hint(value:&Yaml) -> Option<ColorHint>
+        {
+            return Some(ColorHint {
+                condition : Condition::parse(value["when"].as_str()?)?,
+                color     : Color::from_name(value["color"].as_str()?)?
+            });
+        }
+
+        match rule["highlight"]
+        {
+            Yaml::BadValue          => Some(Vec::new()),
+            Yaml::Array(ref values) => values.iter().map(color_hint).collect(),
+            ref value               => Some(vec![color_hint(value)?])
+        }
+    }
+
+    /**
+     * @brief Rewrite the arguments as the rule describes, so that the command produces the
+     *        output the rule can parse. The arguments matching any regex in the remove key
+     *        are dropped, then each entry in the replace key, which has a pattern and a with
+     *        key, substitutes the matching part of every argument. The argv key replaces the
+     *        whole argument list, and finally the flags in the prepend and append keys are
+     *        added, e.g. "prepend: [-P]" for df
+     * @param rule The rule entry
+     * @param args The arguments the user typed
+     * @return The effective arguments or None if the settings are invalid
+     **/
+    fn rewrite_args(rule:&Yaml, args:&[&str]) -> Option<Vec<String>>
+    {
+        fn string_list(value:&Yaml) -> Option<Vec<String>>
+        {
+            match value
+            {
+                &Yaml::BadValue          => Some(Vec::new()),
+                &Yaml::String(ref s)     => Some(vec![s.clone()]),
+                &Yaml::Array(ref values) => values.iter().map(|value| value.as_str().map(|s| s.to_string())).collect(),
+                _                        => None
+            }
+        }
+
+        let mut ret:Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+        let remove = string_list(&rule["remove"])?.iter().map(|re| Regex::new(&format!("^(?:{})$", re)).ok()).collect::<Option<Vec<_>>>()?;
+        ret.retain(|arg| !remove.iter().any(|re| re.is_match(arg)));
+
+        if let Some(replaces) = rule["replace"].as_vec()
+        {
+            for replace in replaces
+            {
+                let re = Regex::new(replace["pattern"].as_str()?).ok()?;
+                let with = replace["with"].as_str()?;
+                ret = ret.iter().map(|arg| re.replace_all(arg, with).into_owned()).collect();
+            }
+        }
+
+        if !rule["argv"].is_badvalue()
+        {
+            ret = string_list(&rule["argv"])?;
+        }
+
+        let mut prepend = string_list(&rule["prepend"])?;
+        prepend.append(&mut ret);
+        prepend.append(&mut string_list(&rule["append"])?);
+
+        return Some(prepend);
+    }
+
+    /**
+     * @brief Parse the execution settings in the rule entry. The env key maps the variable
+     *        names to the values, the locale key sets LC_ALL ("C" by default, "inherit" to keep
+     *        the locale of squery), the cwd key is the working directory, and the timeout and
+     *        read_timeout keys are in seconds
+     * @param rule The rule entry
+     * @return The execution options or None if the settings are invalid
+     **/
+    fn exec_options_from_rule(rule:&Yaml) -> Option<ExecOptions>
+    {
+        fn duration(value:&Yaml) -> Option<Option<Duration>>
+        {
+            let secs = match value
+            {
+                &Yaml::BadValue   => return Some(None),
+                &Yaml::Integer(n) => n as f64,
+                &Yaml::Real(_)    => value.as_f64()?,
+                _                 => return None
+            };
+
+            if secs < 0.0
+            {
+                return None;
+            }
+
+            return Some(Some(Duration::from_millis((secs * 1000.0) as u64)));
+        }
+
+        let mut ret = ExecOptions {
+            read_timeout : duration(&rule["read_timeout"])?,
+            timeout      : duration(&rule["timeout"])?,
+            cwd          : rule["cwd"].as_str().map(|cwd| cwd.to_string()),
+            ..Default::default()
+        };
+
+        match rule["locale"]
+        {
+            Yaml::BadValue                              => {},
+            Yaml::String(ref name) if name == "inherit" => ret.locale = None,
+            Yaml::String(ref name)                      => ret.locale = Some(name.clone()),
+            _                                           => return None
+        }
+
+        if let Some(env) = rule["env"].as_hash()
+        {
+            for (name, value) in env
+            {
+                let value = match value
+                {
+                    &Yaml::String(ref s) => s.clone(),
+                    &Yaml::Integer(n)    => n.to_string(),
+                    &Yaml::Real(ref s)   => s.clone(),
+                    &Yaml::Boolean(b)    => b.to_string(),
+                    _                    => return None
+                };
+                ret.env.push((name.as_str()?.to_string(), value));
+            }
+        }
+
+        return Some(ret);
+    }
+
+    /**
+     * @brief Create the reader described by the query result
+     * @param fp The input we want to read, the lines to skip should have been skipped already
+     * @return The reader or None if the schema is invalid
+     **/
+    pub fn create_reader<T:Read + 'static>(self, fp:BufReader<T>) -> Option<Box<dyn Input>>
+    {
+        if let Some(layout) = self.json
+        {
+            return Some(Box::new(JsonReader::create(&self.schema, fp, layout)?));
+        }
+
+        if let Some(parser) = self.record_parser
+        {
+            return Some(Box::new(RecordTextReader::create_parser(&self.schema, fp, parser)?));
+        }
+
+        let mut reader = match self.header
+        {
+            true  => LineTextReader::create_header_parser(&self.schema, fp, self.line_parser)?,
+            false => LineTextReader::create_parser(&self.schema, fp, self.line_parser)?
+        };
+
+        for re in self.ignore
+        {
+            reader.ignore_line(re);
+        }
+
+        return Some(Box::new(reader));
+    }
+
+    /**
+     * @brief Parse the JSON settings in the rule entry, which is enabled by "json: true".
+     *        The root key is the dotted path to the records and the paths key maps the
+     *        column names to the dotted paths of the values
+     * @param rule The rule entry
+     * @return The JSON layout or None if the output is not JSON
+     **/
+    fn json_layout_from_rule(rule:&Yaml) -> Option<JsonLayout>
+    {
+        if rule["json"].as_bool() != Some(true)
+        {
+            return None;
+        }
+
+        let mut layout = JsonLayout {
+            root  : rule["root"].as_str().map(|root| root.to_string()),
+            paths : Vec::new()
+        };
+
+        if let Some(paths) = rule["paths"].as_hash()
+        {
+            for (column, path) in paths
+            {
+                if let (Some(column), Some(path)) = (column.as_str(), path.as_str())
+                {
+                    layout.paths.push((column.to_string(), path.to_string()));
+                }
+            }
+        }
+
+        return Some(layout);
+    }
+
+    /**
+     * @brief Parse the multi-line record settings in the rule entry. The record key is either
+     *        "blank", "indent", "start: <regex>" or "separator: <regex>", and the fields are
+     *        extracted with the regexes with named groups in the extract key, or from the
+     *        key-value lines seperated by the characters in the keyvalue key (":" by default)
+     * @param rule The rule entry
+     * @return Some(None) if the rule is line based, None if the settings are invalid
+     **/
+    fn record_parser_from_rule(rule:&Yaml) -> Option<Option<Records>>
+    {
+        let boundary = match rule["record"].as_str()
+        {
+            None if rule["pivot"].as_bool() == Some(true) => {
+                return Some(Some(Records::Pivot(SchemaQueryResult::keyvalue_parser_from_rule(rule))));
+            },
+            None           => return Some(None),
+            Some("blank")  => RecordBoundary::Blank,
+            Some("indent") => RecordBoundary::Indent,
+            Some(other)    => {
+                if let Some(re) = other.strip_prefix("start:")
+                {
+                    RecordBoundary::Start(Regex::new(re.trim()).ok()?)
+                }
+                else if let Some(re) = other.strip_prefix("separator:")
+                {
+                    RecordBoundary::Separator(Regex::new(re.trim()).ok()?)
+                }
+                else
+                {
+                    return None;
+                }
+            }
+        };
+
+        let extractor = match rule["extract"].as_vec()
+        {
+            Some(patterns) => {
+                let mut regexes = Vec::new();
+                for pattern in patterns
+                {
+                    regexes.push(Regex::new(pattern.as_str()?).ok()?);
+                }
+                FieldExtractor::Regex(regexes)
+            },
+            None           => FieldExtractor::KeyValue(rule["keyvalue"].as_str().unwrap_or(":").to_string())
+        };
+
+        return Some(Some(Records::Block(BlockParser::create(boundary, extractor))));
+    }
+
+    /**
+     * @brief Create the key-value parser described by the rule entry
+     * @param rule The rule entry
+     * @return The key-value parser
+     **/
+    fn keyvalue_parser_from_rule(rule:&Yaml) -> KeyValueParser
+    {
+        let mut parser = KeyValueParser::create(rule["keyvalue"].as_str().unwrap_or("="));
+        parser.tokens(rule["tokens"].as_bool().unwrap_or(false));
+        return parser;
+    }
 }
 
 impl SchemaManager {
@@ -45,27 +500,161 @@ impl SchemaManager {
         };
     }
 
-    pub fn push_schema_path(&mut self, path:String) -> &mut SchemaManager 
+    pub fn push_schema_path(&mut self, path:String) -> &mut SchemaManager
     {
         self.schema_path_list.push(path);
         return self;
     }
 
-    pub fn query(&self, program : &str, _args : &[&str]) -> Option<SchemaQueryResult>
+    /**
+     * @brief Read the rule file in the search path
+     * @param path The path of the rule file
+     * @return The rule file or None if it can not be read
+     **/
+    fn read_rule_file(path:&Path) -> Option<RuleFile>
+    {
+        if !path.is_file()
+        {
+            return None;
+        }
+
+        let mut content = String::new();
+        File::open(path).ok()?.read_to_string(&mut content).ok()?;
+
+        return Some(RuleFile {
+            program : path.file_stem()?.to_str()?.to_string(),
+            source  : RuleSource::File(path.to_string_lossy().into_owned()),
+            content : content
+        });
+    }
+
+    /**
+     * @brief Get the rule files of the program in the order they are tried, the rule files in
+     *        the search path first, then the built-in rule file
+     * @param program The program name, or the path of the program, e.g. /bin/ps
+     * @return The rule files
+     **/
+    pub fn program_rule_files(&self, program:&str) -> Vec<RuleFile>
     {
-        for ref path in &self.schema_path_list 
+        /* Only the file name is used, so the path can not take us out of the rule directories */
+        let program = match Path::new(program).file_name().and_then(|name| name.to_str())
         {
-            let rule_path = String::new() + path + "/" +program + ".yml";
-            let path_obj = Path::new(&rule_path);
-            if path_obj.exists() && path_obj.is_file()
+            Some(name) => name,
+            None       => return Vec::new()
+        };
+
+        let mut ret:Vec<RuleFile> = self.schema_path_list.iter()
+            .filter_map(|path| SchemaManager::read_rule_file(&Path::new(path).join(program.to_string() + ".yml")))
+            .collect();
+
+        if let Some(content) = builtin_rule(program)
+        {
+            ret.push(RuleFile {
+                program : program.to_string(),
+                source  : RuleSource::Builtin,
+                content : content.to_string()
+            });
+        }
+
+        return ret;
+    }
+
+    /**
+     * @brief Get all the rule files, the rule files in each directory of the search path are
+     *        sorted by the program name, and the built-in rule files come last
+     * @return The rule files
+     **/
+    pub fn rule_files(&self) -> Vec<RuleFile>
+    {
+        let mut ret = Vec::new();
+
+        for path in &self.schema_path_list
+        {
+            let mut files:Vec<RuleFile> = match fs::read_dir(path)
             {
-                if let Ok(mut file) = File::open(&rule_path)
-                {
-                    let mut content = String::new();
+                Ok(entries) => entries.filter_map(|entry| entry.ok())
+                                      .map(|entry| entry.path())
+                                      .filter(|path| path.extension().is_some_and(|ext| ext == "yml"))
+                                      .filter_map(|path| SchemaManager::read_rule_file(&path))
+                                      .collect(),
+                Err(_)      => Vec::new()
+            };
 
-                    if let Ok(_) = file.read_to_string(&mut content)
+            files.sort_by(|a, b| a.program.cmp(&b.program));
+            ret.append(&mut files);
+        }
+
+        ret.extend(BUILTIN_RULES.iter().map(|&(program, content)| RuleFile {
+            program : program.to_string(),
+            source  : RuleSource::Builtin,
+            content : content.to_string()
+        }));
+
+        return ret;
+    }
+
+    /**
+     * @brief Find the rule for the command line and tell where it is. The rule file is
+     *        <path>/<program>.yml, which is a list of rules, and the first rule whose
+     *        arguments pattern matches the whole argument string is used. The rule files in
+     *        the search path are tried in order, then the built-in rule file of the program,
+     *        so a user rule file overrides the built-in one as long as it has a matching rule
+     * @param program The program name
+     * @param args The arguments
+     * @return The rule file, the index of the rule in the file and the query result, None if
+     *         there's no matching rule
+     **/
+    pub fn find(&self, program:&str, args:&[&str]) -> Option<(RuleFile, usize, SchemaQueryResult)>
+    {
+        for file in self.program_rule_files(program)
+        {
+            if let Some((idx, result)) = SchemaManager::match_rule_file(&file.content, args)
+            {
+                return Some((file, idx, result));
+            }
+        }
+
+        return None;
+    }
+
+    /**
+     * @brief Find the rule for the command line, see find for how the rule is found
+     * @param program The program name
+     * @param args The arguments
+     * @return The query result, which carries the schema and the effective arguments we
+     *         should run the command with, None if there's no matching rule
+     **/
+    pub fn query(&self, program : &str, args : &[&str]) -> Option<SchemaQueryResult>
+    {
+        return self.find(program, args).map(|(_, _, result)| result);
+    }
+
+    /**
+     * @brief Find the first matching rule in the rule file
+     * @param content The content of the rule file
+     * @param args The arguments
+     * @return The index of the rule in the file and the query result, or None
+     **/
+    fn match_rule_file(content:&str, args:&[&str]) -> Option<(usize, SchemaQueryResult)>
+    {
+        let docs = YamlLoader::load_from_str(content).ok()?;
+        let arg_str = args.join(" ");
+
+        for (idx, rule) in docs.iter().filter_map(|doc| doc.as_vec()).flat_map(|rules| rules.iter()).enumerate()
+        {
+            let pattern = match rule["arguments"].as_str()
+            {
+                Some(pattern) => pattern,
+                None          => continue
+            };
+
+            if let Ok(re) = Regex::new(&format!("^(?:{})$", pattern))
+            {
+                if re.is_match(&arg_str)
+                {
+                    if let Some(result) = SchemaQueryResult::from_rule(rule, args)
                     {
-                        /* TODO: parse the rule file */
+                        return Some((idx, result));
                     }
                 }
             }
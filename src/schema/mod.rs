@@ -1,3 +1,9 @@
 // Copyright (C) 2018, Hao Hou
 
 pub mod loader; 
+pub mod infer;
+pub mod builtin;
+pub mod validate;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,459 @@
+// Copyright (C) 2018, Hao Hou
+//
+// The rule tests, which parse the captured output of the commands with the rules in data/.
+// A fixture is data/fixtures/<program>/<case>.yml, which has the keys
+//
+//     args:    The arguments the user typed, a list of strings
+//     argv:    The effective arguments after the rule has rewritten them, optional
+//     stdout:  The captured output of the command with the effective arguments
+//     columns: The column names of the parsed table, optional
+//     rows:    The expected rows, each is a list of the values as squery prints them, and
+//              null for the missing values
+extern crate yaml_rust;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Cursor};
+use std::path::{Path, PathBuf};
+use ::schema::loader::{SchemaManager, SchemaQueryResult, RuleSource};
+use ::schema::builtin::BUILTIN_RULES;
+use ::schema::validate::{validate_rule, validate_rule_file};
+use ::schema::infer::infer_schema;
+use ::reader::svparser::SepValParser;
+use ::table::primitive::PrimitiveData;
+
+use self::yaml_rust::{Yaml, YamlLoader};
+
+/**
+ * @brief Get the directory of the rule files in the source tree
+ * @return The path
+ **/
+fn data_dir() -> PathBuf
+{
+    return Path::new(env!("CARGO_MANIFEST_DIR")).join("data");
+}
+
+/**
+ * @brief List the files in the directory with the extension, in name order
+ * @param dir The directory
+ * @param ext The extension
+ * @return The paths, empty if the directory doesn't exist
+ **/
+fn list_files(dir:&Path, ext:&str) -> Vec<PathBuf>
+{
+    let mut ret:Vec<PathBuf> = match fs::read_dir(dir)
+    {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(_)      => Vec::new()
+    };
+
+    ret.retain(|path| path.is_file() && path.extension().is_some_and(|e| e == ext));
+    ret.sort();
+
+    return ret;
+}
+
+/**
+ * @brief Convert the expected value in the fixture to the text squery prints
+ * @param value The YAML value
+ * @return The text or None for the missing value
+ **/
+fn expected_text(value:&Yaml) -> Result<Option<String>, String>
+{
+    match value
+    {
+        &Yaml::Null           => Ok(None),
+        &Yaml::String(ref s)  => Ok(Some(s.clone())),
+        &Yaml::Integer(i)     => Ok(Some(i.to_string())),
+        &Yaml::Real(ref s)    => Ok(Some(s.clone())),
+        &Yaml::Boolean(b)     => Ok(Some(b.to_string())),
+        _                     => Err(format!("unexpected value {:?}", value))
+    }
+}
+
+/**
+ * @brief Get a list of strings in the fixture
+ * @param value The YAML value
+ * @param key The key of the value, which is used in the error message
+ * @return The strings
+ **/
+fn string_list(value:&Yaml, key:&str) -> Result<Vec<String>, String>
+{
+    return value.as_vec()
+        .ok_or_else(|| format!("{} should be a list", key))?
+        .iter()
+        .map(|item| expected_text(item)?.ok_or_else(|| format!("{} has a null item", key)))
+        .collect();
+}
+
+/**
+ * @brief Run a fixture through the rules of the program
+ * @param program The program name
+ * @param fixture The content of the fixture file
+ * @return Nothing if the parsed table is expected, otherwise the reason
+ **/
+fn check_fixture(program:&str, fixture:&str) -> Result<(), String>
+{
+    let docs = YamlLoader::load_from_str(fixture).map_err(|e| format!("invalid fixture: {}", e))?;
+    let fixture = docs.first().ok_or("empty fixture")?;
+
+    let args = string_list(&fixture["args"], "args")?;
+    let args:Vec<&str> = args.iter().map(|arg| &arg[..]).collect();
+
+    let mut schema_man = SchemaManager::new();
+    schema_man.push_schema_path(data_dir().to_string_lossy().into_owned());
+
+    let query = schema_man.query(program, &args).ok_or_else(|| format!("no rule matches {:?}", args))?;
+
+    if !fixture["argv"].is_badvalue()
+    {
+        let argv = string_list(&fixture["argv"], "argv")?;
+        if argv != query.args
+        {
+            return Err(format!("the effective arguments are {:?}, expected {:?}", query.args, argv));
+        }
+    }
+
+    let stdout = fixture["stdout"].as_str().ok_or("stdout should be a string")?.to_string();
+    let mut br = BufReader::new(Cursor::new(stdout.into_bytes()));
+
+    /* The leading lines are skipped by the exec reader before the rule reads the output */
+    for _ in 0..query.skip
+    {
+        let _ = br.read_line(&mut String::new());
+    }
+
+    let mut input = query.create_reader(br).ok_or("the rule can not create the reader")?;
+    let schema = input.determine_table_schema().ok_or("the schema can not be determined")?;
+
+    if !fixture["columns"].is_badvalue()
+    {
+        let columns = string_list(&fixture["columns"], "columns")?;
+        let actual:Vec<&String> = schema.types.iter().map(|&(ref name, _)| name).collect();
+        if actual.iter().map(|name| &name[..]).ne(columns.iter().map(|name| &name[..]))
+        {
+            return Err(format!("the columns are {:?}, expected {:?}", actual, columns));
+        }
+    }
+
+    let expected = fixture["rows"].as_vec().ok_or("rows should be a list")?;
+    let mut count = 0;
+
+    while let Some(row) = input.parse_next_row(&schema)
+    {
+        let actual:Vec<Option<String>> = (0..schema.num_columns()).map(|col| match row.value_at(col)
+        {
+            &PrimitiveData::Nothing() => None,
+            value                     => Some(value.to_human_readable())
+        }).collect();
+
+        let expected_row = expected.get(count).ok_or_else(|| format!("unexpected row {} {:?}", count, actual))?;
+        let expected_row:Vec<Option<String>> = expected_row.as_vec()
+            .ok_or_else(|| format!("row {} should be a list", count))?
+            .iter()
+            .map(expected_text)
+            .collect::<Result<_, _>>()?;
+
+        if actual != expected_row
+        {
+            return Err(format!("row {} is {:?}, expected {:?}", count, actual, expected_row));
+        }
+
+        count += 1;
+    }
+
+    if count != expected.len()
+    {
+        return Err(format!("{} rows are parsed, expected {}", count, expected.len()));
+    }
+
+    return Ok(());
+}
+
+#[test]
+fn fixtures_are_parsed_as_expected()
+{
+    let mut failures = Vec::new();
+    let mut checked = 0;
+
+    for program in list_files(&data_dir(), "yml")
+    {
+        let name = program.file_stem().unwrap().to_string_lossy().into_owned();
+
+        for path in list_files(&data_dir().join("fixtures").join(&name), "yml")
+        {
+            let content = fs::read_to_string(&path).unwrap();
+            if let Err(reason) = check_fixture(&name, &content)
+            {
+                failures.push(format!("{}: {}", path.display(), reason));
+            }
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 0, "no fixture is found");
+    assert!(failures.is_empty(), "{} of {} fixtures failed:\n{}", failures.len(), checked, failures.join("\n"));
+}
+
+#[test]
+fn every_rule_file_has_fixtures()
+{
+    let missing:Vec<String> = list_files(&data_dir(), "yml").iter()
+        .map(|path| path.file_stem().unwrap().to_string_lossy().into_owned())
+        .filter(|name| list_files(&data_dir().join("fixtures").join(name), "yml").is_empty())
+        .collect();
+
+    assert!(missing.is_empty(), "the rule files without fixtures: {}", missing.join(", "));
+}
+
+#[test]
+fn builtin_rules_are_the_rule_files()
+{
+    for path in list_files(&data_dir(), "yml")
+    {
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let content = fs::read_to_string(&path).unwrap();
+
+        match BUILTIN_RULES.iter().find(|&&(program, _)| program == name)
+        {
+            Some(&(_, builtin)) => assert!(builtin == content, "the built-in rules of {} are out of date", name),
+            None                => panic!("{} is not a built-in rule file", name)
+        }
+    }
+
+    assert_eq!(BUILTIN_RULES.len(), list_files(&data_dir(), "yml").len());
+}
+
+#[test]
+fn rule_files_are_valid()
+{
+    for path in list_files(&data_dir(), "yml")
+    {
+        let errors = validate_rule_file(&fs::read_to_string(&path).unwrap());
+        assert!(errors.is_empty(), "{}:\n{}", path.display(), errors.join("\n"));
+    }
+}
+
+/**
+ * @brief Parse a single rule entry
+ * @param text The YAML text of the rule
+ * @return The rule
+ **/
+fn rule(text:&str) -> Yaml
+{
+    return YamlLoader::load_from_str(text).unwrap().remove(0);
+}
+
+#[test]
+fn highlight_is_a_mapping_or_a_list()
+{
+    let single = SchemaQueryResult::from_rule(&rule("{schema: .stat:String, highlight: {when: \"stat == Z\", color: red}}"), &[]).unwrap();
+    assert_eq!(single.highlight.len(), 1);
+    assert_eq!(single.highlight[0].condition.column(), "stat");
+    assert!(single.highlight[0].condition.check(&PrimitiveData::Str("Z".to_string())));
+    assert!(!single.highlight[0].condition.check(&PrimitiveData::Str("S".to_string())));
+
+    let list = SchemaQueryResult::from_rule(&rule("
+schema: .stat:String .cpu:Float
+highlight:
+    - {when: stat ~ ^Z, color: red}
+    - {when: cpu > 50, color: yellow}
+"), &[]).unwrap();
+    assert_eq!(list.highlight.len(), 2);
+    assert_eq!(list.highlight[1].condition.column(), "cpu");
+    assert!(list.highlight[1].condition.check(&PrimitiveData::Float(75.0)));
+
+    let none = SchemaQueryResult::from_rule(&rule("{schema: .stat:String}"), &[]).unwrap();
+    assert!(none.highlight.is_empty());
+}
+
+#[test]
+fn invalid_highlight_is_rejected()
+{
+    for text in &["{schema: .a:Int, highlight: {when: a, color: red}}",
+                  "{schema: .a:Int, highlight: {when: a > 1, color: purple}}",
+                  "{schema: .a:Int, highlight: {color: red}}",
+                  "{schema: .a:Int, highlight: [{when: a ~ (, color: red}]}"]
+    {
+        assert!(SchemaQueryResult::from_rule(&rule(text), &[]).is_none(), "{} is accepted", text);
+        assert!(!validate_rule(&rule(text)).is_empty(), "{} is valid", text);
+    }
+}
+
+#[test]
+fn negative_counts_are_rejected()
+{
+    for text in &["{arguments: .*, schema: .a:Int, skip: -1}",
+                  "{arguments: .*, schema: .a:Int .b:String, rest: true, maxsplit: -1}"]
+    {
+        assert_eq!(validate_rule(&rule(text)).len(), 1, "{} is valid", text);
+
+        /* The loader ignores them instead of wrapping around to a huge count */
+        let query = SchemaQueryResult::from_rule(&rule(text), &[]).unwrap();
+        assert_eq!(query.skip, 0);
+    }
+}
+
+/**
+ * @brief Rewrite the arguments with the rule
+ * @param text The YAML text of the rule, the schema key is added
+ * @param args The arguments the user typed
+ * @return The effective arguments or None if the rule is invalid
+ **/
+fn rewrite(text:&str, args:&[&str]) -> Option<Vec<String>>
+{
+    let rule = rule(&format!("{{schema: .a:String, {}}}", text));
+    return SchemaQueryResult::from_rule(&rule, args).map(|query| query.args);
+}
+
+#[test]
+fn arguments_are_kept_without_the_rewrite_keys()
+{
+    assert_eq!(rewrite("skip: 0", &["-a", "x y"]), Some(vec!["-a".to_string(), "x y".to_string()]));
+    assert_eq!(rewrite("skip: 0", &[]), Some(vec![]));
+}
+
+#[test]
+fn removed_arguments_match_the_whole_argument()
+{
+    /* The regex is anchored, so -ax is kept when -a is removed */
+    assert_eq!(rewrite("remove: -a", &["-a", "-ax", "b"]), Some(vec!["-ax".to_string(), "b".to_string()]));
+    assert_eq!(rewrite("remove: [-a, \"--format=.*\"]", &["--format=x", "-a", "c"]), Some(vec!["c".to_string()]));
+
+    /* The alternatives are grouped before the anchors are added */
+    assert_eq!(rewrite("remove: \"-a|-b\"", &["-a", "-b", "-ab"]), Some(vec!["-ab".to_string()]));
+
+    assert_eq!(rewrite("remove: \"(\"", &["-a"]), None);
+    assert_eq!(rewrite("remove: {a: b}", &["-a"]), None);
+}
+
+#[test]
+fn replaced_arguments_substitute_every_match()
+{
+    let text = "replace: [{pattern: \"^-h$\", with: \"-k\"}, {pattern: \"o\", with: \"0\"}]";
+    assert_eq!(rewrite(text, &["-h", "-hx", "foo"]), Some(vec!["-k".to_string(), "-hx".to_string(), "f00".to_string()]));
+
+    /* The replaces are applied in order, after the removes */
+    let text = "remove: -x, replace: [{pattern: a, with: x}, {pattern: x, with: y}]";
+    assert_eq!(rewrite(text, &["-x", "a"]), Some(vec!["y".to_string()]));
+
+    /* The groups can be used in the replacement */
+    let text = "replace: [{pattern: \"^--(\\\\w+)=(.*)$\", with: \"--$1 $2\"}]";
+    assert_eq!(rewrite(text, &["--sort=pid"]), Some(vec!["--sort pid".to_string()]));
+
+    assert_eq!(rewrite("replace: [{pattern: a}]", &["a"]), None);
+    assert_eq!(rewrite("replace: [{pattern: \"(\", with: b}]", &["a"]), None);
+}
+
+#[test]
+fn argv_prepend_and_append_build_the_final_arguments()
+{
+    /* The argv key replaces the arguments the user typed */
+    assert_eq!(rewrite("argv: [-o, pid]", &["-a"]), Some(vec!["-o".to_string(), "pid".to_string()]));
+    assert_eq!(rewrite("argv: []", &["-a"]), Some(vec![]));
+
+    let text = "prepend: -P, append: [--, x]";
+    assert_eq!(rewrite(text, &["-h"]), Some(vec!["-P".to_string(), "-h".to_string(), "--".to_string(), "x".to_string()]));
+
+    /* The flags are added around the argv */
+    let text = "argv: [-o, pid], prepend: [-e]";
+    assert_eq!(rewrite(text, &["-a"]), Some(vec!["-e".to_string(), "-o".to_string(), "pid".to_string()]));
+
+    assert_eq!(rewrite("prepend: [[-P]]", &[]), None);
+    assert_eq!(rewrite("argv: 1", &[]), None);
+}
+
+/**
+ * @brief Guess the schema of the sample text with the whitespace seperated value parser
+ * @param text The sample text
+ * @return The schema specification and if the first line is the header
+ **/
+fn infer(text:&str) -> Option<(String, bool)>
+{
+    let lines:Vec<String> = text.lines().map(|line| format!("{}\n", line)).collect();
+    let inferred = infer_schema(&lines, &SepValParser::create(" \t\r\n"))?;
+    return Some((inferred.schema.to_spec(), inferred.has_header));
+}
+
+#[test]
+fn inferred_types_are_the_narrowest_for_all_values()
+{
+    let (spec, _) = infer("1 1 2018-01-02 1 1 x\n2 2.5 2018-01-03T10:00:00Z 2018-01-02 x 2\n").unwrap();
+    assert_eq!(spec, ".field_0:Int .field_1:Float .field_2:DateTime .field_3:String .field_4:String .field_5:String");
+
+    /* The integers that don't fit in 64 bits are still numbers */
+    let (spec, _) = infer("1\n99999999999999999999\n").unwrap();
+    assert_eq!(spec, ".field_0:Float");
+}
+
+#[test]
+fn header_line_is_detected()
+{
+    /* The names are upper case */
+    assert_eq!(infer("PID TTY CMD\n1 ? init\n").unwrap(), (".pid:Int .tty:String .cmd:String".to_string(), true));
+    assert_eq!(infer("USER COMMAND\nroot init\n").unwrap(), (".user:String .command:String".to_string(), true));
+
+    /* Some column below is not a string */
+    assert_eq!(infer("name size\nfoo 1\nbar 2\n").unwrap(), (".name:String .size:Int".to_string(), true));
+
+    /* The lower case words above the strings are data */
+    assert_eq!(infer("foo bar\nbaz qux\n").unwrap(), (".field_0:String .field_1:String".to_string(), false));
+
+    /* A line with numbers is never the header, and a single line is the data */
+    assert_eq!(infer("A 1\nB 2\n").unwrap(), (".field_0:String .field_1:Int".to_string(), false));
+    assert_eq!(infer("PID TTY\n").unwrap(), (".field_0:String .field_1:String".to_string(), false));
+
+    /* The duplicated and punctuated names are normalized */
+    assert_eq!(infer("%CPU TIME TIME\n1.5 1 2\n").unwrap(), (".cpu_pct:Float .time:Int .time_2:Int".to_string(), true));
+}
+
+#[test]
+fn ragged_lines_are_inferred()
+{
+    /* Without the header, the widest line decides the number of columns */
+    assert_eq!(infer("1 a\n2 b 3\n4\n").unwrap().0, ".field_0:Int .field_1:String .field_2:Int");
+
+    /* With the header, the extra fields are dropped and the missing columns are strings */
+    assert_eq!(infer("A B C\n1 2 3 4\n5\n").unwrap().0, ".a:Int .b:Int .c:Int");
+    assert_eq!(infer("A B C\n1\n2\n").unwrap().0, ".a:Int .b:String .c:String");
+
+    assert!(infer("\n  \n").is_none());
+    assert!(infer("").is_none());
+}
+
+#[test]
+fn inferred_rule_can_be_loaded()
+{
+    let lines:Vec<String> = vec!["NAME SIZE\n".to_string(), "foo 1.5\n".to_string()];
+    let inferred = infer_schema(&lines, &SepValParser::create(" \t\r\n")).unwrap();
+    let text = inferred.to_rule(" \t\r\n");
+
+    let errors = validate_rule_file(&text);
+    assert!(errors.is_empty(), "{}\n{}", text, errors.join("\n"));
+
+    let rules = YamlLoader::load_from_str(&text).unwrap();
+    let query = SchemaQueryResult::from_rule(&rules[0][0], &[]).unwrap();
+    assert_eq!(query.schema, ".name:String .size:Float");
+    assert_eq!(query.skip, 1);
+}
+
+#[test]
+fn rule_files_are_found_by_the_program_file_name()
+{
+    /* data/ps.yml is right outside the fixture directory */
+    let mut schema_man = SchemaManager::new();
+    schema_man.push_schema_path(data_dir().join("fixtures").to_string_lossy().into_owned());
+
+    for program in &["ps", "/bin/ps", "./ps", "../ps"]
+    {
+        let files = schema_man.program_rule_files(program);
+        assert_eq!(files.len(), 1, "{}", program);
+        assert_eq!(files[0].program, "ps");
+        assert_eq!(files[0].source, RuleSource::Builtin);
+        assert!(schema_man.query(program, &["aux"]).is_some(), "{}", program);
+    }
+
+    for program in &["..", "/", ""]
+    {
+        assert!(schema_man.program_rule_files(program).is_empty(), "{}", program);
+    }
+}
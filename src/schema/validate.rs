@@ -0,0 +1,414 @@
+// Copyright (C) 2018, Hao Hou
+//
+// Check the rule files and tell what is wrong with each rule, the loader just skips the
+// invalid rules, which makes a typo in the rule file hard to find
+extern crate regex;
+extern crate yaml_rust;
+
+use ::reader::fixedwidth::FixedWidthParser;
+use ::reader::exec::ExitPolicy;
+use ::schema::loader::SchemaQueryResult;
+use ::table::filter::Condition;
+use ::table::schema::TableSchema;
+use ::writer::textstyle::Color;
+
+use self::regex::Regex;
+use self::yaml_rust::{Yaml, YamlLoader};
+
+/// The keys a rule entry can have
+const RULE_KEYS : [&str; 30] = [
+    "arguments", "schema", "skip", "header", "on_error",
+    "fixed", "sepval", "rest", "maxsplit", "keyvalue", "tokens", "pivot", "regex",
+    "record", "extract", "json", "root", "paths", "ignore",
+    "remove", "replace", "argv", "prepend", "append",
+    "env", "locale", "cwd", "timeout", "read_timeout", "highlight"
+];
+
+/**
+ * @brief Describe the YAML value for the error message
+ * @param value The value
+ * @return The description
+ **/
+fn describe(value:&Yaml) -> &'static str
+{
+    match value
+    {
+        &Yaml::Real(_)    => "a float",
+        &Yaml::Integer(_) => "an integer",
+        &Yaml::String(_)  => "a string",
+        &Yaml::Boolean(_) => "a boolean",
+        &Yaml::Array(_)   => "a list",
+        &Yaml::Hash(_)    => "a mapping",
+        _                 => "null"
+    }
+}
+
+/**
+ * @brief Indent the following lines of a multi-line message, e.g. a regex syntax error
+ * @param message The message
+ * @return The indented message
+ **/
+fn indent(message:&str) -> String
+{
+    return message.lines().collect::<Vec<_>>().join("\n    ");
+}
+
+/**
+ * @brief Find out which column in the schema specification is invalid
+ * @param spec The schema specification
+ * @return The error message
+ **/
+fn schema_error(spec:&str) -> String
+{
+    for token in spec.split_whitespace().filter(|token| token.starts_with('.'))
+    {
+        if TableSchema::from_spec(token).is_none()
+        {
+            return format!("invalid column {} in the schema, it should be .name:Int, .name:Float, .name:String or .name:DateTime", token);
+        }
+    }
+
+    return format!("invalid schema \"{}\"", spec);
+}
+
+/**
+ * @brief The checker of a rule entry, which collects the errors
+ **/
+struct RuleChecker<'a> {
+    /// The rule entry
+    rule   : &'a Yaml,
+    /// The errors found so far
+    errors : Vec<String>
+}
+
+impl <'a> RuleChecker<'a> {
+    /**
+     * @brief Check the regex under the key
+     * @param key The key, which is used in the error message
+     * @param pattern The regex
+     **/
+    fn regex(&mut self, key:&str, pattern:&str)
+    {
+        if let Err(e) = Regex::new(pattern)
+        {
+            self.errors.push(format!("invalid regex in {}: {}", key, indent(&e.to_string())));
+        }
+    }
+
+    /**
+     * @brief Check if the value under the key is a string
+     * @param key The key
+     * @return The string or None if the key is missing or it's not a string
+     **/
+    fn string(&mut self, key:&str) -> Option<&'a str>
+    {
+        match self.rule[key]
+        {
+            Yaml::BadValue      => None,
+            Yaml::String(ref s) => Some(s),
+            ref other           => {
+                self.errors.push(format!("{} should be a string, not {}", key, describe(other)));
+                None
+            }
+        }
+    }
+
+    /**
+     * @brief Check if the value under the key is a string or a list of strings
+     * @param key The key
+     * @param single If a single string is allowed
+     * @return The strings, empty if the key is missing or it's invalid
+     **/
+    fn strings(&mut self, key:&str, single:bool) -> Vec<&'a str>
+    {
+        let rule = self.rule;
+
+        match rule[key]
+        {
+            Yaml::BadValue               => Vec::new(),
+            Yaml::String(ref s) if single => vec![&s[..]],
+            Yaml::Array(ref items)       => {
+                let mut ret = Vec::new();
+                for (idx, item) in items.iter().enumerate()
+                {
+                    match item.as_str()
+                    {
+                        Some(s) => ret.push(s),
+                        None    => self.errors.push(format!("item {} of {} should be a string, not {}", idx + 1, key, describe(item)))
+                    }
+                }
+                ret
+            },
+            ref other                    => {
+                let what = if single { "a string or a list of strings" } else { "a list of strings" };
+                self.errors.push(format!("{} should be {}, not {}", key, what, describe(other)));
+                Vec::new()
+            }
+        }
+    }
+
+    /**
+     * @brief Check if the value under the key is a boolean
+     * @param key The key
+     **/
+    fn boolean(&mut self, key:&str)
+    {
+        match self.rule[key]
+        {
+            Yaml::BadValue | Yaml::Boolean(_) => {},
+            ref other                         => self.errors.push(format!("{} should be true or false, not {}", key, describe(other)))
+        }
+    }
+
+    /**
+     * @brief Check if the value under the key is a non-negative number
+     * @param key The key
+     * @param integer If only the integer is allowed
+     **/
+    fn number(&mut self, key:&str, integer:bool)
+    {
+        let valid = match self.rule[key]
+        {
+            Yaml::BadValue              => true,
+            Yaml::Integer(n)            => n >= 0,
+            Yaml::Real(_) if !integer   => self.rule[key].as_f64().is_some_and(|n| n >= 0.0),
+            _                           => false
+        };
+
+        if !valid
+        {
+            let what = if integer { "a non-negative integer" } else { "a non-negative number of seconds" };
+            self.errors.push(format!("{} should be {}", key, what));
+        }
+    }
+
+    /**
+     * @brief Check all the keys of the rule entry
+     **/
+    fn check(&mut self)
+    {
+        let rule = self.rule;
+
+        let hash = match rule.as_hash()
+        {
+            Some(hash) => hash,
+            None       => {
+                self.errors.push(format!("the rule should be a mapping, not {}", describe(rule)));
+                return;
+            }
+        };
+
+        for key in hash.keys()
+        {
+            match key.as_str()
+            {
+                Some(name) if RULE_KEYS.contains(&name) => {},
+                Some(name)                              => self.errors.push(format!("unknown key {}", name)),
+                None                                    => self.errors.push(format!("the key should be a string, not {}", describe(key)))
+            }
+        }
+
+        match self.string("arguments")
+        {
+            Some(pattern) => self.regex("arguments", &format!("^(?:{})$", pattern)),
+            None          => if rule["arguments"].is_badvalue() { self.errors.push("missing arguments, use .* to match any arguments".to_string()); }
+        }
+
+        match self.string("schema")
+        {
+            Some(spec) => if TableSchema::from_spec(spec).is_none() { self.errors.push(schema_error(spec)); },
+            None       => if rule["schema"].is_badvalue() { self.errors.push("missing schema".to_string()); }
+        }
+
+        for key in &["header", "rest", "tokens", "pivot", "json"]
+        {
+            self.boolean(key);
+        }
+        self.number("skip", true);
+        self.number("maxsplit", true);
+        self.number("timeout", false);
+        self.number("read_timeout", false);
+
+        if let Some(name) = self.string("on_error")
+        {
+            if ExitPolicy::from_name(name).is_none()
+            {
+                self.errors.push(format!("unknown on_error policy {}, it should be fail, warn or ignore", name));
+            }
+        }
+
+        if let Some(spec) = self.string("fixed")
+        {
+            if spec != "header" && FixedWidthParser::from_spec(spec).is_none()
+            {
+                self.errors.push(format!("invalid fixed \"{}\", it should be header or the ranges like 0-8 9-15 16-", spec));
+            }
+        }
+
+        self.string("sepval");
+        self.string("keyvalue");
+        self.string("root");
+        self.string("cwd");
+        self.string("locale");
+
+        if let Some(pattern) = self.string("regex")
+        {
+            self.regex("regex", pattern);
+
+            if !(rule["fixed"].is_badvalue() && rule["sepval"].is_badvalue())
+            {
+                self.errors.push("regex is ignored since fixed or sepval is given".to_string());
+            }
+        }
+
+        if let Some(boundary) = self.string("record")
+        {
+            if let Some(pattern) = boundary.strip_prefix("start:")
+            {
+                self.regex("record", pattern.trim());
+            }
+            else if let Some(pattern) = boundary.strip_prefix("separator:")
+            {
+                self.regex("record", pattern.trim());
+            }
+            else if boundary != "blank" && boundary != "indent"
+            {
+                self.errors.push(format!("invalid record \"{}\", it should be blank, indent, start: <regex> or separator: <regex>", boundary));
+            }
+        }
+
+        for pattern in self.strings("extract", false)
+        {
+            self.regex("extract", pattern);
+        }
+
+        for pattern in self.strings("ignore", false)
+        {
+            self.regex("ignore", pattern);
+        }
+
+        for pattern in self.strings("remove", true)
+        {
+            self.regex("remove", &format!("^(?:{})$", pattern));
+        }
+
+        self.strings("argv", true);
+        self.strings("prepend", true);
+        self.strings("append", true);
+
+        match rule["replace"]
+        {
+            Yaml::BadValue             => {},
+            Yaml::Array(ref replaces)  => {
+                for (idx, replace) in replaces.iter().enumerate()
+                {
+                    match (replace["pattern"].as_str(), replace["with"].as_str())
+                    {
+                        (Some(pattern), Some(_)) => self.regex("replace", pattern),
+                        _                        => self.errors.push(format!("item {} of replace should have the string keys pattern and with", idx + 1))
+                    }
+                }
+            },
+            ref other                  => self.errors.push(format!("replace should be a list, not {}", describe(other)))
+        }
+
+        let highlights = match rule["highlight"]
+        {
+            Yaml::BadValue          => Vec::new(),
+            Yaml::Array(ref values) => values.iter().collect(),
+            ref value               => vec![value]
+        };
+
+        for (idx, highlight) in highlights.into_iter().enumerate()
+        {
+            match highlight["when"].as_str()
+            {
+                Some(cond) => if Condition::parse(cond).is_none() { self.errors.push(format!("invalid condition \"{}\" in highlight", cond)); },
+                None       => self.errors.push(format!("item {} of highlight should have the string key when", idx + 1))
+            }
+
+            match highlight["color"].as_str()
+            {
+                Some(name) => if Color::from_name(name).is_none() { self.errors.push(format!("unknown color {} in highlight, it should be red, green, yellow, blue, magenta or cyan", name)); },
+                None       => self.errors.push(format!("item {} of highlight should have the string key color", idx + 1))
+            }
+        }
+
+        for (key, value) in [("env", &rule["env"]), ("paths", &rule["paths"])]
+        {
+            match value
+            {
+                &Yaml::BadValue    => {},
+                &Yaml::Hash(ref h) => {
+                    for (name, value) in h
+                    {
+                        if name.as_str().is_none() || value.as_hash().is_some() || value.as_vec().is_some() || value.is_null()
+                        {
+                            self.errors.push(format!("{} should map the names to the values", key));
+                            break;
+                        }
+                    }
+                },
+                other              => self.errors.push(format!("{} should be a mapping, not {}", key, describe(other)))
+            }
+        }
+    }
+}
+
+/**
+ * @brief Check a rule entry
+ * @param rule The rule entry
+ * @return The errors, empty if the rule is valid
+ **/
+pub fn validate_rule(rule:&Yaml) -> Vec<String>
+{
+    let mut checker = RuleChecker {
+        rule   : rule,
+        errors : Vec::new()
+    };
+
+    checker.check();
+
+    /* Anything we don't check above is caught by the loader */
+    if checker.errors.is_empty() && SchemaQueryResult::from_rule(rule, &[]).is_none()
+    {
+        checker.errors.push("the rule is invalid".to_string());
+    }
+
+    return checker.errors;
+}
+
+/**
+ * @brief Check the rule file, which is a list of rule entries
+ * @param content The content of the rule file
+ * @return The errors, each starts with the number of the rule, empty if the file is valid
+ **/
+pub fn validate_rule_file(content:&str) -> Vec<String>
+{
+    let docs = match YamlLoader::load_from_str(content)
+    {
+        Ok(docs) => docs,
+        Err(e)   => return vec![format!("invalid YAML: {}", e)]
+    };
+
+    let mut ret = Vec::new();
+
+    for doc in &docs
+    {
+        let rules = match doc.as_vec()
+        {
+            Some(rules) => rules,
+            None        => {
+                ret.push(format!("the rule file should be a list of rules, not {}", describe(doc)));
+                continue;
+            }
+        };
+
+        for (idx, rule) in rules.iter().enumerate()
+        {
+            ret.extend(validate_rule(rule).into_iter().map(|error| format!("rule {}: {}", idx + 1, error)));
+        }
+    }
+
+    return ret;
+}
@@ -0,0 +1,166 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The date time support for the table
+ */
+
+/**
+ * @brief A point of time, which is the number of seconds since the Unix epoch.
+ * @note The time without a time zone is treated as it is in UTC
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp(pub i64);
+
+/**
+ * @brief Convert the civil date to the number of days since the Unix epoch
+ * @param y The year
+ * @param m The month
+ * @param d The day
+ * @return The number of days
+ **/
+fn days_from_civil(y:i64, m:i64, d:i64) -> i64
+{
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    return era * 146097 + doe - 719468;
+}
+
+/**
+ * @brief Convert the number of days since the Unix epoch to the civil date
+ * @param z The number of days
+ * @return The year, month and day
+ **/
+fn civil_from_days(z:i64) -> (i64, i64, i64)
+{
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    return (yoe + era * 400 + if m <= 2 { 1 } else { 0 }, m, d);
+}
+
+/**
+ * @brief Parse a fixed number of digits
+ * @param s The string to parse
+ * @param n The number of digits
+ * @return The value and the remaining string
+ **/
+fn parse_digits(s:&str, n:usize) -> Option<(i64, &str)>
+{
+    if s.len() < n || !s.as_bytes()[..n].iter().all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let (head, tail) = s.split_at(n);
+    return head.parse::<i64>().ok().map(|v| (v, tail));
+}
+
+/**
+ * @brief Parse the time zone suffix
+ * @param s The string to parse, which is either empty, Z or +HH:MM / -HHMM
+ * @return The offset in seconds
+ **/
+fn parse_zone(s:&str) -> Option<i64>
+{
+    if s.is_empty() || s == "Z"
+    {
+        return Some(0);
+    }
+
+    let (sign, rest) = if let Some(rest) = s.strip_prefix('+') { (1, rest) }
+                       else if let Some(rest) = s.strip_prefix('-') { (-1, rest) }
+                       else { return None };
+    let (hh, rest) = parse_digits(rest, 2)?;
+    let rest = rest.trim_start_matches(':');
+    let (mm, rest) = parse_digits(rest, 2)?;
+
+    if !rest.is_empty() || hh > 23 || mm > 59
+    {
+        return None;
+    }
+
+    return Some(sign * (hh * 3600 + mm * 60));
+}
+
+/**
+ * @brief Parse an ISO 8601 like date time, the accepted formats are
+ *        YYYY-MM-DD and YYYY-MM-DD[T| ]HH:MM[:SS[.frac]][ ][Z|+HH:MM|-HH:MM]
+ * @param s The string to parse
+ * @return The timestamp or None if it's not a date time
+ **/
+pub fn parse_datetime(s:&str) -> Option<Timestamp>
+{
+    let s = s.trim();
+
+    let (year, rest)  = parse_digits(s, 4)?;
+    if !rest.starts_with('-') { return None; }
+    let (month, rest) = parse_digits(&rest[1..], 2)?;
+    if !rest.starts_with('-') { return None; }
+    let (day, rest)   = parse_digits(&rest[1..], 2)?;
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day)
+    {
+        return None;
+    }
+
+    let mut seconds = days_from_civil(year, month, day) * 86400;
+
+    if rest.is_empty()
+    {
+        return Some(Timestamp(seconds));
+    }
+
+    if !rest.starts_with('T') && !rest.starts_with(' ')
+    {
+        return None;
+    }
+
+    let (hour, rest) = parse_digits(&rest[1..], 2)?;
+    if !rest.starts_with(':') { return None; }
+    let (minute, mut rest) = parse_digits(&rest[1..], 2)?;
+    let mut second = 0;
+
+    if rest.starts_with(':')
+    {
+        let (sec, next) = parse_digits(&rest[1..], 2)?;
+        second = sec;
+        rest = next;
+
+        if rest.starts_with('.')
+        {
+            rest = rest[1..].trim_start_matches(|c:char| c.is_ascii_digit());
+        }
+    }
+
+    if hour > 23 || minute > 59 || second > 60
+    {
+        return None;
+    }
+
+    seconds += hour * 3600 + minute * 60 + second;
+
+    /* The zone may be seperated by a blank, e.g. git log --date=iso and stat */
+    return Some(Timestamp(seconds - parse_zone(rest.trim_start())?));
+}
+
+/**
+ * @brief Format the timestamp as YYYY-MM-DD HH:MM:SS, which can be parsed by parse_datetime
+ * @param ts The timestamp
+ * @return The formatted string
+ **/
+pub fn format_datetime(ts:Timestamp) -> String
+{
+    let days = if ts.0 >= 0 { ts.0 / 86400 } else { (ts.0 - 86399) / 86400 };
+    let secs = ts.0 - days * 86400;
+    let (y, m, d) = civil_from_days(days);
+
+    return format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", y, m, d, secs / 3600, secs % 3600 / 60, secs % 60);
+}
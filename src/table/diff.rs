@@ -0,0 +1,74 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * Compare two snapshots of a table
+ */
+use std::collections::{HashMap, VecDeque};
+use table::primitive::PrimitiveData;
+
+/**
+ * @brief How a row has changed since the previous snapshot
+ **/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RowChange {
+    Unchanged,
+    /// There's no row with the same key in the previous snapshot
+    Added,
+    /// The row with the same key has different values
+    Changed,
+    /// The row is only in the previous snapshot
+    Removed
+}
+
+/**
+ * @brief Make the key of the row
+ * @param row The values of the row
+ * @param keys The key columns, all the columns if it's empty
+ * @return The key
+ **/
+fn row_key(row:&[PrimitiveData], keys:&[usize]) -> String
+{
+    let text = |col:usize| row.get(col).map_or(String::new(), |value| value.to_human_readable());
+
+    return match keys.len()
+    {
+        0 => (0..row.len()).map(text).collect::<Vec<_>>().join("\u{1f}"),
+        _ => keys.iter().map(|&col| text(col)).collect::<Vec<_>>().join("\u{1f}")
+    };
+}
+
+/**
+ * @brief Compare the rows with the previous snapshot. The rows are matched by the values of
+ *        the key columns, the rows with the same key are matched in order
+ * @param previous The rows of the previous snapshot
+ * @param current The rows of the current snapshot
+ * @param keys The key columns, all the columns if it's empty, in this case a changed row is
+ *        reported as removed and added
+ * @return How each current row has changed, and the indices of the removed previous rows
+ **/
+pub fn diff_rows(previous:&[Vec<PrimitiveData>], current:&[Vec<PrimitiveData>], keys:&[usize]) -> (Vec<RowChange>, Vec<usize>)
+{
+    let mut unmatched = HashMap::<String, VecDeque<usize>>::new();
+
+    for (idx, row) in previous.iter().enumerate()
+    {
+        unmatched.entry(row_key(row, keys)).or_default().push_back(idx);
+    }
+
+    let changes = current.iter().map(|row| {
+        match unmatched.get_mut(&row_key(row, keys)).and_then(|rows| rows.pop_front())
+        {
+            None      => RowChange::Added,
+            Some(idx) => {
+                let same = previous[idx].len() == row.len() &&
+                           previous[idx].iter().zip(row.iter()).all(|(a, b)| a.to_human_readable() == b.to_human_readable());
+                if same { RowChange::Unchanged } else { RowChange::Changed }
+            }
+        }
+    }).collect();
+
+    let mut removed:Vec<usize> = unmatched.into_values().flat_map(|rows| rows.into_iter()).collect();
+    removed.sort();
+
+    return (changes, removed);
+}
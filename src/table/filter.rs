@@ -0,0 +1,264 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The row filter and the projection, which are applied to each row as soon as it's read, so
+ * they work on the commands that never stop
+ */
+extern crate regex;
+
+use self::regex::Regex;
+use std::cmp::Ordering;
+use table::input::Input;
+use table::schema::TableSchema;
+use table::row::Row;
+use table::primitive::PrimitiveData;
+use table::datetime::parse_datetime;
+
+/**
+ * @brief The comparison in the condition
+ **/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CompareOp {
+    Eq, Ne, Lt, Le, Gt, Ge,
+    /// The text of the cell matches the regex
+    Match
+}
+
+/**
+ * @brief The condition on a column, e.g. rss>1000, user=root or command~^/usr
+ **/
+#[derive(Clone)]
+pub struct Condition {
+    /// The column name
+    column : String,
+    /// The comparison
+    op     : CompareOp,
+    /// The value we compare with
+    value  : String,
+    /// The compiled regex for the match operator
+    regex  : Option<Regex>
+}
+
+impl Condition {
+    /**
+     * @brief Get the name of the column the condition is on
+     * @return The column name
+     **/
+    pub fn column(&self) -> &str
+    {
+        return &self.column;
+    }
+
+    /**
+     * @brief Parse the condition, which is <column><op><value> and the operator is one of
+     *        = (or ==), !=, <, <=, >, >= and ~ for the regex match. The spaces around the
+     *        column name and the value are ignored, e.g. "state == Z"
+     * @param text The condition text
+     * @return The condition or None if it's invalid
+     **/
+    pub fn parse(text:&str) -> Option<Condition>
+    {
+        const OPS : [(&str, CompareOp); 8] = [
+            ("==", CompareOp::Eq), ("!=", CompareOp::Ne), ("<=", CompareOp::Le), (">=", CompareOp::Ge),
+            ("=", CompareOp::Eq), ("<", CompareOp::Lt), (">", CompareOp::Gt), ("~", CompareOp::Match)
+        ];
+
+        let pos = text.find(&['=', '!', '<', '>', '~'][..])?;
+        let (column, rest) = text.split_at(pos);
+
+        let &(symbol, op) = OPS.iter().find(|&&(symbol, _)| rest.starts_with(symbol))?;
+        let value = rest[symbol.len()..].trim();
+
+        if column.trim().is_empty()
+        {
+            return None;
+        }
+
+        return Some(Condition {
+            column : column.trim().to_string(),
+            op     : op,
+            value  : value.to_string(),
+            regex  : match op
+            {
+                CompareOp::Match => Some(Regex::new(value).ok()?),
+                _                => None
+            }
+        });
+    }
+
+    /**
+     * @brief Check if the cell meets the condition. The numbers and the date times are
+     *        compared by value if the value can be parsed, otherwise the text is compared.
+     *        The missing value never meets the condition
+     * @param cell The cell
+     * @return The check result
+     **/
+    pub fn check(&self, cell:&PrimitiveData) -> bool
+    {
+        if let &PrimitiveData::Nothing() = cell
+        {
+            return false;
+        }
+
+        if let Some(ref re) = self.regex
+        {
+            return re.is_match(&cell.to_human_readable());
+        }
+
+        let ord = match (cell, cell.as_number())
+        {
+            (_, Some(number))                => self.value.trim().parse::<f64>().ok().and_then(|value| number.partial_cmp(&value)),
+            (&PrimitiveData::DateTime(ts), _) => parse_datetime(self.value.trim()).map(|value| ts.0.cmp(&value.0)),
+            _                                => None
+        };
+
+        let ord = ord.unwrap_or_else(|| cell.to_human_readable()[..].cmp(&self.value[..]));
+
+        match self.op
+        {
+            CompareOp::Eq    => ord == Ordering::Equal,
+            CompareOp::Ne    => ord != Ordering::Equal,
+            CompareOp::Lt    => ord == Ordering::Less,
+            CompareOp::Le    => ord != Ordering::Greater,
+            CompareOp::Gt    => ord == Ordering::Greater,
+            CompareOp::Ge    => ord != Ordering::Less,
+            CompareOp::Match => false
+        }
+    }
+}
+
+/**
+ * @brief The input that passes the rows meeting all the conditions, with only the selected
+ *        columns
+ **/
+pub struct FilterInput<'a> {
+    /// The input we read from
+    input      : &'a mut dyn Input,
+    /// The names of the selected columns, None for all the columns
+    columns    : Option<Vec<String>>,
+    /// The conditions
+    conditions : Vec<Condition>,
+    /// The schema of the input
+    inner      : Option<TableSchema>,
+    /// The input column of each output column
+    projection : Vec<usize>,
+    /// The input column of each condition
+    checked    : Vec<usize>,
+    /// The first column name that isn't in the input
+    unknown    : Option<String>
+}
+
+impl <'a> FilterInput<'a> {
+    /**
+     * @brief Create a new filter
+     * @param input The input we read from
+     * @param columns The names of the selected columns, None for all the columns
+     * @param conditions The conditions
+     * @return The newly created filter
+     **/
+    pub fn create(input:&'a mut dyn Input, columns:Option<Vec<String>>, conditions:Vec<Condition>) -> FilterInput<'a>
+    {
+        return FilterInput {
+            input      : input,
+            columns    : columns,
+            conditions : conditions,
+            inner      : None,
+            projection : Vec::new(),
+            checked    : Vec::new(),
+            unknown    : None
+        };
+    }
+
+    /**
+     * @brief Get the selected or checked column that isn't in the input, which is why the
+     *        schema can not be determined
+     * @return The column name or None if all the columns are found
+     **/
+    pub fn unknown_column(&self) -> Option<&str>
+    {
+        return self.unknown.as_ref().map(|name| &name[..]);
+    }
+}
+
+/**
+ * @brief Find the column by its name
+ * @param schema The schema
+ * @param name The column name
+ * @return The column index or None if there's no such column
+ **/
+fn find_column(schema:&TableSchema, name:&str) -> Option<usize>
+{
+    return schema.types.iter().position(|&(ref column, _)| column == name);
+}
+
+impl <'a> Input for FilterInput<'a> {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        let inner = self.input.determine_table_schema()?;
+
+        /* Remember the first missing column, so that the caller can tell why there's no schema */
+        let mut unknown = None;
+        let checked;
+        let projection;
+        {
+            let mut lookup = |name:&String| {
+                let ret = find_column(&inner, name);
+                if ret.is_none() && unknown.is_none()
+                {
+                    unknown = Some(name.clone());
+                }
+                ret
+            };
+
+            checked = self.conditions.iter().map(|cond| lookup(&cond.column)).collect::<Option<Vec<_>>>();
+            projection = match self.columns
+            {
+                Some(ref columns) => columns.iter().map(&mut lookup).collect::<Option<Vec<_>>>(),
+                None              => Some((0..inner.num_columns()).collect())
+            };
+        }
+        self.unknown = unknown;
+
+        self.checked = checked?;
+        self.projection = projection?;
+
+        /* The sort keys are kept as long as the columns are selected */
+        let sort_keys:Vec<usize> = inner.sort_keys.iter()
+            .map_while(|key| self.projection.iter().position(|col| col == key))
+            .collect();
+
+        let ret = TableSchema {
+            sorted    : inner.sorted && sort_keys.len() == inner.sort_keys.len(),
+            sort_keys : sort_keys,
+            types     : self.projection.iter().map(|&col| inner.types[col].clone()).collect()
+        };
+
+        self.inner = Some(inner);
+
+        return Some(ret);
+    }
+
+    fn parse_next_row<'b>(&mut self, schema:&'b TableSchema) -> Option<Row<'b>>
+    {
+        let inner = self.inner.as_ref()?;
+
+        loop
+        {
+            let row = self.input.parse_next_row(inner)?;
+
+            if !self.conditions.iter().zip(self.checked.iter()).all(|(cond, &col)| cond.check(row.value_at(col)))
+            {
+                continue;
+            }
+
+            let mut ret = Row::empty(schema);
+
+            for (idx, &col) in self.projection.iter().enumerate()
+            {
+                ret.set_value(idx, row.value_at(col).clone());
+            }
+
+            return Some(ret);
+        }
+    }
+}
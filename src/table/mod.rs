@@ -1,6 +1,14 @@
 pub mod schema;
 pub mod row;
 pub mod primitive;
+pub mod datetime;
 pub mod output;
+#[allow(clippy::module_inception)]
 pub mod table;
 pub mod input;
+pub mod filter;
+pub mod window;
+pub mod diff;
+
+#[cfg(test)]
+mod tests;
@@ -23,7 +23,7 @@ impl OutputResult {
      * @param how The continuation
      * @return the result
      **/
-    pub fn then<T>(&mut self, table:&mut Table, output:&mut T, how:&Fn(&mut Table, &mut T) -> OutputResult) -> OutputResult
+    pub fn then<T>(&mut self, table:&mut Table, output:&mut T, how:&dyn Fn(&mut Table, &mut T) -> OutputResult) -> OutputResult
         where T : Output
     {
         match self {
@@ -6,6 +6,7 @@
 
 use std::default::Default;
 use table::schema::PrimitiveSchema;
+use table::datetime::{Timestamp, format_datetime};
 
 /**
  * @brief The data type for a primitive data
@@ -21,7 +22,9 @@ pub enum PrimitiveData {
     Float(f64),
     /// We got a string
     //StrRef(&'a String)
-    Str(String)
+    Str(String),
+    /// We got a date time
+    DateTime(Timestamp)
 }
 
 impl PrimitiveData {
@@ -31,7 +34,36 @@ impl PrimitiveData {
             &PrimitiveData::Nothing()   => "".to_string(),
             &PrimitiveData::Int(what)   => format!("{}", what),
             &PrimitiveData::Float(what) => format!("{}", what),
-            &PrimitiveData::Str(ref what)   => what.clone()
+            &PrimitiveData::Str(ref what)   => what.clone(),
+            &PrimitiveData::DateTime(what)  => format_datetime(what)
+        }
+    }
+
+    /**
+     * @brief Get the column type of the value
+     * @return The type or None if there's nothing
+     **/
+    pub fn schema_type(&self) -> Option<PrimitiveSchema>
+    {
+        match self {
+            &PrimitiveData::Nothing()   => None,
+            &PrimitiveData::Int(_)      => Some(PrimitiveSchema::Int),
+            &PrimitiveData::Float(_)    => Some(PrimitiveSchema::Float),
+            &PrimitiveData::Str(_)      => Some(PrimitiveSchema::Str),
+            &PrimitiveData::DateTime(_) => Some(PrimitiveSchema::DateTime)
+        }
+    }
+
+    /**
+     * @brief Get the numeric value
+     * @return The value or None if it's not a number
+     **/
+    pub fn as_number(&self) -> Option<f64>
+    {
+        match self {
+            &PrimitiveData::Int(what)   => Some(what as f64),
+            &PrimitiveData::Float(what) => Some(what),
+            _                           => None
         }
     }
 }
@@ -68,3 +100,8 @@ impl PrimitiveValueT<String> for String {
     fn schema_type() -> PrimitiveSchema { PrimitiveSchema::Str }
 }
 
+
+impl PrimitiveValueT<Timestamp> for Timestamp {
+    fn to_primitive_value(val:Timestamp) -> PrimitiveData { PrimitiveData::DateTime(val) }
+    fn schema_type() -> PrimitiveSchema { PrimitiveSchema::DateTime }
+}
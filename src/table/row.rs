@@ -43,7 +43,7 @@ impl <'schema> Row<'schema> {
      * @param schema The table schema we want to use 
      * @return The newly created row data
      **/
-    pub fn empty(schema:&TableSchema) -> Row 
+    pub fn empty(schema:&TableSchema) -> Row<'_> 
     {
         let mut ret = Row {
             schema: schema,
@@ -72,4 +72,26 @@ impl <'schema> Row<'schema> {
         
         return false;
     }
+
+    /**
+     * @brief Assign a value of any type to the column in this row
+     * @param idx The column index
+     * @param val The value, which should match the column type unless it's Nothing
+     * @return If this operation success
+     **/
+    pub fn set_value(&mut self, idx: usize, val: PrimitiveData) -> bool
+    {
+        let matched = match val.schema_type()
+        {
+            Some(schema) => self.schema.check_schema(idx, schema),
+            None         => idx < self.column_data.len()
+        };
+
+        if matched
+        {
+            self.column_data[idx] = val;
+        }
+
+        return matched;
+    }
 }
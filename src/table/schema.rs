@@ -5,9 +5,8 @@
  */
 /**
  * @brief The primitive colomn types
- * @todo Add date support
  **/
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[derive(PartialEq)]
 pub enum PrimitiveSchema {
     /// The column is an integer
@@ -15,13 +14,32 @@ pub enum PrimitiveSchema {
     /// The column is a float
     Float, 
     /// The column is a string
-    Str
+    Str,
+    /// The column is a date time
+    DateTime
+}
+
+impl PrimitiveSchema {
+    /**
+     * @brief Get the type name used in the schema specification
+     * @return The type name
+     **/
+    pub fn type_name(&self) -> &'static str
+    {
+        match self
+        {
+            &PrimitiveSchema::Int   => "Int",
+            &PrimitiveSchema::Float => "Float",
+            &PrimitiveSchema::Str   => "String",
+            &PrimitiveSchema::DateTime => "DateTime"
+        }
+    }
 }
 
 /**
  * @brief Represent a table schema 
  **/
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TableSchema { 
     /// The list of key index that we used for sorting the table
     pub sort_keys: Vec<usize>,
@@ -66,6 +84,41 @@ impl TableSchema {
         return self.types[idx].1 == schema;
     }
 
+    /**
+     * @brief Format the schema as a specification string, which is the reverse of from_spec
+     * @return The specification string
+     **/
+    pub fn to_spec(&self) -> String
+    {
+        let mut ret = String::new();
+
+        for &(ref name, ref ptype) in &self.types
+        {
+            if ret.len() > 0
+            {
+                ret.push(' ');
+            }
+
+            ret.push_str(&format!(".{}:{}", name, ptype.type_name()));
+        }
+
+        if self.sort_keys.len() > 0
+        {
+            ret.push_str(if self.sorted { " sorted:" } else { " sort:" });
+
+            for (idx, key) in self.sort_keys.iter().enumerate()
+            {
+                if idx > 0
+                {
+                    ret.push(',');
+                }
+                ret.push_str(&self.types[*key].0);
+            }
+        }
+
+        return ret;
+    }
+
     /**
      * @brief Parse a schema specificication from the string
      * @param spec The schema specification
@@ -73,7 +126,7 @@ impl TableSchema {
      *           .name1:type1 [.name2:type2 .... .nameN:typeN] [sort|sorted:key1,key2...,keyM]
      * @return The newly created schema
      **/
-    pub fn from_spec(spec:&String) -> Option<TableSchema> 
+    pub fn from_spec(spec:&str) -> Option<TableSchema> 
     {
         fn parse_ws(s:&str) -> &str
         {
@@ -91,7 +144,7 @@ impl TableSchema {
             return ret;
         }
 
-        fn expect<'a, 'b>(s:&'a str, what:&'b str) -> Option<&'a str>
+        fn expect<'a>(s:&'a str, what:&str) -> Option<&'a str>
         {
             if s.len() < what.len()
             {
@@ -107,7 +160,7 @@ impl TableSchema {
             return None;
         }
 
-        fn parse_token<'a,'b>(s:&'a str, leading:&'b str) -> Option<(&'a str,&'a str)>
+        fn parse_token<'a>(s:&'a str, leading:&str) -> Option<(&'a str,&'a str)>
         {
             if let Some(mut begin) = expect(parse_ws(s), leading)
             {
@@ -148,6 +201,7 @@ impl TableSchema {
                     "Int"    => Some((PrimitiveSchema::Int, next)),
                     "Float"  => Some((PrimitiveSchema::Float, next)),
                     "String" => Some((PrimitiveSchema::Str, next)),
+                    "DateTime" => Some((PrimitiveSchema::DateTime, next)),
                     _        => None
                 }
             }
@@ -181,7 +235,7 @@ impl TableSchema {
             return (ret, to_parse); 
         }
 
-        fn parse_key_list<'a,'b>(s:&'a str, fields:&'b Vec<(String, PrimitiveSchema)>) -> Option<(Vec<usize>, &'a str)>
+        fn parse_key_list<'a>(s:&'a str, fields:&[(String, PrimitiveSchema)]) -> Option<(Vec<usize>, &'a str)>
         {
             let mut ret = Vec::<usize>::new();
             let mut first_time = true;
@@ -213,7 +267,7 @@ impl TableSchema {
             return Some((ret, to_parse));
         }
 
-        fn parse_sort_keys<'a,'b>(s:&'a str, schema:&'b mut TableSchema) -> Option<&'a str>
+        fn parse_sort_keys<'a>(s:&'a str, schema:&mut TableSchema) -> Option<&'a str>
         {
             if let Some((keyword, next)) = parse_token(s, "")
             {
@@ -225,7 +279,7 @@ impl TableSchema {
                     _        =>   { return None; }
                 }
 
-                if let Some((keys, mut rem)) = parse_key_list(next, &schema.types)
+                if let Some((keys, rem)) = parse_key_list(next, &schema.types)
                 {
                     schema.sort_keys = keys;
                     return Some(parse_ws(rem));
@@ -254,7 +308,7 @@ impl TableSchema {
 impl PartialEq for TableSchema {
     fn eq(&self, rhs: &TableSchema) -> bool 
     {
-        if self as * const  _ == rhs as * const _
+        if ::std::ptr::eq(self, rhs)
         {
             return true;
         }
@@ -4,6 +4,7 @@
  * The table object
  */
 use std::iter::Iterator;
+use std::collections::VecDeque;
 use std::mem::swap;
 use std::mem::transmute;
 use table::schema::TableSchema;
@@ -22,17 +23,13 @@ pub enum TableDataSource<'parser> {
     Empty,
     /// Use a parser as Input, the second bool represent if we need to keep all the data we have
     /// read
-    Parser(&'parser mut Input, bool)
+    Parser(&'parser mut dyn Input, bool)
 }
 
 impl <'a> TableDataSource<'a> {
     pub fn is_empty(&self) -> bool
     {
-        match self 
-        {
-            &TableDataSource::Empty => true,
-            _     => false
-        }
+        return matches!(self, &TableDataSource::Empty);
     }
 }
 
@@ -49,7 +46,9 @@ pub struct Table<'schema, 'parser> {
     /// The data source we want to use
     data_source: TableDataSource<'parser>,
     /// The cursor for the seq access 
-    cursor:usize
+    cursor:usize,
+    /// The rows we have read from the parser ahead of the iterator
+    lookahead: VecDeque<Row<'schema>>
 }
 
 pub struct TableRandomAccessor<'table, 'schema:'table, 'parser:'table> {
@@ -78,7 +77,13 @@ impl <'table, 'schema:'table, 'parser:'table> Iterator for &'table mut Table<'sc
         {
             if let &mut TableDataSource::Parser(ref mut parser, ref _keep_used) = &mut self.data_source
             {
-                if let Some(new_row) = parser.parse_next_row(self.schema)
+                let next_row = match self.lookahead.pop_front()
+                {
+                    Some(row) => Some(row),
+                    None      => parser.parse_next_row(self.schema)
+                };
+
+                if let Some(new_row) = next_row
                 {
                     if 0 == self.rows.len() || *_keep_used
                     {
@@ -117,7 +122,7 @@ impl <'table, 'schema:'table, 'parser:'table> TableRandomAccessor<'table, 'schem
      * @param col The column index
      * @return The reference to the cell data
      **/
-    pub fn get_cell<'a>(&'a self, row:usize, col:usize) -> Option<&'a PrimitiveData>
+    pub fn get_cell(&self, row:usize, col:usize) -> Option<&PrimitiveData>
     {
         return Some(self.table.rows[row].value_at(col));
     }
@@ -151,7 +156,8 @@ impl <'schema, 'parser> Table <'schema, 'parser> {
             schema : schema,
             rows   : Vec::new(),
             data_source : data_source,
-            cursor : 0
+            cursor : 0,
+            lookahead : VecDeque::new()
         };
     }
 
@@ -164,6 +170,47 @@ impl <'schema, 'parser> Table <'schema, 'parser> {
         return self.schema.num_columns();
     }
 
+    /**
+     * @brief Check if all the rows are loaded to memory, which means the table is ready for
+     *        random access
+     * @return The check result
+     **/
+    pub fn is_loaded(&self) -> bool
+    {
+        return self.data_source.is_empty();
+    }
+
+    /**
+     * @brief Look at the next rows without consuming them, the rows are still returned by the
+     *        iterator afterwards. This is used by the output plugin to scan the table in preprocess
+     * @param count The max number of rows we want to look at
+     * @return The rows ahead of the iterator, which may be less than count at the end of table
+     **/
+    pub fn peek<'a>(&'a mut self, count:usize) -> &'a [Row<'schema>]
+    {
+        if self.data_source.is_empty()
+        {
+            let end = if self.rows.len() - self.cursor > count { self.cursor + count } else { self.rows.len() };
+            return &self.rows[self.cursor..end];
+        }
+
+        if let &mut TableDataSource::Parser(ref mut parser, _) = &mut self.data_source
+        {
+            while self.lookahead.len() < count
+            {
+                match parser.parse_next_row(self.schema)
+                {
+                    Some(row) => self.lookahead.push_back(row),
+                    None      => break
+                }
+            }
+        }
+
+        let buffered = self.lookahead.make_contiguous();
+        let end = if buffered.len() > count { count } else { buffered.len() };
+        return &buffered[..end];
+    }
+
     /**
      * @brief Dump the table
      * @param output_handle The output plugin instance 
@@ -200,18 +247,13 @@ impl <'schema, 'parser> Table <'schema, 'parser> {
     {
         if !self.data_source.is_empty()
         {
+            self.rows.extend(self.lookahead.drain(..));
+
             if let &mut TableDataSource::Parser(ref mut parser, ref _keep_used) = &mut self.data_source
             {
-                loop 
+                while let Some(row) = parser.parse_next_row(self.schema)
                 {
-                    if let Some(row) = parser.parse_next_row(self.schema)
-                    {
-                        self.rows.push(row);
-                    }
-                    else 
-                    {
-                        break;
-                    }
+                    self.rows.push(row);
                 }
 
             }
@@ -0,0 +1,339 @@
+// Copyright (C) 2018, Hao Hou
+//
+// The table tests, which check the values, the conditions and the row operations without
+// running any command
+use std::collections::VecDeque;
+use std::time::Duration;
+use ::table::datetime::{Timestamp, parse_datetime, format_datetime};
+use ::table::diff::{RowChange, diff_rows};
+use ::table::filter::{Condition, FilterInput};
+use ::table::input::Input;
+use ::table::primitive::PrimitiveData;
+use ::table::row::Row;
+use ::table::schema::TableSchema;
+use ::table::window::{WindowInput, WindowSize, Aggregate};
+
+/**
+ * @brief The input that gives the schema and the rows we have prepared
+ **/
+struct RowsInput {
+    schema : Option<TableSchema>,
+    rows   : VecDeque<Vec<PrimitiveData>>
+}
+
+impl RowsInput {
+    fn create(spec:&str, rows:Vec<Vec<PrimitiveData>>) -> RowsInput
+    {
+        return RowsInput {
+            schema : Some(TableSchema::from_spec(spec).unwrap()),
+            rows   : rows.into_iter().collect()
+        };
+    }
+}
+
+impl Input for RowsInput {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        return self.schema.take();
+    }
+
+    fn parse_next_row<'schema>(&mut self, schema:&'schema TableSchema) -> Option<Row<'schema>>
+    {
+        let values = self.rows.pop_front()?;
+        let mut row = Row::empty(schema);
+
+        for (idx, value) in values.into_iter().enumerate()
+        {
+            assert!(row.set_value(idx, value), "the value of column {} doesn't match the schema", idx);
+        }
+
+        return Some(row);
+    }
+}
+
+/**
+ * @brief Read all the rows from the input
+ * @param input The input
+ * @return The schema specification and the text of each cell, None for the missing value
+ **/
+fn read_all(input:&mut dyn Input) -> (String, Vec<Vec<Option<String>>>)
+{
+    let schema = input.determine_table_schema().expect("no schema");
+    let mut rows = Vec::new();
+
+    while let Some(row) = input.parse_next_row(&schema)
+    {
+        rows.push((0..schema.num_columns()).map(|col| match row.value_at(col)
+        {
+            &PrimitiveData::Nothing() => None,
+            value                     => Some(value.to_human_readable())
+        }).collect());
+    }
+
+    return (schema.to_spec(), rows);
+}
+
+fn s(text:&str) -> PrimitiveData
+{
+    return PrimitiveData::Str(text.to_string());
+}
+
+fn text(text:&str) -> Option<String>
+{
+    return Some(text.to_string());
+}
+
+#[test]
+fn dates_and_times_are_parsed_as_utc()
+{
+    assert_eq!(parse_datetime("1970-01-01"), Some(Timestamp(0)));
+    assert_eq!(parse_datetime("2018-01-02"), Some(Timestamp(1514851200)));
+    assert_eq!(parse_datetime("2018-01-02 03:04"), Some(Timestamp(1514862240)));
+    assert_eq!(parse_datetime("2018-01-02T03:04:05"), Some(Timestamp(1514862245)));
+    assert_eq!(parse_datetime("2018-01-02T03:04:05.123456Z"), Some(Timestamp(1514862245)));
+    assert_eq!(parse_datetime("  2018-01-02 03:04:05  "), Some(Timestamp(1514862245)));
+    assert_eq!(parse_datetime("1969-12-31 23:59:59"), Some(Timestamp(-1)));
+    assert_eq!(parse_datetime("2016-02-29"), Some(Timestamp(1456704000)));
+}
+
+#[test]
+fn time_zones_are_applied()
+{
+    assert_eq!(parse_datetime("2018-01-02T03:04:05+08:00"), Some(Timestamp(1514862245 - 8 * 3600)));
+    assert_eq!(parse_datetime("2018-01-02T03:04:05-0130"), Some(Timestamp(1514862245 + 5400)));
+
+    /* git log --date=iso and stat put a blank before the zone */
+    assert_eq!(parse_datetime("2018-01-02 03:04:05 +0800"), Some(Timestamp(1514862245 - 8 * 3600)));
+    assert_eq!(parse_datetime("2018-01-02 03:04:05.000000000 -0000"), Some(Timestamp(1514862245)));
+}
+
+#[test]
+fn malformed_dates_and_times_are_rejected()
+{
+    for text in &["", "2018", "2018-1-2", "18-01-02", "2018/01/02", "2018-13-01", "2018-00-10",
+                  "2018-01-32", "2018-01-02T24:00", "2018-01-02T03:60", "2018-01-02T03",
+                  "2018-01-02X03:04", "2018-01-02T03:04:05+24:00", "2018-01-02T03:04:05 PST",
+                  "2018-01-02T03:04:05+08:00:00", "123", "1.5", "Oct18"]
+    {
+        assert_eq!(parse_datetime(text), None, "{} is parsed", text);
+    }
+}
+
+#[test]
+fn formatted_date_times_are_parsed_back()
+{
+    assert_eq!(format_datetime(Timestamp(0)), "1970-01-01 00:00:00");
+    assert_eq!(format_datetime(Timestamp(-1)), "1969-12-31 23:59:59");
+    assert_eq!(format_datetime(Timestamp(1514862245)), "2018-01-02 03:04:05");
+
+    for &ts in &[0, -1, 1514862245, 951782400, -86400 * 365 * 100, 4102444800]
+    {
+        assert_eq!(parse_datetime(&format_datetime(Timestamp(ts))), Some(Timestamp(ts)));
+    }
+}
+
+#[test]
+fn conditions_are_parsed()
+{
+    for &(text, column) in &[("rss>1000", "rss"), ("user=root", "user"), ("state == Z", "state"),
+                             ("  a  !=  b ", "a"), ("a<=1", "a"), ("a>=1", "a"), ("a<1", "a"),
+                             ("command~^/usr", "command"), ("a=", "a")]
+    {
+        let cond = Condition::parse(text);
+        assert!(cond.is_some(), "{} is rejected", text);
+        assert_eq!(cond.unwrap().column(), column);
+    }
+
+    for text in &["", "rss", "=1", " ~x", "a!1", "a~(", "a~[z-a]"]
+    {
+        assert!(Condition::parse(text).is_none(), "{} is accepted", text);
+    }
+}
+
+#[test]
+fn conditions_compare_numbers_dates_and_text()
+{
+    let check = |text:&str, cell:PrimitiveData| Condition::parse(text).unwrap().check(&cell);
+
+    /* The numbers are compared by value, not by text */
+    assert!(check("rss>1000", PrimitiveData::Int(9999)));
+    assert!(!check("rss>1000", PrimitiveData::Int(200)));
+    assert!(check("cpu<=1.5", PrimitiveData::Float(1.5)));
+    assert!(check("n==10", PrimitiveData::Float(10.0)));
+    assert!(check("n!=10", PrimitiveData::Int(11)));
+
+    /* The dates are compared in time, even with a different zone */
+    let ts = parse_datetime("2018-01-02 03:04:05").unwrap();
+    assert!(check("t>2018-01-01", PrimitiveData::DateTime(ts)));
+    assert!(check("t=2018-01-02T11:04:05+08:00", PrimitiveData::DateTime(ts)));
+    assert!(!check("t<2018-01-02", PrimitiveData::DateTime(ts)));
+
+    /* The text is compared when the value is not a number */
+    assert!(check("user=root", s("root")));
+    assert!(!check("user=root", s("rooted")));
+    assert!(!check("n>abc", PrimitiveData::Int(1)));
+    assert!(check("name>=b", s("bob")));
+
+    /* The regex matches any part of the text unless it's anchored */
+    assert!(check("command~^/usr", s("/usr/bin/ls")));
+    assert!(!check("command~^/usr", s("/bin/usr")));
+    assert!(check("stat~Z", s("Zs")));
+    assert!(check("pid~^1", PrimitiveData::Int(123)));
+
+    /* The missing value never meets the condition */
+    assert!(!check("a!=1", PrimitiveData::Nothing()));
+    assert!(!check("a~.*", PrimitiveData::Nothing()));
+}
+
+#[test]
+fn filter_selects_the_rows_and_the_columns()
+{
+    let mut input = RowsInput::create(".pid:Int .user:String .rss:Int", vec![
+        vec![PrimitiveData::Int(1), s("root"), PrimitiveData::Int(100)],
+        vec![PrimitiveData::Int(2), s("alice"), PrimitiveData::Int(5000)],
+        vec![PrimitiveData::Int(3), s("root"), PrimitiveData::Nothing()]
+    ]);
+    let conditions = vec![Condition::parse("user=root").unwrap()];
+    let mut filter = FilterInput::create(&mut input, Some(vec!["rss".to_string(), "pid".to_string()]), conditions);
+
+    let (spec, rows) = read_all(&mut filter);
+    assert_eq!(spec, ".rss:Int .pid:Int");
+    assert_eq!(rows, vec![vec![text("100"), text("1")], vec![None, text("3")]]);
+
+    /* The condition on an unknown column fails the schema */
+    let mut input = RowsInput::create(".pid:Int", Vec::new());
+    let mut filter = FilterInput::create(&mut input, None, vec![Condition::parse("user=root").unwrap()]);
+    assert!(filter.determine_table_schema().is_none());
+    assert_eq!(filter.unknown_column(), Some("user"));
+
+    /* So does an unknown selected column, and the known columns are not reported */
+    let mut input = RowsInput::create(".pid:Int", Vec::new());
+    let mut filter = FilterInput::create(&mut input, Some(vec!["pid".to_string(), "rsss".to_string()]), Vec::new());
+    assert!(filter.determine_table_schema().is_none());
+    assert_eq!(filter.unknown_column(), Some("rsss"));
+
+    let mut input = RowsInput::create(".pid:Int", Vec::new());
+    let mut filter = FilterInput::create(&mut input, Some(vec!["pid".to_string()]), Vec::new());
+    assert!(filter.determine_table_schema().is_some());
+    assert_eq!(filter.unknown_column(), None);
+}
+
+#[test]
+fn window_sizes_are_parsed()
+{
+    assert_eq!(WindowSize::parse("10"), Some(WindowSize::Rows(10)));
+    assert_eq!(WindowSize::parse("30s"), Some(WindowSize::Time(Duration::from_secs(30))));
+    assert_eq!(WindowSize::parse("5m"), Some(WindowSize::Time(Duration::from_secs(300))));
+    assert_eq!(WindowSize::parse("2h"), Some(WindowSize::Time(Duration::from_secs(7200))));
+
+    for text in &["", "0", "0s", "-1", "1d", "s", "1.5m", "99999999999999999999", "99999999999999999h", "307445734561825861m"]
+    {
+        assert_eq!(WindowSize::parse(text), None, "{} is accepted", text);
+    }
+}
+
+#[test]
+fn window_aggregates_the_latest_rows()
+{
+    let rows = || vec![
+        vec![s("a"), PrimitiveData::Int(1), PrimitiveData::Float(0.5)],
+        vec![s("b"), PrimitiveData::Int(3), PrimitiveData::Nothing()],
+        vec![s("c"), PrimitiveData::Int(8), PrimitiveData::Float(1.5)]
+    ];
+    let aggregate = |aggregate:Aggregate| {
+        let mut input = RowsInput::create(".name:String .n:Int .f:Float", rows());
+        let mut window = WindowInput::create(&mut input, WindowSize::Rows(2), aggregate);
+        read_all(&mut window)
+    };
+
+    assert_eq!(aggregate(Aggregate::Sum).1, vec![
+        vec![text("a"), text("1"), text("0.5")],
+        vec![text("b"), text("4"), text("0.5")],
+        vec![text("c"), text("11"), text("1.5")]
+    ]);
+    assert_eq!(aggregate(Aggregate::Max).1[2], vec![text("c"), text("8"), text("1.5")]);
+    assert_eq!(aggregate(Aggregate::Min).1[2], vec![text("c"), text("3"), text("1.5")]);
+
+    let (spec, rows) = aggregate(Aggregate::Avg);
+    assert_eq!(spec, ".name:String .n:Float .f:Float");
+    assert_eq!(rows[1], vec![text("b"), text("2"), text("0.5")]);
+    assert_eq!(rows[2], vec![text("c"), text("5.5"), text("1.5")]);
+}
+
+#[test]
+fn window_sum_does_not_overflow()
+{
+    let mut input = RowsInput::create(".n:Int", vec![
+        vec![PrimitiveData::Int(i64::MAX)],
+        vec![PrimitiveData::Int(1)],
+        vec![PrimitiveData::Int(i64::MIN)],
+        vec![PrimitiveData::Int(-1)]
+    ]);
+    let mut window = WindowInput::create(&mut input, WindowSize::Rows(2), Aggregate::Sum);
+    let (_, rows) = read_all(&mut window);
+
+    /* The sum is clamped to the range of the Int column rather than wrapped */
+    assert_eq!(rows, vec![
+        vec![Some(i64::MAX.to_string())],
+        vec![Some(i64::MAX.to_string())],
+        vec![text("-9223372036854775807")],
+        vec![Some(i64::MIN.to_string())]
+    ]);
+
+    let mut input = RowsInput::create(".n:Int", vec![vec![PrimitiveData::Int(i64::MAX)], vec![PrimitiveData::Int(i64::MAX)]]);
+    let mut window = WindowInput::create(&mut input, WindowSize::Rows(2), Aggregate::Avg);
+    assert_eq!(read_all(&mut window).1[1], vec![Some((i64::MAX as f64).to_string())]);
+}
+
+/**
+ * @brief Make the rows of the snapshot, each row is a pid and a state
+ * @param rows The pid and the state of each row
+ * @return The rows
+ **/
+fn snapshot(rows:&[(i64, &str)]) -> Vec<Vec<PrimitiveData>>
+{
+    return rows.iter().map(|&(pid, state)| vec![PrimitiveData::Int(pid), s(state)]).collect();
+}
+
+#[test]
+fn diff_finds_the_added_removed_and_changed_rows()
+{
+    let previous = snapshot(&[(1, "S"), (2, "R"), (3, "S")]);
+    let current = snapshot(&[(1, "S"), (3, "Z"), (4, "R")]);
+
+    let (changes, removed) = diff_rows(&previous, &current, &[0]);
+    assert_eq!(changes, vec![RowChange::Unchanged, RowChange::Changed, RowChange::Added]);
+    assert_eq!(removed, vec![1]);
+
+    /* Without the key columns, the changed row is removed and added */
+    let (changes, removed) = diff_rows(&previous, &current, &[]);
+    assert_eq!(changes, vec![RowChange::Unchanged, RowChange::Added, RowChange::Added]);
+    assert_eq!(removed, vec![1, 2]);
+
+    let (changes, removed) = diff_rows(&[], &current, &[0]);
+    assert_eq!(changes, vec![RowChange::Added; 3]);
+    assert!(removed.is_empty());
+
+    let (changes, removed) = diff_rows(&previous, &[], &[0]);
+    assert!(changes.is_empty());
+    assert_eq!(removed, vec![0, 1, 2]);
+}
+
+#[test]
+fn diff_matches_the_duplicate_keys_in_order()
+{
+    let previous = snapshot(&[(1, "a"), (1, "b"), (2, "c"), (1, "d")]);
+    let current = snapshot(&[(1, "a"), (2, "c"), (1, "x")]);
+
+    /* The first two rows with key 1 are matched, the third one is removed */
+    let (changes, removed) = diff_rows(&previous, &current, &[0]);
+    assert_eq!(changes, vec![RowChange::Unchanged, RowChange::Unchanged, RowChange::Changed]);
+    assert_eq!(removed, vec![3]);
+
+    /* The same rows are matched one to one */
+    let previous = snapshot(&[(1, "a"), (1, "a")]);
+    let current = snapshot(&[(1, "a"), (1, "a"), (1, "a")]);
+    let (changes, removed) = diff_rows(&previous, &current, &[]);
+    assert_eq!(changes, vec![RowChange::Unchanged, RowChange::Unchanged, RowChange::Added]);
+    assert!(removed.is_empty());
+}
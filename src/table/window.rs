@@ -0,0 +1,241 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The windowed aggregate, which emits the aggregate over the latest rows after each row is
+ * read, e.g. the average of the last 10 rows of vmstat 1
+ */
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use table::input::Input;
+use table::schema::{TableSchema, PrimitiveSchema};
+use table::row::Row;
+use table::primitive::PrimitiveData;
+
+/**
+ * @brief How many rows the window holds
+ **/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WindowSize {
+    /// The latest n rows
+    Rows(usize),
+    /// The rows read in the latest period
+    Time(Duration)
+}
+
+impl WindowSize {
+    /**
+     * @brief Parse the window size, which is either a row count like 10, or a period like 60s,
+     *        5m or 1h
+     * @param text The text to parse
+     * @return The window size or None if it's invalid or too large
+     **/
+    pub fn parse(text:&str) -> Option<WindowSize>
+    {
+        let (number, unit) = match text.find(|c:char| !c.is_ascii_digit())
+        {
+            Some(pos) => text.split_at(pos),
+            None      => (text, "")
+        };
+
+        let number = number.parse::<u64>().ok().filter(|n| *n > 0)?;
+
+        match unit
+        {
+            ""  => Some(WindowSize::Rows(number as usize)),
+            "s" => Some(WindowSize::Time(Duration::from_secs(number))),
+            "m" => Some(WindowSize::Time(Duration::from_secs(number.checked_mul(60)?))),
+            "h" => Some(WindowSize::Time(Duration::from_secs(number.checked_mul(3600)?))),
+            _   => None
+        }
+    }
+}
+
+/**
+ * @brief The aggregate function applied to the numeric columns
+ **/
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Aggregate {
+    Avg, Sum, Min, Max
+}
+
+impl Aggregate {
+    /**
+     * @brief Parse the aggregate name
+     * @param name The name, which is avg, sum, min or max
+     * @return The aggregate or None if the name is unknown
+     **/
+    pub fn from_name(name:&str) -> Option<Aggregate>
+    {
+        match name
+        {
+            "avg" => Some(Aggregate::Avg),
+            "sum" => Some(Aggregate::Sum),
+            "min" => Some(Aggregate::Min),
+            "max" => Some(Aggregate::Max),
+            _     => None
+        }
+    }
+
+    /**
+     * @brief Aggregate the values of a column, the missing values are skipped. The sum of
+     *        the integers becomes a float if it overflows
+     * @param values The values in the window
+     * @return The aggregated value, Nothing if there's no value
+     **/
+    fn apply<'a, I>(&self, values:I) -> PrimitiveData
+        where I: Iterator<Item = &'a PrimitiveData>
+    {
+        let mut count = 0;
+        let mut ret = PrimitiveData::Nothing();
+
+        for value in values
+        {
+            ret = match (self, &ret, value)
+            {
+                (_, _, &PrimitiveData::Nothing())                                      => continue,
+                (_, &PrimitiveData::Nothing(), _)                                      => value.clone(),
+                (&Aggregate::Sum, &PrimitiveData::Int(a), &PrimitiveData::Int(b))     => match a.checked_add(b)
+                {
+                    Some(sum) => PrimitiveData::Int(sum),
+                    None      => PrimitiveData::Float(a as f64 + b as f64)
+                },
+                (&Aggregate::Min, &PrimitiveData::Int(a), &PrimitiveData::Int(b))     => PrimitiveData::Int(a.min(b)),
+                (&Aggregate::Max, &PrimitiveData::Int(a), &PrimitiveData::Int(b))     => PrimitiveData::Int(a.max(b)),
+                (&Aggregate::Min, _, _)                                                => PrimitiveData::Float(ret.as_number().unwrap_or(0.0).min(value.as_number().unwrap_or(0.0))),
+                (&Aggregate::Max, _, _)                                                => PrimitiveData::Float(ret.as_number().unwrap_or(0.0).max(value.as_number().unwrap_or(0.0))),
+                (&Aggregate::Sum, _, _) | (&Aggregate::Avg, _, _)                      => PrimitiveData::Float(ret.as_number().unwrap_or(0.0) + value.as_number().unwrap_or(0.0))
+            };
+            count += 1;
+        }
+
+        if let (&Aggregate::Avg, Some(sum)) = (self, ret.as_number())
+        {
+            return PrimitiveData::Float(sum / count as f64);
+        }
+
+        return ret;
+    }
+}
+
+/**
+ * @brief The input that emits the aggregate of the window after each row is read. The
+ *        numeric columns are aggregated and the other columns have the values of the latest
+ *        row
+ **/
+pub struct WindowInput<'a> {
+    /// The input we read from
+    input     : &'a mut dyn Input,
+    /// How many rows the window holds
+    size      : WindowSize,
+    /// The aggregate function
+    aggregate : Aggregate,
+    /// The schema of the input
+    inner     : Option<TableSchema>,
+    /// The rows in the window with the time they are read
+    rows      : VecDeque<(Instant, Vec<PrimitiveData>)>
+}
+
+impl <'a> WindowInput<'a> {
+    /**
+     * @brief Create a new windowed aggregate
+     * @param input The input we read from
+     * @param size How many rows the window holds
+     * @param aggregate The aggregate function
+     * @return The newly created input
+     **/
+    pub fn create(input:&'a mut dyn Input, size:WindowSize, aggregate:Aggregate) -> WindowInput<'a>
+    {
+        return WindowInput {
+            input     : input,
+            size      : size,
+            aggregate : aggregate,
+            inner     : None,
+            rows      : VecDeque::new()
+        };
+    }
+
+    /**
+     * @brief Drop the rows that are out of the window, the latest row is always kept
+     **/
+    fn evict(&mut self)
+    {
+        let now = Instant::now();
+
+        while self.rows.len() > 1
+        {
+            let expired = match self.size
+            {
+                WindowSize::Rows(n)      => self.rows.len() > n,
+                WindowSize::Time(period) => self.rows.front().is_some_and(|&(time, _)| now.duration_since(time) > period)
+            };
+
+            if !expired
+            {
+                break;
+            }
+
+            self.rows.pop_front();
+        }
+    }
+}
+
+impl <'a> Input for WindowInput<'a> {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        let inner = self.input.determine_table_schema()?;
+        let mut ret = inner.clone();
+
+        if self.aggregate == Aggregate::Avg
+        {
+            for &mut (_, ref mut ptype) in ret.types.iter_mut()
+            {
+                if *ptype == PrimitiveSchema::Int
+                {
+                    *ptype = PrimitiveSchema::Float;
+                }
+            }
+        }
+
+        self.inner = Some(inner);
+
+        return Some(ret);
+    }
+
+    fn parse_next_row<'b>(&mut self, schema:&'b TableSchema) -> Option<Row<'b>>
+    {
+        let values = {
+            let inner = self.inner.as_ref()?;
+            let row = self.input.parse_next_row(inner)?;
+            (0..inner.num_columns()).map(|col| row.value_at(col).clone()).collect()
+        };
+
+        self.rows.push_back((Instant::now(), values));
+        self.evict();
+
+        let inner = self.inner.as_ref()?;
+        let mut ret = Row::empty(schema);
+
+        for (col, &(_, ref ptype)) in inner.types.iter().enumerate()
+        {
+            let value = match ptype
+            {
+                &PrimitiveSchema::Int | &PrimitiveSchema::Float => self.aggregate.apply(self.rows.iter().map(|&(_, ref values)| &values[col])),
+                _                                               => self.rows.back().map_or(PrimitiveData::Nothing(), |&(_, ref values)| values[col].clone())
+            };
+
+            /* Keep the type of the column for the aggregate of an Int column except avg, the
+             * sum that overflows is clamped since the column type is decided before the rows
+             * are read */
+            let value = match (schema.field_type(col), value)
+            {
+                (&PrimitiveSchema::Int, PrimitiveData::Float(f)) => PrimitiveData::Int(f as i64),
+                (&PrimitiveSchema::Float, PrimitiveData::Int(i)) => PrimitiveData::Float(i as f64),
+                (_, value)                                       => value
+            };
+
+            ret.set_value(col, value);
+        }
+
+        return Some(ret);
+    }
+}
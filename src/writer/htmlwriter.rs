@@ -0,0 +1,161 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The HTML table output
+ */
+use std::io::Write;
+use ::table::output::{Output, OutputResult};
+use ::table::table::Table;
+use ::table::schema::TableSchema;
+use ::writer::write_row;
+
+/**
+ * @brief Escape the text with HTML entities, the result is safe for both the text
+ *        content and a quoted attribute value
+ * @param text The text to escape
+ * @return The escaped text
+ **/
+fn escape_html(text:&str) -> String
+{
+    let mut ret = String::with_capacity(text.len());
+    for ch in text.chars()
+    {
+        match ch
+        {
+            '&'  => ret.push_str("&amp;"),
+            '<'  => ret.push_str("&lt;"),
+            '>'  => ret.push_str("&gt;"),
+            '"'  => ret.push_str("&quot;"),
+            '\'' => ret.push_str("&#39;"),
+            _    => ret.push(ch)
+        }
+    }
+    return ret;
+}
+
+/**
+ * @brief Compute the attributes of each column from the schema
+ * @param schema The table schema
+ * @return The attributes, which carry the column name and type
+ **/
+fn column_attrs(schema:&TableSchema) -> Vec<String>
+{
+    return schema.types.iter()
+        .map(|&(ref name, ref ptype)| format!(" class=\"{}\" data-type=\"{}\"", escape_html(name), ptype.type_name()))
+        .collect();
+}
+
+/**
+ * @brief The HTML table writer. Each cell carries the column name as the class and
+ *        the column type as the data-type attribute, so that it can be styled by CSS
+ **/
+pub struct HtmlOutputer<W:Write> {
+    /// The stream we are writing to
+    out    : Option<W>,
+    /// The attributes for each column, which is computed from the schema
+    attrs  : Vec<String>,
+    /// If each row is flushed as soon as it's written
+    follow : bool
+}
+
+impl <W:Write> HtmlOutputer<W> {
+    /**
+     * @brief Create a new HTML writer
+     * @param out The stream we want to write
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W) -> HtmlOutputer<W>
+    {
+        return HtmlOutputer {
+            out    : Some(out),
+            attrs  : Vec::new(),
+            follow : false
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut HtmlOutputer<W>
+    {
+        self.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for HtmlOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult
+    {
+        if let Some(ref mut out) = self.out
+        {
+            self.attrs = column_attrs(table.schema);
+
+            let mut buf = String::from("<table>\n<thead>\n<tr>");
+            for (attr, &(ref name, _)) in self.attrs.iter().zip(&table.schema.types)
+            {
+                buf.push_str(&format!("<th{}>{}</th>", attr, escape_html(name)));
+            }
+            buf.push_str("</tr>\n</thead>\n<tbody>\n");
+
+            if out.write_all(buf.as_bytes()).is_ok()
+            {
+                return OutputResult::Success();
+            }
+        }
+        return OutputResult::Fail();
+    }
+
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        /* The schema may not be written, e.g. the table is appended to another one */
+        if self.attrs.len() != table.num_columns()
+        {
+            self.attrs = column_attrs(table.schema);
+        }
+
+        let follow = self.follow;
+        match &mut self.out
+        {
+            &mut None              => OutputResult::Fail(),
+            &mut Some(ref mut out) => {
+                let cols = table.num_columns();
+                for raw_row in table
+                {
+                    let mut buf = String::from("<tr>");
+                    for c in 0..cols
+                    {
+                        buf.push_str(&format!("<td{}>{}</td>", self.attrs[c], escape_html(&raw_row.value_at(c).to_human_readable())));
+                    }
+                    buf.push_str("</tr>\n");
+
+                    if write_row(out, &buf, follow).is_err()
+                    {
+                        return OutputResult::Fail();
+                    }
+                }
+
+                match out.write_all(b"</tbody>\n</table>\n").and_then(|_| out.flush())
+                {
+                    Ok(_)  => OutputResult::Success(),
+                    Err(_) => OutputResult::Fail()
+                }
+            }
+        }
+    }
+
+    fn get_output_result(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The JSON based output, which keeps the type of each cell
+ */
+use std::io::Write;
+use ::table::output::{Output, OutputResult};
+use ::table::table::Table;
+use ::table::schema::TableSchema;
+use ::table::row::Row;
+use ::table::primitive::PrimitiveData;
+use ::reader::json::push_json_string;
+use ::writer::write_row;
+
+/**
+ * @brief Append a cell as a JSON value to the buffer
+ * @param buf The buffer
+ * @param data The cell data
+ * @note JSON can not represent NaN and infinity, so they are written as null
+ **/
+fn push_json_value(buf:&mut String, data:&PrimitiveData)
+{
+    match data
+    {
+        &PrimitiveData::Nothing()       => buf.push_str("null"),
+        &PrimitiveData::Int(what)       => buf.push_str(&format!("{}", what)),
+        &PrimitiveData::Float(what)     => {
+            if what.is_finite()
+            {
+                buf.push_str(&format!("{:?}", what));
+            }
+            else
+            {
+                buf.push_str("null");
+            }
+        },
+        &PrimitiveData::Str(ref what)   => push_json_string(buf, what),
+        &PrimitiveData::DateTime(_)     => push_json_string(buf, &data.to_human_readable())
+    }
+}
+
+/**
+ * @brief Format a row as a JSON object keyed by the column names
+ * @param schema The table schema
+ * @param row The row to format
+ * @return The formatted object
+ **/
+fn format_json_object(schema:&TableSchema, row:&Row) -> String
+{
+    let mut ret = String::new();
+    ret.push('{');
+    for (idx, &(ref name, _)) in schema.types.iter().enumerate()
+    {
+        if idx > 0
+        {
+            ret.push(',');
+        }
+        push_json_string(&mut ret, name);
+        ret.push(':');
+        push_json_value(&mut ret, row.value_at(idx));
+    }
+    ret.push('}');
+    return ret;
+}
+
+/**
+ * @brief Write all the rows in the table as a JSON array
+ * @param out The stream to write
+ * @param table The table to dump
+ * @param follow If each row is flushed as soon as it's written
+ * @return The write result
+ **/
+fn write_json_array<W:Write>(out:&mut W, table:&mut Table, follow:bool) -> OutputResult
+{
+    let schema = table.schema;
+    let mut first = true;
+    for raw_row in table
+    {
+        let mut buf = (if first { "[\n" } else { ",\n" }).to_string();
+        buf.push_str(&format_json_object(schema, raw_row));
+        first = false;
+
+        /* Like the NDJSON writer, each row is written as soon as it's read */
+        if write_row(out, &buf, follow).is_err()
+        {
+            return OutputResult::Fail();
+        }
+    }
+
+    if out.write_all((if first { "[]\n" } else { "\n]\n" }).as_bytes()).and_then(|_| out.flush()).is_ok()
+    {
+        return OutputResult::Success();
+    }
+
+    return OutputResult::Fail();
+}
+
+/**
+ * @brief Write each row in the table as a JSON object on its own line
+ * @param out The stream to write
+ * @param table The table to dump
+ * @param follow If each row is flushed as soon as it's written
+ * @return The write result
+ **/
+fn write_json_lines<W:Write>(out:&mut W, table:&mut Table, follow:bool) -> OutputResult
+{
+    let schema = table.schema;
+    for raw_row in table
+    {
+        let mut buf = format_json_object(schema, raw_row);
+        buf.push('\n');
+
+        if write_row(out, &buf, follow).is_err()
+        {
+            return OutputResult::Fail();
+        }
+    }
+
+    if out.flush().is_ok()
+    {
+        return OutputResult::Success();
+    }
+
+    return OutputResult::Fail();
+}
+
+/**
+ * @brief The JSON writer, which dumps the table as an array of objects
+ **/
+pub struct JsonOutputer<W:Write> {
+    /// The stream we are writing to
+    out    : Option<W>,
+    /// If each row is flushed as soon as it's written
+    follow : bool
+}
+
+impl <W:Write> JsonOutputer<W> {
+    /**
+     * @brief Create a new JSON writer
+     * @param out The stream we want to write
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W) -> JsonOutputer<W>
+    {
+        return JsonOutputer {
+            out    : Some(out),
+            follow : false
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut JsonOutputer<W>
+    {
+        self.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for JsonOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        match &mut self.out
+        {
+            &mut None              => OutputResult::Fail(),
+            &mut Some(ref mut out) => write_json_array(out, table, self.follow)
+        }
+    }
+
+    fn get_output_result(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
+
+/**
+ * @brief The newline delimited JSON writer, which writes one object per line
+ **/
+pub struct NdjsonOutputer<W:Write> {
+    /// The stream we are writing to
+    out    : Option<W>,
+    /// If each row is flushed as soon as it's written
+    follow : bool
+}
+
+impl <W:Write> NdjsonOutputer<W> {
+    /**
+     * @brief Create a new NDJSON writer
+     * @param out The stream we want to write
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W) -> NdjsonOutputer<W>
+    {
+        return NdjsonOutputer {
+            out    : Some(out),
+            follow : false
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut NdjsonOutputer<W>
+    {
+        self.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for NdjsonOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        match &mut self.out
+        {
+            &mut None              => OutputResult::Fail(),
+            &mut Some(ref mut out) => write_json_lines(out, table, self.follow)
+        }
+    }
+
+    fn get_output_result(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
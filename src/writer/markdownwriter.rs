@@ -0,0 +1,156 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The GitHub flavoured markdown table output
+ */
+use std::io::Write;
+use ::table::output::{Output, OutputResult};
+use ::table::table::Table;
+use ::table::schema::PrimitiveSchema;
+use ::writer::write_row;
+
+/**
+ * @brief Escape the text so that it can be put into a markdown table cell, the angle
+ *        brackets and the ampersand are escaped as well, otherwise the cell may be
+ *        rendered as HTML or an entity
+ * @param text The cell text
+ * @return The escaped text
+ **/
+fn escape_markdown(text:&str) -> String
+{
+    let mut ret = String::with_capacity(text.len());
+    for ch in text.chars()
+    {
+        match ch
+        {
+            '|'  => ret.push_str("\\|"),
+            '&'  => ret.push_str("\\&"),
+            '\\' => ret.push_str("\\\\"),
+            '<'  => ret.push_str("\\<"),
+            '>'  => ret.push_str("\\>"),
+            '\n' => ret.push_str("<br>"),
+            '\r' => {},
+            _    => ret.push(ch)
+        }
+    }
+    return ret;
+}
+
+/**
+ * @brief The markdown table writer, numeric columns are right aligned and the
+ *        string columns are left aligned. A markdown table has at least one column, so
+ *        the table without any column is written with a single empty column
+ **/
+pub struct MarkdownOutputer<W:Write> {
+    /// The stream we are writing to
+    out    : Option<W>,
+    /// If each row is flushed as soon as it's written
+    follow : bool
+}
+
+impl <W:Write> MarkdownOutputer<W> {
+    /**
+     * @brief Create a new markdown writer
+     * @param out The stream we want to write
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W) -> MarkdownOutputer<W>
+    {
+        return MarkdownOutputer {
+            out    : Some(out),
+            follow : false
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut MarkdownOutputer<W>
+    {
+        self.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for MarkdownOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult
+    {
+        if let Some(ref mut out) = self.out
+        {
+            let mut header = String::from("|");
+            let mut align  = String::from("|");
+            for &(ref name, ref ptype) in &table.schema.types
+            {
+                header.push_str(&format!(" {} |", escape_markdown(name)));
+                align.push_str(match ptype {
+                    &PrimitiveSchema::Int | &PrimitiveSchema::Float => " ---: |",
+                    &PrimitiveSchema::Str | &PrimitiveSchema::DateTime => " :--- |"
+                });
+            }
+
+            if table.schema.types.is_empty()
+            {
+                header.push_str("  |");
+                align.push_str(" --- |");
+            }
+
+            if out.write_all(format!("{}\n{}\n", header, align).as_bytes()).is_ok()
+            {
+                return OutputResult::Success();
+            }
+        }
+        return OutputResult::Fail();
+    }
+
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        let follow = self.follow;
+        match &mut self.out
+        {
+            &mut None              => OutputResult::Fail(),
+            &mut Some(ref mut out) => {
+                let cols = table.num_columns();
+                for raw_row in table
+                {
+                    let mut buf = String::from("|");
+                    for c in 0..cols
+                    {
+                        buf.push_str(&format!(" {} |", escape_markdown(&raw_row.value_at(c).to_human_readable())));
+                    }
+                    if cols == 0
+                    {
+                        buf.push_str("  |");
+                    }
+                    buf.push('\n');
+
+                    if write_row(out, &buf, follow).is_err()
+                    {
+                        return OutputResult::Fail();
+                    }
+                }
+
+                match out.flush()
+                {
+                    Ok(_)  => OutputResult::Success(),
+                    Err(_) => OutputResult::Fail()
+                }
+            }
+        }
+    }
+
+    fn get_output_result(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
@@ -1,5 +1,34 @@
 /*
  * Copyright (C) 2018, Hao Hou
  */
+use std::io::{self, Write};
 
 pub mod tablewriter;
+pub mod svwriter;
+pub mod jsonwriter;
+pub mod squerywriter;
+pub mod markdownwriter;
+pub mod htmlwriter;
+pub mod terminal;
+pub mod textstyle;
+
+/**
+ * @brief Write the text of a row to the stream
+ * @param out The stream to write
+ * @param text The row text
+ * @param flush If the stream is flushed after the row, which is only needed when the command
+ *        keeps running, since flushing each row of a large table is slow
+ * @return The IO result
+ **/
+fn write_row<W:Write>(out:&mut W, text:&str, flush:bool) -> io::Result<()>
+{
+    out.write_all(text.as_bytes())?;
+    if flush
+    {
+        out.flush()?;
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests;
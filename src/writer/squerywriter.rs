@@ -0,0 +1,139 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The self-describing squery output, which can be read by another squery instance
+ */
+use std::io::Write;
+use ::table::output::{Output, OutputResult};
+use ::table::table::Table;
+use ::table::primitive::PrimitiveData;
+use ::writer::write_row;
+
+/**
+ * @brief Append a cell to the buffer in the format that reader::squeryparser expects
+ * @param buf The buffer
+ * @param data The cell data
+ **/
+fn push_squery_cell(buf:&mut String, data:&PrimitiveData)
+{
+    match data
+    {
+        &PrimitiveData::Nothing()     => buf.push_str("\\N"),
+        &PrimitiveData::Str(ref what) => {
+            for ch in what.chars()
+            {
+                match ch
+                {
+                    '\t' => buf.push_str("\\t"),
+                    '\n' => buf.push_str("\\n"),
+                    '\r' => buf.push_str("\\r"),
+                    '\\' => buf.push_str("\\\\"),
+                    _    => buf.push(ch)
+                }
+            }
+        },
+        _                             => buf.push_str(&data.to_human_readable())
+    }
+}
+
+/**
+ * @brief The squery writer. The first line is the schema specification, and each of the
+ *        following lines is a row with tab seperated fields
+ **/
+pub struct SqueryOutputer<W:Write> {
+    /// The stream we are writing to
+    out    : Option<W>,
+    /// If each row is flushed as soon as it's written
+    follow : bool
+}
+
+impl <W:Write> SqueryOutputer<W> {
+    /**
+     * @brief Create a new squery writer
+     * @param out The stream we want to write
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W) -> SqueryOutputer<W>
+    {
+        return SqueryOutputer {
+            out    : Some(out),
+            follow : false
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut SqueryOutputer<W>
+    {
+        self.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for SqueryOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult
+    {
+        if let Some(ref mut out) = self.out
+        {
+            let spec = table.schema.to_spec() + "\n";
+            if out.write_all(spec.as_bytes()).is_ok()
+            {
+                return OutputResult::Success();
+            }
+        }
+        return OutputResult::Fail();
+    }
+
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult
+    {
+        return OutputResult::Success();
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        let follow = self.follow;
+        match &mut self.out
+        {
+            &mut None              => OutputResult::Fail(),
+            &mut Some(ref mut out) => {
+                let cols = table.num_columns();
+                for raw_row in table
+                {
+                    let mut buf = String::new();
+                    for c in 0..cols
+                    {
+                        if c > 0
+                        {
+                            buf.push('\t');
+                        }
+                        push_squery_cell(&mut buf, raw_row.value_at(c));
+                    }
+                    buf.push('\n');
+
+                    if write_row(out, &buf, follow).is_err()
+                    {
+                        return OutputResult::Fail();
+                    }
+                }
+
+                match out.flush()
+                {
+                    Ok(_)  => OutputResult::Success(),
+                    Err(_) => OutputResult::Fail()
+                }
+            }
+        }
+    }
+
+    fn get_output_result(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The seperated value output, for example CSV or TSV
+ */
+use std::borrow::Cow;
+use std::io::Write;
+use ::table::output::{Output, OutputResult};
+use ::table::table::Table;
+use ::writer::write_row;
+
+/**
+ * @brief Escape a cell for the CSV format (RFC 4180), the cell is quoted only when
+ *        it contains a delimiter, a quote or a line break
+ * @param text The cell text
+ * @return The escaped text
+ **/
+fn escape_csv(text:&str) -> Cow<'_, str>
+{
+    if !text.contains(&[',', '"', '\n', '\r'][..])
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut ret = String::with_capacity(text.len() + 2);
+    ret.push('"');
+    for ch in text.chars()
+    {
+        if ch == '"'
+        {
+            ret.push('"');
+        }
+        ret.push(ch);
+    }
+    ret.push('"');
+
+    return Cow::Owned(ret);
+}
+
+/**
+ * @brief Escape a cell for the TSV format. Since TSV has no quoting, the tab, line breaks
+ *        and the backslash itself are written as backslash escape sequences
+ * @param text The cell text
+ * @return The escaped text
+ **/
+fn escape_tsv(text:&str) -> Cow<'_, str>
+{
+    if !text.contains(&['\t', '\n', '\r', '\\'][..])
+    {
+        return Cow::Borrowed(text);
+    }
+
+    let mut ret = String::with_capacity(text.len() + 2);
+    for ch in text.chars()
+    {
+        match ch
+        {
+            '\t' => ret.push_str("\\t"),
+            '\n' => ret.push_str("\\n"),
+            '\r' => ret.push_str("\\r"),
+            '\\' => ret.push_str("\\\\"),
+            _    => ret.push(ch)
+        }
+    }
+
+    return Cow::Owned(ret);
+}
+
+/**
+ * @brief The shared implementation of the seperated value writers
+ **/
+struct SepValWriter<W:Write> {
+    /// The stream we are writing to
+    out    : Option<W>,
+    /// The field delimiter
+    delim  : &'static str,
+    /// How we escape a single cell
+    escape : fn(&str) -> Cow<'_, str>,
+    /// If we need to write the header row
+    header : bool,
+    /// If each line is flushed as soon as it's written
+    follow : bool
+}
+
+impl <W:Write> SepValWriter<W> {
+    fn write_line<'a, I>(&mut self, cells:I) -> OutputResult
+        where I : Iterator<Item = Cow<'a, str>>
+    {
+        if let Some(ref mut out) = self.out
+        {
+            let mut line = String::new();
+            for (idx, cell) in cells.enumerate()
+            {
+                if idx > 0
+                {
+                    line.push_str(self.delim);
+                }
+                line.push_str(&(self.escape)(&cell));
+            }
+            line.push('\n');
+
+            if write_row(out, &line, self.follow).is_ok()
+            {
+                return OutputResult::Success();
+            }
+        }
+        return OutputResult::Fail();
+    }
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult
+    {
+        if !self.header
+        {
+            return OutputResult::Success();
+        }
+
+        let schema = table.schema;
+        return self.write_line(schema.types.iter().map(|&(ref name, _)| Cow::Borrowed(name.as_str())));
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        let cols = table.num_columns();
+        for raw_row in table
+        {
+            if let OutputResult::Fail() = self.write_line((0..cols).map(|c| Cow::Owned(raw_row.value_at(c).to_human_readable())))
+            {
+                return OutputResult::Fail();
+            }
+        }
+
+        if let Some(ref mut out) = self.out
+        {
+            if out.flush().is_ok()
+            {
+                return OutputResult::Success();
+            }
+        }
+
+        return OutputResult::Fail();
+    }
+
+    fn take_output(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
+
+/**
+ * @brief The CSV writer, which writes the rows to the stream as soon as we pull them from the table
+ **/
+pub struct CsvOutputer<W:Write> {
+    writer : SepValWriter<W>
+}
+
+impl <W:Write> CsvOutputer<W> {
+    /**
+     * @brief Create a new CSV writer
+     * @param out The stream we want to write
+     * @param header If we want the column names as the first row
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W, header:bool) -> CsvOutputer<W>
+    {
+        return CsvOutputer {
+            writer : SepValWriter {
+                out    : Some(out),
+                delim  : ",",
+                escape : escape_csv,
+                header : header,
+                follow : false
+            }
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut CsvOutputer<W>
+    {
+        self.writer.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for CsvOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult { self.writer.write_schema(table) }
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult { OutputResult::Success() }
+    fn write_records(&mut self, table:&mut Table) -> OutputResult { self.writer.write_records(table) }
+    fn get_output_result(&mut self) -> W { self.writer.take_output() }
+}
+
+/**
+ * @brief The TSV writer, which writes the rows to the stream as soon as we pull them from the table
+ **/
+pub struct TsvOutputer<W:Write> {
+    writer : SepValWriter<W>
+}
+
+impl <W:Write> TsvOutputer<W> {
+    /**
+     * @brief Create a new TSV writer
+     * @param out The stream we want to write
+     * @param header If we want the column names as the first row
+     * @return The newly created writer
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W, header:bool) -> TsvOutputer<W>
+    {
+        return TsvOutputer {
+            writer : SepValWriter {
+                out    : Some(out),
+                delim  : "\t",
+                escape : escape_tsv,
+                header : header,
+                follow : false
+            }
+        };
+    }
+
+    /**
+     * @brief Set if each row is flushed as soon as it's written, so the rows of a command
+     *        that keeps running show up in time
+     * @param follow If the command keeps running
+     * @return The outputer itself
+     **/
+    pub fn follow(&mut self, follow:bool) -> &mut TsvOutputer<W>
+    {
+        self.writer.follow = follow;
+        return self;
+    }
+}
+
+impl <W:Write> Output for TsvOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult { self.writer.write_schema(table) }
+    fn preprocess(&mut self, _table:&mut Table) -> OutputResult { OutputResult::Success() }
+    fn write_records(&mut self, table:&mut Table) -> OutputResult { self.writer.write_records(table) }
+    fn get_output_result(&mut self) -> W { self.writer.take_output() }
+}
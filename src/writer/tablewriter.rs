@@ -3,144 +3,631 @@
  *
  * The table based output
  */
+extern crate unicode_width;
+extern crate unicode_segmentation;
+
 use std::mem::swap;
+use std::io::{self, Write};
+use self::unicode_width::UnicodeWidthStr;
+use self::unicode_segmentation::UnicodeSegmentation;
 use ::table::output::{Output, OutputResult};
 use ::table::table::Table;
+use ::table::schema::{TableSchema, PrimitiveSchema};
+use ::table::row::Row as TableRow;
+use ::writer::terminal::{get_terminal_width, stdout_is_terminal};
+use ::writer::textstyle::{TableStyle, HLine, Align, Color, ColorMode, ColorHint, NumberFormat, paint};
 
-type Cell = String;
+/// A cell in the text table, None if the value is missing
+type Cell = Option<String>;
 
 type Row = Vec<Cell>;
 
-pub struct TableResult {
-    header: Row,
-    body  : Vec<Row>
+/**
+ * @brief The minimal width of a column when we shrink the table to fit the max width
+ **/
+const MIN_COLUMN_WIDTH : usize = 4;
+
+/**
+ * @brief The text of the missing values in the coloured table when no null text is given,
+ *        so that the dimmed missing value can be seen and told from the empty string
+ **/
+const COLORED_NULL_TEXT : &str = "-";
+
+/**
+ * @brief What we do when the cell is wider than its column
+ **/
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// Wrap the text into multiple lines, on word boundaries if possible
+    Wrap,
+    /// Cut the text and put an ellipsis at the end
+    Truncate
 }
 
-impl TableResult {
-    fn determine_layout(&self, _max_width:usize, min_width:usize) -> Vec<usize>
+/**
+ * @brief Get the number of terminal columns that the text takes, the East Asian wide
+ *        characters take two columns, and the combining marks and zero-width characters
+ *        take none
+ * @param text The text
+ * @return The width
+ **/
+pub fn text_width(text:&str) -> usize
+{
+    return UnicodeWidthStr::width(text);
+}
+
+/**
+ * @brief Split the cell text into the lines, a cell may have line breaks in it
+ * @param text The cell text
+ * @return The lines without the line breaks
+ **/
+fn cell_lines(text:&str) -> impl Iterator<Item = &str>
+{
+    return text.split('\n').map(|line| line.trim_end_matches('\r'));
+}
+
+/**
+ * @brief Get the width of the cell, which is the width of its widest line
+ * @param text The cell text
+ * @return The width
+ **/
+pub fn cell_width(text:&str) -> usize
+{
+    return cell_lines(text).map(text_width).max().unwrap_or(0);
+}
+
+/**
+ * @brief Split the text on a grapheme boundary so that the first part is no wider than
+ *        the given width
+ * @param text The text to split
+ * @param width The max width of the first part
+ * @return The two parts
+ **/
+pub fn split_at_width(text:&str, width:usize) -> (&str, &str)
+{
+    let mut current = 0;
+    for (pos, grapheme) in text.grapheme_indices(true)
     {
-        let mut ret = vec![0usize; self.header.len()];
+        current += text_width(grapheme);
+        if current > width
+        {
+            return text.split_at(pos);
+        }
+    }
+    return (text, "");
+}
 
-        let mut total_length = ret.len() + 1;
+/**
+ * @brief Wrap a line into lines no wider than the width, we break on the spaces
+ *        when possible
+ * @param text The line to wrap
+ * @param width The max width of each line
+ * @return The wrapped lines
+ **/
+fn wrap_line(text:&str, width:usize) -> Vec<String>
+{
+    let mut ret = Vec::new();
+    let mut rest = text;
 
-        for idx in 0..self.header.len()
+    while width > 0 && text_width(rest) > width
+    {
+        let (mut head, mut tail) = split_at_width(rest, width);
+
+        if head.len() == 0
         {
-            if self.header[idx].len() > ret[idx]
+            /* A wide character that doesn't fit the column, we put it on its own line */
+            let first = rest.graphemes(true).next().unwrap_or("");
+            head = &rest[..first.len()];
+            tail = &rest[first.len()..];
+        }
+
+        if tail.starts_with(' ')
+        {
+            tail = tail.trim_start_matches(' ');
+        }
+        else if let Some(pos) = head.rfind(' ')
+        {
+            if head[..pos].trim_end_matches(' ').len() > 0
             {
-                ret[idx] = self.header[idx].len()
+                tail = &rest[pos + 1..];
+                head = &rest[..pos];
             }
         }
 
-        for r in &self.body 
+        ret.push(head.trim_end_matches(' ').to_string());
+        rest = tail;
+    }
+
+    if ret.len() == 0 || rest.len() > 0
+    {
+        ret.push(rest.to_string());
+    }
+
+    return ret;
+}
+
+/**
+ * @brief Wrap the text into lines no wider than the width, the line breaks in the text
+ *        are kept and each line is wrapped on its own
+ * @param text The text to wrap
+ * @param width The max width of each line
+ * @return The wrapped lines
+ **/
+pub fn wrap_text(text:&str, width:usize) -> Vec<String>
+{
+    return cell_lines(text).flat_map(|line| wrap_line(line, width)).collect();
+}
+
+/**
+ * @brief Cut the text to the width, the last column is replaced with an ellipsis. Only
+ *        the first line is kept if the text has line breaks
+ * @param text The text to truncate
+ * @param width The max width
+ * @return The truncated text
+ **/
+pub fn truncate_text(text:&str, width:usize) -> String
+{
+    let mut lines = cell_lines(text);
+    let first = lines.next().unwrap_or("");
+    let more = lines.next().is_some();
+
+    if !more && text_width(first) <= width
+    {
+        return first.to_string();
+    }
+
+    if width == 0
+    {
+        return String::new();
+    }
+
+    if text_width(first) < width
+    {
+        return first.to_string() + "\u{2026}";
+    }
+
+    return split_at_width(first, width - 1).0.to_string() + "\u{2026}";
+}
+
+/**
+ * @brief The formatting and drawing state shared by the buffered and the streaming text table
+ **/
+struct TextRenderer {
+    /// How we handle the overflowed cells for each column
+    overflow: Vec<Overflow>,
+    /// How the cells are aligned in each column
+    align   : Vec<Align>,
+    /// The style of the table
+    style   : TableStyle,
+    /// When we use the ANSI colours
+    color   : ColorMode,
+    /// The text we show for the missing values
+    null_text : String,
+    /// How we format the numbers
+    number_format : NumberFormat,
+    /// The colour hints for the rows
+    color_hints : Vec<ColorHint>,
+    /// The column index for each colour hint, None if the column doesn't exist
+    hint_columns : Vec<Option<usize>>
+}
+
+impl TextRenderer {
+    fn new() -> TextRenderer
+    {
+        return TextRenderer {
+            overflow      : Vec::new(),
+            align         : Vec::new(),
+            style         : Default::default(),
+            color         : ColorMode::Never,
+            null_text     : String::new(),
+            number_format : Default::default(),
+            color_hints   : Vec::new(),
+            hint_columns  : Vec::new()
+        };
+    }
+
+    fn set_overflow(&mut self, col:usize, mode:Overflow)
+    {
+        if self.overflow.len() <= col
+        {
+            self.overflow.resize(col + 1, Overflow::Wrap);
+        }
+        self.overflow[col] = mode;
+    }
+
+    fn get_overflow(&self, col:usize) -> Overflow
+    {
+        return *self.overflow.get(col).unwrap_or(&Overflow::Wrap);
+    }
+
+    fn is_colored(&self) -> bool
+    {
+        match self.color
+        {
+            ColorMode::Always => true,
+            ColorMode::Never  => false,
+            ColorMode::Auto   => stdout_is_terminal()
+        }
+    }
+
+    /**
+     * @brief Prepare the renderer for the table schema
+     * @param schema The table schema
+     * @return The header row
+     **/
+    fn setup(&mut self, schema:&TableSchema) -> Row
+    {
+        let mut header = Row::new();
+        for &(ref name, ref ptype) in &schema.types 
         {
-            for (idx, val) in r.into_iter().enumerate()
+            header.push(Some(name.to_string()));
+            self.align.push(match ptype {
+                &PrimitiveSchema::Int | &PrimitiveSchema::Float => Align::Right,
+                &PrimitiveSchema::Str | &PrimitiveSchema::DateTime => Align::Left
+            });
+        }
+
+        let color_hints = &self.color_hints;
+        self.hint_columns = color_hints.iter().map(|hint| {
+            schema.types.iter().position(|&(ref name, _)| name == hint.condition.column())
+        }).collect();
+
+        return header;
+    }
+
+    /**
+     * @brief Format a table row into the text cells
+     * @param raw_row The row to format
+     * @param cols The number of columns
+     * @return The text cells and the colour of the row
+     **/
+    fn format_row(&self, raw_row:&TableRow, cols:usize) -> (Row, Option<Color>)
+    {
+        let mut cur_row = Vec::new();
+        for c in 0..cols
+        {
+            cur_row.push(self.number_format.format(raw_row.value_at(c)));
+        }
+
+        for (hint, col) in self.color_hints.iter().zip(self.hint_columns.iter())
+        {
+            if let &Some(col) = col
             {
-                if val.len() > ret[idx]
+                if hint.condition.check(raw_row.value_at(col))
                 {
-                    ret[idx] = val.len();
+                    return (cur_row, Some(hint.color));
                 }
             }
         }
 
+        return (cur_row, None);
+    }
+
+    fn cell_text<'a>(&'a self, cell:&'a Cell) -> &'a str
+    {
+        match cell
+        {
+            &Some(ref text) => text,
+            &None           => &self.null_text
+        }
+    }
+
+    /**
+     * @brief Get the text we draw for the cell, the missing value has a placeholder in the
+     *        coloured table if the null text is empty
+     * @param cell The cell
+     * @param colored If the table is coloured
+     * @return The text
+     **/
+    fn display_text<'a>(&'a self, cell:&'a Cell, colored:bool) -> &'a str
+    {
+        if cell.is_none() && colored && self.null_text.len() == 0
+        {
+            return COLORED_NULL_TEXT;
+        }
+        return self.cell_text(cell);
+    }
+
+    /**
+     * @brief Widen the layout so that the row fits
+     * @param layout The column widths
+     * @param row The row
+     **/
+    fn measure_row(&self, layout:&mut [usize], row:&Row)
+    {
+        for (idx, val) in row.iter().enumerate()
+        {
+            if cell_width(self.cell_text(val)) > layout[idx]
+            {
+                layout[idx] = cell_width(self.cell_text(val));
+            }
+        }
+    }
+
+    /**
+     * @brief Adjust the natural column widths, so that the table is between the min and max width
+     * @param natural The natural width of each column
+     * @param max_width The max width of the table
+     * @param min_width The min width of the table
+     * @return The final layout
+     **/
+    fn fit_layout(&self, natural:Vec<usize>, max_width:usize, min_width:usize) -> Vec<usize>
+    {
+        let mut ret = natural;
+
+        let chars = self.style.border.box_chars();
+        let mut total_length = 2 * text_width(chars.edge) + ret.len().saturating_sub(1) * text_width(chars.inner);
+
         for col in &ret 
         {
             total_length += col;
         }
 
-        /* TODO: what if the total_legnth is larger than the max_width ? */
+        /* Shrink the widest column until the table fits, or nothing can be shrinked */
+        while total_length > max_width
+        {
+            let mut widest = 0;
+            for idx in 0..ret.len()
+            {
+                if ret[idx] > ret[widest]
+                {
+                    widest = idx;
+                }
+            }
+
+            if ret.len() == 0 || ret[widest] <= MIN_COLUMN_WIDTH
+            {
+                break;
+            }
 
-        if total_length < min_width
+            ret[widest] -= 1;
+            total_length -= 1;
+        }
+
+        /* Spread the extra width evenly, the leftover goes to the leading columns */
+        if total_length < min_width && ret.len() > 0
         {
             let rem = min_width - total_length;
+            let cols = ret.len();
 
-            for i in 0..ret.len()
+            for (i, width) in ret.iter_mut().enumerate()
             {
-                ret[i] += rem / ret.len() + (if rem % ret.len() < i { 1 } else { 0 });
+                *width += rem / cols + (if i < rem % cols { 1 } else { 0 });
             }
         }
 
         return ret
     }
 
-    fn print_hl(&self, layout:&Vec<usize>)
+    fn render_hl(&self, layout:&[usize], line:HLine) -> String
     {
-        print!("+");
-        for i in 0..layout.len() 
+        let chars = self.style.border.box_chars();
+        let mut ret = String::new();
+
+        if chars.horizontal.len() == 0
         {
-            for _j in 0..layout[i]
+            return ret;
+        }
+
+        let junctions = chars.junctions(line);
+
+        ret.push_str(junctions[0]);
+        for (i, &width) in layout.iter().enumerate()
+        {
+            if i > 0
             {
-                print!("-");
+                ret.push_str(junctions[1]);
+            }
+            for _j in 0..width
+            {
+                ret.push_str(chars.horizontal);
             }
-            print!("+");
         }
-        print!("\n");
+        ret.push_str(junctions[2]);
+        ret.push('\n');
+
+        return ret;
     }
 
-    fn print_row(&self, layout:&Vec<usize>, data:&Row)
+    fn render_row(&self, layout:&[usize], data:&Row, codes:&[&str], colored:bool) -> String
     {
-        print!("|");
-        let mut rem = Vec::<usize>::new();
-        let mut total_rem = 0;
+        let chars = self.style.border.box_chars();
+        let mut lines = Vec::<Vec<String>>::new();
+        let mut height = 1;
+        let mut ret = String::new();
 
-        for i in 0..layout.len() 
+        for i in 0..layout.len()
         {
-            rem.push(data[i].len());
-            total_rem += rem[i];
+            let text = self.display_text(&data[i], colored);
+            lines.push(match self.get_overflow(i)
+            {
+                Overflow::Wrap     => wrap_text(text, layout[i]),
+                Overflow::Truncate => vec![truncate_text(text, layout[i])]
+            });
+
+            if lines[i].len() > height
+            {
+                height = lines[i].len();
+            }
         }
 
-        while total_rem > 0
+        for line in 0..height
         {
+            ret.push_str(chars.edge);
             for i in 0..layout.len()
             {
-                let width = layout[i];
-                let text  = &data[i];
+                if i > 0
+                {
+                    ret.push_str(chars.inner);
+                }
 
-                let current = if width < rem[i] { width } else { rem[i] };
-                let left = (width - current) / 2;
-                let right = width - left - current;
+                let width = layout[i];
+                let text  = lines[i].get(line).map(|s| s.as_str()).unwrap_or("");
 
-                for _ in 0..left 
+                let current = text_width(text);
+                let (left, right) = match self.align.get(i).unwrap_or(&Align::Center)
                 {
-                    print!(" ");
-                }
+                    &Align::Left   => (0, width.saturating_sub(current)),
+                    &Align::Right  => (width.saturating_sub(current), 0),
+                    &Align::Center => {
+                        let left = width.saturating_sub(current) / 2;
+                        (left, width.saturating_sub(left + current))
+                    }
+                };
 
-                print!("{}", &text[(text.len() - rem[i])..(text.len() - rem[i] + current)]);
-                
-                for _ in 0..right
+                let mut cell_codes = codes.to_vec();
+                if data[i].is_none()
                 {
-                    print!(" ");
+                    cell_codes.push("2");
                 }
 
-                print!("|");
-                
-                rem[i] -= current;
-                total_rem -= current;
-
+                ret.push_str(&format!("{:left$}{}{:right$}", "", 
+                                      if colored { paint(text, &cell_codes) } else { text.to_string() },
+                                      "", left = left, right = right));
             }
-            print!("\n");
+            ret.push_str(chars.edge);
+            ret.push('\n');
         }
+
+        return ret;
     }
 
+    fn render_body_row(&self, layout:&[usize], data:&Row, color:Option<Color>, colored:bool) -> String
+    {
+        let codes = match color
+        {
+            Some(color) => vec![color.ansi_code()],
+            None        => vec![]
+        };
+
+        return self.render_row(layout, data, &codes, colored);
+    }
+}
+
+pub struct TableResult {
+    header  : Row,
+    body    : Vec<Row>,
+    /// The natural width of each column, which is measured in the preprocess pass
+    natural : Vec<usize>,
+    /// The colour of each row in the body
+    row_colors : Vec<Option<Color>>,
+    /// How we draw the table
+    renderer: TextRenderer
+}
+
+impl TableResult {
+    /**
+     * @brief Set how we handle the cell that is wider than the column
+     * @param col The column index
+     * @param mode The overflow mode
+     **/
     #[allow(dead_code)]
-    pub fn print_text_table(&self, max_width:usize, min_width:usize)
+    pub fn set_overflow(&mut self, col:usize, mode:Overflow)
+    {
+        self.renderer.set_overflow(col, mode);
+    }
+
+    /**
+     * @brief Paint the row in the colour, which overrides the colour hints
+     * @param idx The row index
+     * @param color The colour, None for the default colour
+     **/
+    #[allow(dead_code)]
+    pub fn set_row_color(&mut self, idx:usize, color:Option<Color>)
+    {
+        if idx < self.row_colors.len()
+        {
+            self.row_colors[idx] = color;
+        }
+    }
+
+    /**
+     * @brief Fit the column widths measured in the preprocess pass into the table width
+     * @param max_width The max width of the table
+     * @param min_width The min width of the table
+     * @return The layout
+     **/
+    fn determine_layout(&self, max_width:usize, min_width:usize) -> Vec<usize>
+    {
+        return self.renderer.fit_layout(self.natural.clone(), max_width, min_width);
+    }
+
+    /**
+     * @brief Draw the table as text
+     * @param max_width The max width of the table
+     * @param min_width The min width of the table
+     * @return The text of the table
+     **/
+    pub fn render_text_table(&self, max_width:usize, min_width:usize) -> String
     {
         let layout = self.determine_layout(max_width, min_width);
+        let colored = self.renderer.is_colored();
+        let renderer = &self.renderer;
+        let mut ret = String::new();
 
-        self.print_hl(&layout);
-        self.print_row(&layout, &self.header);
-        self.print_hl(&layout);
+        ret.push_str(&renderer.render_hl(&layout, HLine::Top));
+        ret.push_str(&renderer.render_row(&layout, &self.header, &["1"], colored));
+        ret.push_str(&renderer.render_hl(&layout, HLine::Middle));
 
-        for row in &self.body 
+        for (idx, row) in self.body.iter().enumerate()
         {
-            self.print_row(&layout, row);
-            self.print_hl(&layout);
+            if idx > 0 && renderer.style.row_separator
+            {
+                ret.push_str(&renderer.render_hl(&layout, HLine::Middle));
+            }
+
+            ret.push_str(&renderer.render_body_row(&layout, row, self.row_colors[idx], colored));
         }
+
+        ret.push_str(&renderer.render_hl(&layout, HLine::Bottom));
+
+        return ret;
+    }
+
+    /**
+     * @brief Print the table to the standard output
+     * @param max_width The max width of the table
+     * @param min_width The min width of the table
+     * @note The reader may go away before the table ends, e.g. squery -- ps aux | head, so
+     *       the broken pipe is not an error
+     **/
+    #[allow(dead_code)]
+    pub fn print_text_table(&self, max_width:usize, min_width:usize)
+    {
+        let text = self.render_text_table(max_width, min_width);
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        if let Err(err) = out.write_all(text.as_bytes()).and_then(|_| out.flush())
+        {
+            if err.kind() != io::ErrorKind::BrokenPipe
+            {
+                eprintln!("squery: can not write the table: {}", err);
+            }
+        }
+    }
+
+    /**
+     * @brief Print the table and fit it into the width of the terminal
+     * @param min_width The min width of the table
+     * @note If we can not detect the terminal width, the table width is not limited
+     **/
+    #[allow(dead_code)]
+    pub fn print_terminal_table(&self, min_width:usize)
+    {
+        self.print_text_table(get_terminal_width().unwrap_or(usize::MAX), min_width);
     }
 }
 
+/**
+ * @brief The text table writer that keeps the rows until the table is printed. The column
+ *        widths are decided in the preprocess pass, which looks at every row we are going
+ *        to print
+ **/
 #[allow(dead_code)]
 pub struct TableOutputer {
-    result : Option<TableResult>,
+    result : Option<TableResult>
 }
 
 impl TableOutputer {
@@ -149,11 +636,77 @@ impl TableOutputer {
     {
         return TableOutputer {
             result: Some(TableResult {
-                header : Vec::new(),
-                body   : Vec::new()
+                header     : Vec::new(),
+                body       : Vec::new(),
+                natural    : Vec::new(),
+                row_colors : Vec::new(),
+                renderer   : TextRenderer::new()
             })
         };
     }
+
+    fn renderer(&mut self) -> &mut TextRenderer
+    {
+        return &mut self.result.as_mut().unwrap().renderer;
+    }
+
+    /**
+     * @brief Set how the numbers should be formatted
+     * @param format The number format
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn number_format(&mut self, format:NumberFormat) -> &mut TableOutputer
+    {
+        self.renderer().number_format = format;
+        return self;
+    }
+
+    /**
+     * @brief Set the border style of the table
+     * @param style The table style
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn style(&mut self, style:TableStyle) -> &mut TableOutputer
+    {
+        self.renderer().style = style;
+        return self;
+    }
+
+    /**
+     * @brief Set when the ANSI colours are used
+     * @param mode The colour mode
+     * @return The outputer itself
+     **/
+    pub fn color_mode(&mut self, mode:ColorMode) -> &mut TableOutputer
+    {
+        self.renderer().color = mode;
+        return self;
+    }
+
+    /**
+     * @brief Set the text for the missing values
+     * @param text The text
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn null_text(&mut self, text:&str) -> &mut TableOutputer
+    {
+        self.renderer().null_text = text.to_string();
+        return self;
+    }
+
+    /**
+     * @brief Add a colour hint, the first matched hint decides the colour of the row
+     * @param hint The colour hint
+     * @return The outputer itself
+     **/
+    pub fn add_color_hint(&mut self, hint:ColorHint) -> &mut TableOutputer
+    {
+        self.renderer().color_hints.push(hint);
+        return self;
+    }
 }
 
 impl Output for TableOutputer {
@@ -165,19 +718,32 @@ impl Output for TableOutputer {
         {
             &mut None          => OutputResult::Fail(),
             &mut Some(ref mut result) => {
-                let schema = table.schema;
-                for &(ref name, _) in &schema.types 
-                {
-                    result.header.push(name.to_string());
-                }
+                result.header = result.renderer.setup(table.schema);
+                result.natural = vec![0usize; result.header.len()];
+                result.renderer.measure_row(&mut result.natural, &result.header);
                 OutputResult::Success()
             }
         }
     }
 
-    fn preprocess(&mut self, _table:&mut Table) -> OutputResult 
+    fn preprocess(&mut self, table:&mut Table) -> OutputResult 
     {
-        return OutputResult::Success();
+        match &mut self.result
+        {
+            &mut None          => OutputResult::Fail(),
+            &mut Some(ref mut result) => {
+                let cols = table.num_columns();
+
+                /* All the rows are kept until the table is printed anyway, so we measure them
+                 * all rather than a sample */
+                for raw_row in table.peek(usize::MAX)
+                {
+                    let (cur_row, _) = result.renderer.format_row(raw_row, cols);
+                    result.renderer.measure_row(&mut result.natural, &cur_row);
+                }
+                OutputResult::Success()
+            }
+        }
     }
 
     fn write_records(&mut self, table:&mut Table) -> OutputResult 
@@ -189,14 +755,9 @@ impl Output for TableOutputer {
                 let cols = table.num_columns();
                 for raw_row in table
                 {
-                    let mut cur_row = Vec::new();
-                    for c in 0..cols
-                    {
-                        let cell_data = raw_row.value_at(c);
-
-                        cur_row.push(cell_data.to_human_readable());
-                    }
+                    let (cur_row, row_color) = result.renderer.format_row(raw_row, cols);
                     result.body.push(cur_row);
+                    result.row_colors.push(row_color);
                 }
                 OutputResult::Success()
             }
@@ -212,3 +773,286 @@ impl Output for TableOutputer {
         ret.unwrap()
     }
 }
+
+/**
+ * @brief The text table writer that prints the rows as soon as we pull them from the table.
+ *        The column widths are decided in the preprocess pass by peeking the first few rows
+ *        and the width hints, the rows are wrapped or truncated to fit the columns
+ **/
+pub struct StreamTableOutputer<W:Write> {
+    /// The stream we are writing to
+    out : Option<W>,
+    /// How we draw the table
+    renderer : TextRenderer,
+    /// The header row
+    header : Row,
+    /// How many rows we look at before we decide the layout
+    sample_rows : usize,
+    /// The layout decided in the preprocess pass
+    layout : Option<Vec<usize>>,
+    /// The width hint for the columns, which overrides the sampled width
+    width_hints : Vec<Option<usize>>,
+    /// The max width of the table
+    max_width : usize,
+    /// The min width of the table
+    min_width : usize
+}
+
+impl <W:Write> StreamTableOutputer<W> {
+    /**
+     * @brief Create a new streaming text table writer
+     * @param out The stream we want to write
+     * @param sample_rows How many rows we use to decide the column widths
+     * @return The newly created writer
+     * @note By default the table is fit into the terminal width. If the table is already loaded
+     *       to memory, all the rows are used to decide the column widths
+     **/
+    #[allow(dead_code)]
+    pub fn create(out:W, sample_rows:usize) -> StreamTableOutputer<W>
+    {
+        return StreamTableOutputer {
+            out         : Some(out),
+            renderer    : TextRenderer::new(),
+            header      : Vec::new(),
+            sample_rows : sample_rows,
+            layout      : None,
+            width_hints : Vec::new(),
+            max_width   : get_terminal_width().unwrap_or(usize::MAX),
+            min_width   : 0
+        };
+    }
+
+    /**
+     * @brief Set the width limit of the table
+     * @param max_width The max width
+     * @param min_width The min width
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn table_width(&mut self, max_width:usize, min_width:usize) -> &mut StreamTableOutputer<W>
+    {
+        self.max_width = max_width;
+        self.min_width = min_width;
+        return self;
+    }
+
+    /**
+     * @brief Give the column a fixed width instead of the sampled one
+     * @param col The column index
+     * @param width The column width
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn column_width(&mut self, col:usize, width:usize) -> &mut StreamTableOutputer<W>
+    {
+        if self.width_hints.len() <= col
+        {
+            self.width_hints.resize(col + 1, None);
+        }
+        self.width_hints[col] = Some(width);
+        return self;
+    }
+
+    /**
+     * @brief Set how we handle the cell that is wider than the column
+     * @param col The column index
+     * @param mode The overflow mode
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn overflow(&mut self, col:usize, mode:Overflow) -> &mut StreamTableOutputer<W>
+    {
+        self.renderer.set_overflow(col, mode);
+        return self;
+    }
+
+    /**
+     * @brief Set how the numbers should be formatted
+     * @param format The number format
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn number_format(&mut self, format:NumberFormat) -> &mut StreamTableOutputer<W>
+    {
+        self.renderer.number_format = format;
+        return self;
+    }
+
+    /**
+     * @brief Set the border style of the table
+     * @param style The table style
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn style(&mut self, style:TableStyle) -> &mut StreamTableOutputer<W>
+    {
+        self.renderer.style = style;
+        return self;
+    }
+
+    /**
+     * @brief Set when the ANSI colours are used
+     * @param mode The colour mode
+     * @return The outputer itself
+     **/
+    pub fn color_mode(&mut self, mode:ColorMode) -> &mut StreamTableOutputer<W>
+    {
+        self.renderer.color = mode;
+        return self;
+    }
+
+    /**
+     * @brief Set the text for the missing values
+     * @param text The text
+     * @return The outputer itself
+     **/
+    #[allow(dead_code)]
+    pub fn null_text(&mut self, text:&str) -> &mut StreamTableOutputer<W>
+    {
+        self.renderer.null_text = text.to_string();
+        return self;
+    }
+
+    /**
+     * @brief Add a colour hint, the first matched hint decides the colour of the row
+     * @param hint The colour hint
+     * @return The outputer itself
+     **/
+    pub fn add_color_hint(&mut self, hint:ColorHint) -> &mut StreamTableOutputer<W>
+    {
+        self.renderer.color_hints.push(hint);
+        return self;
+    }
+
+    /**
+     * @brief Decide the column widths from the header, the sampled rows and the width hints
+     * @param sampled The sampled rows
+     * @return The layout
+     **/
+    fn determine_layout(&self, sampled:&[(Row, Option<Color>)]) -> Vec<usize>
+    {
+        let mut ret = vec![0usize; self.header.len()];
+
+        self.renderer.measure_row(&mut ret, &self.header);
+
+        for &(ref row, _) in sampled
+        {
+            self.renderer.measure_row(&mut ret, row);
+        }
+
+        for (idx, hint) in self.width_hints.iter().enumerate()
+        {
+            if let (&Some(width), true) = (hint, idx < ret.len())
+            {
+                ret[idx] = width;
+            }
+        }
+
+        return self.renderer.fit_layout(ret, self.max_width, self.min_width);
+    }
+
+    /**
+     * @brief Write the table header
+     * @param out The stream to write
+     * @param layout The table layout
+     * @return If the write success
+     **/
+    fn write_head(&self, out:&mut W, layout:&[usize]) -> bool
+    {
+        let colored = self.renderer.is_colored();
+
+        let mut buf = self.renderer.render_hl(layout, HLine::Top);
+        buf.push_str(&self.renderer.render_row(layout, &self.header, &["1"], colored));
+        buf.push_str(&self.renderer.render_hl(layout, HLine::Middle));
+
+        return out.write_all(buf.as_bytes()).is_ok();
+    }
+
+    /**
+     * @brief Write a single row
+     * @param out The stream to write
+     * @param layout The table layout
+     * @param row The row to write
+     * @param first If this is the first row of the table
+     * @return If the write success
+     **/
+    fn write_row(&self, out:&mut W, layout:&[usize], row:&(Row, Option<Color>), first:bool) -> bool
+    {
+        let colored = self.renderer.is_colored();
+        let mut buf = String::new();
+
+        if !first && self.renderer.style.row_separator
+        {
+            buf.push_str(&self.renderer.render_hl(layout, HLine::Middle));
+        }
+        buf.push_str(&self.renderer.render_body_row(layout, &row.0, row.1, colored));
+
+        return out.write_all(buf.as_bytes()).and_then(|_| out.flush()).is_ok();
+    }
+}
+
+impl <W:Write> Output for StreamTableOutputer<W> {
+    type IOResult = W;
+
+    fn write_schema(&mut self, table:&mut Table) -> OutputResult
+    {
+        self.header = self.renderer.setup(table.schema);
+        return OutputResult::Success();
+    }
+
+    fn preprocess(&mut self, table:&mut Table) -> OutputResult
+    {
+        let cols = table.num_columns();
+        let sample_rows = if table.is_loaded() { usize::MAX } else { self.sample_rows };
+
+        let sampled : Vec<_> = table.peek(sample_rows).iter().map(|row| self.renderer.format_row(row, cols)).collect();
+
+        self.layout = Some(self.determine_layout(&sampled));
+
+        return OutputResult::Success();
+    }
+
+    fn write_records(&mut self, table:&mut Table) -> OutputResult
+    {
+        let (mut out, layout) = match (self.out.take(), self.layout.take())
+        {
+            (Some(out), Some(layout)) => (out, layout),
+            _                         => return OutputResult::Fail()
+        };
+
+        let cols = table.num_columns();
+        let mut failed = !self.write_head(&mut out, &layout);
+
+        if !failed
+        {
+            for (idx, raw_row) in table.enumerate()
+            {
+                if !self.write_row(&mut out, &layout, &self.renderer.format_row(raw_row, cols), idx == 0)
+                {
+                    failed = true;
+                    break;
+                }
+            }
+        }
+
+        if !failed
+        {
+            let bottom = self.renderer.render_hl(&layout, HLine::Bottom);
+            failed = out.write_all(bottom.as_bytes()).and_then(|_| out.flush()).is_err();
+        }
+
+        self.out = Some(out);
+
+        if failed
+        {
+            return OutputResult::Fail();
+        }
+
+        return OutputResult::Success();
+    }
+
+    fn get_output_result(&mut self) -> W
+    {
+        return self.out.take().unwrap();
+    }
+}
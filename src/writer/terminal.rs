@@ -0,0 +1,95 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The helpers for the terminal we are writing to
+ */
+extern crate libc;
+
+use std::env;
+
+/**
+ * @brief Ask the kernel for the width of the terminal attached to the standard output
+ * @return The width or None if the standard output is not a terminal
+ **/
+#[cfg(unix)]
+fn query_tty_width() -> Option<usize>
+{
+    let mut ws = libc::winsize {
+        ws_row    : 0,
+        ws_col    : 0,
+        ws_xpixel : 0,
+        ws_ypixel : 0
+    };
+
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) } == 0 && ws.ws_col > 0
+    {
+        return Some(ws.ws_col as usize);
+    }
+
+    return None;
+}
+
+#[cfg(not(unix))]
+fn query_tty_width() -> Option<usize>
+{
+    return None;
+}
+
+/**
+ * @brief Detect the width of the terminal. We try TIOCGWINSZ first, and then
+ *        the $COLUMNS environment variable
+ * @return The terminal width or None if we can not figure it out
+ **/
+#[allow(dead_code)]
+pub fn get_terminal_width() -> Option<usize>
+{
+    if let Some(width) = query_tty_width()
+    {
+        return Some(width);
+    }
+
+    if let Ok(columns) = env::var("COLUMNS")
+    {
+        if let Ok(width) = columns.trim().parse::<usize>()
+        {
+            if width > 0
+            {
+                return Some(width);
+            }
+        }
+    }
+
+    return None;
+}
+
+/**
+ * @brief Check if the standard output is a terminal
+ * @return The check result
+ **/
+#[cfg(unix)]
+pub fn stdout_is_terminal() -> bool
+{
+    return unsafe { libc::isatty(libc::STDOUT_FILENO) } == 1;
+}
+
+#[cfg(not(unix))]
+pub fn stdout_is_terminal() -> bool
+{
+    return false;
+}
+
+/**
+ * @brief Check if the standard input is a terminal
+ * @return The check result
+ **/
+#[cfg(unix)]
+pub fn stdin_is_terminal() -> bool
+{
+    return unsafe { libc::isatty(libc::STDIN_FILENO) } == 1;
+}
+
+#[cfg(not(unix))]
+pub fn stdin_is_terminal() -> bool
+{
+    return false;
+}
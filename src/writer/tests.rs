@@ -0,0 +1,810 @@
+// Copyright (C) 2018, Hao Hou
+//
+// The writer tests, which dump a small in-memory table with each writer and check the text
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Cursor, Write};
+use std::rc::Rc;
+use ::cli::options::Options;
+use ::cli::runner::open_input;
+use ::table::datetime::parse_datetime;
+use ::table::input::Input;
+use ::table::output::Output;
+use ::table::primitive::PrimitiveData;
+use ::table::row::Row;
+use ::table::schema::TableSchema;
+use ::table::table::{Table, TableDataSource};
+use ::writer::svwriter::{CsvOutputer, TsvOutputer};
+use ::writer::squerywriter::SqueryOutputer;
+use ::writer::markdownwriter::MarkdownOutputer;
+use ::writer::htmlwriter::HtmlOutputer;
+use ::writer::jsonwriter::{JsonOutputer, NdjsonOutputer};
+use ::writer::tablewriter::{TableOutputer, StreamTableOutputer, TableResult, Overflow, wrap_text, truncate_text, split_at_width};
+use ::writer::textstyle::{Color, ColorHint, ColorMode, Border, TableStyle, NumberFormat};
+use ::table::filter::Condition;
+
+/**
+ * @brief The input that gives the rows we have prepared
+ **/
+struct RowsInput {
+    rows : VecDeque<Vec<PrimitiveData>>
+}
+
+impl Input for RowsInput {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        return None;
+    }
+
+    fn parse_next_row<'schema>(&mut self, schema:&'schema TableSchema) -> Option<Row<'schema>>
+    {
+        let values = self.rows.pop_front()?;
+        let mut row = Row::empty(schema);
+
+        for (idx, value) in values.into_iter().enumerate()
+        {
+            assert!(row.set_value(idx, value), "the value of column {} doesn't match the schema", idx);
+        }
+
+        return Some(row);
+    }
+}
+
+/**
+ * @brief Dump the rows with the writer
+ * @param spec The schema specification of the table
+ * @param rows The rows
+ * @param output The writer, which writes to a byte buffer
+ * @return The text the writer produces
+ **/
+fn dump<O>(spec:&str, rows:Vec<Vec<PrimitiveData>>, output:O) -> String
+    where O : Output<IOResult = Vec<u8>>
+{
+    return dump_schema(&TableSchema::from_spec(spec).unwrap(), rows, output);
+}
+
+/**
+ * @brief Dump the rows of the schema with the writer
+ * @param schema The table schema
+ * @param rows The rows
+ * @param output The writer, which writes to a byte buffer
+ * @return The text the writer produces
+ **/
+fn dump_schema<O>(schema:&TableSchema, rows:Vec<Vec<PrimitiveData>>, mut output:O) -> String
+    where O : Output<IOResult = Vec<u8>>
+{
+    let mut input = RowsInput { rows : rows.into_iter().collect() };
+    let mut table = Table::empty(schema, TableDataSource::Parser(&mut input, false));
+
+    let out = table.dump(&mut output).expect("the writer fails");
+    return String::from_utf8(out).unwrap();
+}
+
+fn s(text:&str) -> PrimitiveData
+{
+    return PrimitiveData::Str(text.to_string());
+}
+
+#[test]
+fn csv_quotes_only_the_cells_that_need_it()
+{
+    let rows = vec![
+        vec![s("plain"), PrimitiveData::Int(1)],
+        vec![s("a,b"), PrimitiveData::Int(2)],
+        vec![s("say \"hi\""), PrimitiveData::Int(3)],
+        vec![s("two\nlines"), PrimitiveData::Int(4)],
+        vec![s("cr\r"), PrimitiveData::Int(5)],
+        vec![s("back\\slash\ttab"), PrimitiveData::Nothing()],
+        vec![PrimitiveData::Nothing(), PrimitiveData::Int(-7)]
+    ];
+
+    let text = dump(".name:String .value:Int", rows, CsvOutputer::create(Vec::new(), true));
+
+    assert_eq!(text, "name,value\n\
+                      plain,1\n\
+                      \"a,b\",2\n\
+                      \"say \"\"hi\"\"\",3\n\
+                      \"two\nlines\",4\n\
+                      \"cr\r\",5\n\
+                      back\\slash\ttab,\n\
+                      ,-7\n");
+}
+
+#[test]
+fn csv_writes_the_header_only_when_asked()
+{
+    let text = dump(".name:String", vec![], CsvOutputer::create(Vec::new(), true));
+    assert_eq!(text, "name\n");
+
+    let text = dump(".name:String", vec![vec![s("x")]], CsvOutputer::create(Vec::new(), false));
+    assert_eq!(text, "x\n");
+}
+
+#[test]
+fn tsv_escapes_tabs_line_breaks_and_backslashes()
+{
+    let rows = vec![
+        vec![s("plain"), PrimitiveData::Float(1.5)],
+        vec![s("a\tb"), PrimitiveData::Float(2.0)],
+        vec![s("two\nlines\r"), PrimitiveData::Nothing()],
+        vec![s("C:\\dir\\t"), PrimitiveData::Float(-0.25)],
+        vec![s("comma, \"quote\""), PrimitiveData::Float(0.0)],
+        vec![PrimitiveData::Nothing(), PrimitiveData::Nothing()]
+    ];
+
+    let text = dump(".name:String .value:Float", rows, TsvOutputer::create(Vec::new(), true));
+
+    assert_eq!(text, "name\tvalue\n\
+                      plain\t1.5\n\
+                      a\\tb\t2\n\
+                      two\\nlines\\r\t\n\
+                      C:\\\\dir\\\\t\t-0.25\n\
+                      comma, \"quote\"\t0\n\
+                      \t\n");
+}
+
+#[test]
+fn squery_output_is_read_back_with_the_types_and_sort_keys()
+{
+    let spec = ".name:String .count:Int .ratio:Float .time:DateTime sorted:count,name";
+    let rows = vec![
+        vec![s("plain"), PrimitiveData::Int(-3), PrimitiveData::Float(0.1), PrimitiveData::DateTime(parse_datetime("2018-01-02 03:04:05").unwrap())],
+        vec![s("tab\there"), PrimitiveData::Int(i64::MAX), PrimitiveData::Float(-1e300), PrimitiveData::Nothing()],
+        vec![s("two\nlines\r"), PrimitiveData::Nothing(), PrimitiveData::Float(2.0), PrimitiveData::DateTime(parse_datetime("1969-12-31 23:59:59").unwrap())],
+        vec![s("back\\slash \\N"), PrimitiveData::Int(0), PrimitiveData::Nothing(), PrimitiveData::Nothing()],
+        vec![s("\\N"), PrimitiveData::Int(1), PrimitiveData::Float(1.5), PrimitiveData::Nothing()],
+        vec![PrimitiveData::Nothing(), PrimitiveData::Nothing(), PrimitiveData::Nothing(), PrimitiveData::Nothing()]
+    ];
+
+    let text = dump(spec, rows.clone(), SqueryOutputer::create(Vec::new()));
+    assert!(text.starts_with(&format!("{}\n", spec)), "unexpected spec line in {:?}", text);
+
+    let options = Options::parse(&[]).unwrap();
+    let mut input = open_input(&options, BufReader::new(Cursor::new(text.into_bytes()))).expect("the squery output is not recognized");
+    let schema = input.determine_table_schema().expect("the schema is not read back");
+
+    assert_eq!(schema.to_spec(), spec);
+    assert_eq!(schema.sort_keys, vec![1, 0]);
+    assert!(schema.sorted);
+
+    for expected in &rows
+    {
+        let row = input.parse_next_row(&schema).expect("missing row");
+        let actual:Vec<String> = (0..schema.num_columns()).map(|col| format!("{:?}", row.value_at(col))).collect();
+        let expected:Vec<String> = expected.iter().map(|value| format!("{:?}", value)).collect();
+        assert_eq!(actual, expected);
+    }
+
+    assert!(input.parse_next_row(&schema).is_none());
+}
+
+#[test]
+fn plain_text_input_is_not_read_as_squery_output()
+{
+    let options = Options::parse(&[]).unwrap();
+    let text = "USER PID\nroot 1\nbob 22\n".to_string();
+    let mut input = open_input(&options, BufReader::new(Cursor::new(text.into_bytes()))).unwrap();
+    let schema = input.determine_table_schema().unwrap();
+
+    assert_eq!(schema.to_spec(), ".user:String .pid:Int");
+}
+
+#[test]
+fn markdown_escapes_the_cells()
+{
+    let rows = vec![
+        vec![s("a|b"), PrimitiveData::Int(1)],
+        vec![s("<b>bold</b> & \"quoted\""), PrimitiveData::Nothing()],
+        vec![s("&lt; \\ two\r\nlines"), PrimitiveData::Int(-2)]
+    ];
+
+    let text = dump(".text:String .n:Int", rows, MarkdownOutputer::create(Vec::new()));
+
+    assert_eq!(text, "| text | n |\n\
+                      | :--- | ---: |\n\
+                      | a\\|b | 1 |\n\
+                      | \\<b\\>bold\\</b\\> \\& \"quoted\" |  |\n\
+                      | \\&lt; \\\\ two<br>lines | -2 |\n");
+}
+
+#[test]
+fn markdown_table_without_columns_has_an_empty_column()
+{
+    let schema = TableSchema {
+        sort_keys : Vec::new(),
+        sorted    : false,
+        types     : Vec::new()
+    };
+
+    let text = dump_schema(&schema, vec![vec![], vec![]], MarkdownOutputer::create(Vec::new()));
+
+    assert_eq!(text, "|  |\n| --- |\n|  |\n|  |\n");
+}
+
+#[test]
+fn html_escapes_the_cells_and_the_attributes()
+{
+    let rows = vec![
+        vec![s("a|b"), PrimitiveData::Float(0.5)],
+        vec![s("<script>\"x\" & 'y'</script>"), PrimitiveData::Nothing()]
+    ];
+
+    let text = dump(".text:String .value:Float", rows, HtmlOutputer::create(Vec::new()));
+
+    assert_eq!(text, "<table>\n<thead>\n\
+                      <tr><th class=\"text\" data-type=\"String\">text</th><th class=\"value\" data-type=\"Float\">value</th></tr>\n\
+                      </thead>\n<tbody>\n\
+                      <tr><td class=\"text\" data-type=\"String\">a|b</td><td class=\"value\" data-type=\"Float\">0.5</td></tr>\n\
+                      <tr><td class=\"text\" data-type=\"String\">&lt;script&gt;&quot;x&quot; &amp; &#39;y&#39;&lt;/script&gt;</td><td class=\"value\" data-type=\"Float\"></td></tr>\n\
+                      </tbody>\n</table>\n");
+}
+
+#[test]
+fn html_rows_can_be_written_without_the_schema()
+{
+    let schema = TableSchema::from_spec(".n:Int").unwrap();
+    let mut input = RowsInput { rows : vec![vec![PrimitiveData::Int(1)]].into_iter().collect() };
+    let mut table = Table::empty(&schema, TableDataSource::Parser(&mut input, false));
+    let mut output = HtmlOutputer::create(Vec::new());
+
+    output.write_records(&mut table);
+
+    let text = String::from_utf8(output.get_output_result()).unwrap();
+    assert_eq!(text, "<tr><td class=\"n\" data-type=\"Int\">1</td></tr>\n</tbody>\n</table>\n");
+}
+
+#[test]
+fn json_keeps_the_types_of_the_cells()
+{
+    let time = parse_datetime("2018-01-02T03:04:05Z").unwrap();
+    let rows = vec![
+        vec![PrimitiveData::Int(-3), PrimitiveData::Float(1.5), s("x"), PrimitiveData::DateTime(time)],
+        vec![PrimitiveData::Int(0), PrimitiveData::Float(2.0), s("7"), PrimitiveData::Nothing()]
+    ];
+
+    let text = dump(".i:Int .f:Float .s:String .t:DateTime", rows, JsonOutputer::create(Vec::new()));
+
+    assert_eq!(text, format!("[\n\
+                              {{\"i\":-3,\"f\":1.5,\"s\":\"x\",\"t\":\"{}\"}},\n\
+                              {{\"i\":0,\"f\":2.0,\"s\":\"7\",\"t\":null}}\n\
+                              ]\n", PrimitiveData::DateTime(time).to_human_readable()));
+}
+
+#[test]
+fn json_writes_null_for_the_missing_and_non_finite_numbers()
+{
+    let rows = vec![
+        vec![PrimitiveData::Float(f64::NAN), PrimitiveData::Nothing()],
+        vec![PrimitiveData::Float(f64::INFINITY), PrimitiveData::Int(1)],
+        vec![PrimitiveData::Float(f64::NEG_INFINITY), PrimitiveData::Nothing()]
+    ];
+
+    let text = dump(".f:Float .i:Int", rows, JsonOutputer::create(Vec::new()));
+
+    assert_eq!(text, "[\n{\"f\":null,\"i\":null},\n{\"f\":null,\"i\":1},\n{\"f\":null,\"i\":null}\n]\n");
+}
+
+#[test]
+fn json_escapes_the_strings()
+{
+    let rows = vec![vec![s("say \"hi\" \\ \n\r\t\u{1}\u{1f} \u{e9}\u{6f22}\u{1f600} \u{7f}")]];
+
+    let text = dump(".text:String", rows, JsonOutputer::create(Vec::new()));
+
+    /* Only the control characters are escaped, the other characters are written as they are */
+    assert_eq!(text, "[\n{\"text\":\"say \\\"hi\\\" \\\\ \\n\\r\\t\\u0001\\u001f \u{e9}\u{6f22}\u{1f600} \u{7f}\"}\n]\n");
+}
+
+#[test]
+fn empty_json_array_is_written_for_no_rows()
+{
+    assert_eq!(dump(".a:Int", vec![], JsonOutputer::create(Vec::new())), "[]\n");
+    assert_eq!(dump(".a:Int", vec![], NdjsonOutputer::create(Vec::new())), "");
+}
+
+#[test]
+fn ndjson_writes_one_object_per_line()
+{
+    let rows = vec![
+        vec![s("a\nb"), PrimitiveData::Int(1)],
+        vec![PrimitiveData::Nothing(), PrimitiveData::Int(2)]
+    ];
+
+    let text = dump(".name:String .n:Int", rows, NdjsonOutputer::create(Vec::new()));
+
+    assert_eq!(text, "{\"name\":\"a\\nb\",\"n\":1}\n{\"name\":null,\"n\":2}\n");
+}
+
+/**
+ * @brief Read the rows into the buffered text table
+ * @param spec The schema specification of the table
+ * @param rows The rows
+ * @return The text table, which is not drawn yet
+ **/
+fn text_table(spec:&str, rows:Vec<Vec<PrimitiveData>>) -> TableResult
+{
+    return styled_table(spec, rows, TableOutputer::create());
+}
+
+/**
+ * @brief Read the rows into the buffered text table with the writer we have set up
+ * @param spec The schema specification of the table
+ * @param rows The rows
+ * @param output The text table writer
+ * @return The text table, which is not drawn yet
+ **/
+fn styled_table(spec:&str, rows:Vec<Vec<PrimitiveData>>, mut output:TableOutputer) -> TableResult
+{
+    let schema = TableSchema::from_spec(spec).unwrap();
+    let mut input = RowsInput { rows : rows.into_iter().collect() };
+    let mut table = Table::empty(&schema, TableDataSource::Parser(&mut input, false));
+
+    return table.dump(&mut output).expect("the writer fails");
+}
+
+/**
+ * @brief Check that each line of the drawn table has the same width, which means the box
+ *        is not broken
+ * @param text The drawn table
+ * @return The width of the table
+ **/
+fn box_width(text:&str) -> usize
+{
+    let widths:Vec<usize> = text.lines().map(::writer::tablewriter::text_width).collect();
+    assert!(widths.iter().all(|&width| width == widths[0]), "the box is broken:\n{}", text);
+    return widths[0];
+}
+
+#[test]
+fn split_at_width_keeps_the_first_part_within_the_width()
+{
+    assert_eq!(split_at_width("abcdef", 4), ("abcd", "ef"));
+    assert_eq!(split_at_width("abc", 3), ("abc", ""));
+    assert_eq!(split_at_width("abc", 0), ("", "abc"));
+    assert_eq!(split_at_width("", 2), ("", ""));
+}
+
+#[test]
+fn wrap_text_breaks_on_spaces_when_possible()
+{
+    assert_eq!(wrap_text("hello world foo", 5), vec!["hello", "world", "foo"]);
+    assert_eq!(wrap_text("hello world foo", 11), vec!["hello world", "foo"]);
+    assert_eq!(wrap_text("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    assert_eq!(wrap_text("ab cdefgh", 4), vec!["ab", "cdef", "gh"]);
+    assert_eq!(wrap_text("short", 10), vec!["short"]);
+    assert_eq!(wrap_text("", 3), vec![""]);
+}
+
+#[test]
+fn wrap_text_keeps_the_line_breaks()
+{
+    assert_eq!(wrap_text("a\nbb cc", 10), vec!["a", "bb cc"]);
+    assert_eq!(wrap_text("abcd\r\nef", 2), vec!["ab", "cd", "ef"]);
+    assert_eq!(wrap_text("a\n\nb", 5), vec!["a", "", "b"]);
+}
+
+#[test]
+fn truncate_text_puts_an_ellipsis_at_the_end()
+{
+    assert_eq!(truncate_text("hello", 5), "hello");
+    assert_eq!(truncate_text("hello world", 5), "hell\u{2026}");
+    assert_eq!(truncate_text("hello", 1), "\u{2026}");
+    assert_eq!(truncate_text("hello", 0), "");
+    assert_eq!(truncate_text("ab\ncd", 5), "ab\u{2026}");
+    assert_eq!(truncate_text("abcde\nf", 5), "abcd\u{2026}");
+}
+
+#[test]
+fn min_width_is_spread_over_the_leading_columns()
+{
+    let table = text_table(".a:String .b:String .c:String", vec![vec![s("x"), s("y"), s("z")]]);
+
+    /* The natural width is 7, the 5 extra columns go 2, 2 and 1 */
+    let text = table.render_text_table(usize::MAX, 12);
+    assert_eq!(text.lines().next(), Some("+---+---+--+"));
+    assert_eq!(box_width(&text), 12);
+
+    let text = table.render_text_table(usize::MAX, 0);
+    assert_eq!(text.lines().next(), Some("+-+-+-+"));
+}
+
+#[test]
+fn wide_columns_are_shrinked_to_the_max_width()
+{
+    let mut table = text_table(".name:String .n:Int", vec![
+        vec![s("a rather long value"), PrimitiveData::Int(1)],
+        vec![s("short"), PrimitiveData::Int(22)]
+    ]);
+
+    let text = table.render_text_table(16, 0);
+    assert_eq!(box_width(&text), 16);
+    assert!(text.contains("|a rather   | 1|\n|long value |  |\n"), "unexpected wrapping:\n{}", text);
+
+    table.set_overflow(0, Overflow::Truncate);
+    let text = table.render_text_table(16, 0);
+    assert_eq!(box_width(&text), 16);
+    assert!(text.contains("|a rather l\u{2026}| 1|\n"), "unexpected truncation:\n{}", text);
+
+    /* The columns are never shrinked below the min column width */
+    let text = table.render_text_table(5, 0);
+    assert!(box_width(&text) > 5);
+}
+
+#[test]
+fn line_breaks_in_cells_do_not_break_the_box()
+{
+    let mut table = text_table(".text:String .n:Int", vec![
+        vec![s("one\ntwo\r\nthree"), PrimitiveData::Int(1)],
+        vec![s("x"), PrimitiveData::Int(2)]
+    ]);
+
+    let text = table.render_text_table(usize::MAX, 0);
+    assert_eq!(box_width(&text), 9);
+    assert!(text.contains("|one  |1|\n|two  | |\n|three| |\n"), "unexpected lines:\n{}", text);
+
+    table.set_overflow(0, Overflow::Truncate);
+    let text = table.render_text_table(usize::MAX, 0);
+    assert_eq!(box_width(&text), 9);
+    assert!(text.contains("|one\u{2026} |1|\n"), "unexpected truncation:\n{}", text);
+}
+
+#[test]
+fn display_width_counts_wide_and_zero_width_characters()
+{
+    use ::writer::tablewriter::text_width;
+
+    assert_eq!(text_width("abc"), 3);
+    assert_eq!(text_width("\u{6f22}\u{5b57}"), 4);
+    assert_eq!(text_width("e\u{301}"), 1);
+    assert_eq!(text_width("\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}"), 2);
+}
+
+#[test]
+fn split_at_width_does_not_break_graphemes()
+{
+    /* A wide character is never cut in half */
+    assert_eq!(split_at_width("\u{6f22}\u{5b57}", 3), ("\u{6f22}", "\u{5b57}"));
+    assert_eq!(split_at_width("\u{6f22}\u{5b57}", 1), ("", "\u{6f22}\u{5b57}"));
+
+    /* The combining mark stays with its base character */
+    assert_eq!(split_at_width("e\u{301}x", 1), ("e\u{301}", "x"));
+
+    /* The ZWJ sequence is a single grapheme */
+    let family = "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    assert_eq!(split_at_width(&format!("a{}b", family), 3), (&format!("a{}", family)[..], "b"));
+    assert_eq!(split_at_width(&format!("a{}b", family), 2), ("a", &format!("{}b", family)[..]));
+}
+
+#[test]
+fn wrap_and_truncate_use_the_display_width()
+{
+    assert_eq!(wrap_text("\u{6f22}\u{5b57}\u{6f22}", 3), vec!["\u{6f22}", "\u{5b57}", "\u{6f22}"]);
+    assert_eq!(wrap_text("\u{6f22}", 1), vec!["\u{6f22}"]);
+    assert_eq!(wrap_text("e\u{301}e\u{301}e\u{301}", 2), vec!["e\u{301}e\u{301}", "e\u{301}"]);
+    assert_eq!(truncate_text("\u{6f22}\u{5b57}\u{6f22}", 4), "\u{6f22}\u{2026}");
+    assert_eq!(truncate_text("e\u{301}e\u{301}e\u{301}", 3), "e\u{301}e\u{301}e\u{301}");
+}
+
+#[test]
+fn columns_are_sized_by_the_display_width()
+{
+    let family = "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    let table = text_table(".name:String .n:Int", vec![
+        vec![s("\u{6f22}\u{5b57}"), PrimitiveData::Int(1)],
+        vec![s("cafe\u{301}"), PrimitiveData::Int(2)],
+        vec![s(family), PrimitiveData::Int(3)]
+    ]);
+
+    let text = table.render_text_table(usize::MAX, 0);
+    assert_eq!(box_width(&text), 8);
+    assert!(text.contains("|\u{6f22}\u{5b57}|1|\n"), "unexpected layout:\n{}", text);
+    assert!(text.contains(&format!("|{}  |3|\n", family)), "unexpected layout:\n{}", text);
+
+    /* The wide characters are wrapped without breaking the box */
+    let table = text_table(".name:String", vec![vec![s("\u{6f22}\u{5b57}\u{6f22}\u{5b57}\u{6f22}")]]);
+    let text = table.render_text_table(7, 0);
+    assert_eq!(box_width(&text), 7);
+    assert!(text.contains("|\u{6f22}\u{5b57} |\n|\u{6f22}\u{5b57} |\n|\u{6f22}   |\n"), "unexpected wrapping:\n{}", text);
+}
+
+#[test]
+fn rows_meeting_the_highlight_condition_are_painted()
+{
+    let schema = TableSchema::from_spec(".pid:Int .stat:String").unwrap();
+    let mut input = RowsInput { rows : vec![
+        vec![PrimitiveData::Int(1), s("Ss")],
+        vec![PrimitiveData::Int(2), s("Z")],
+        vec![PrimitiveData::Int(3), PrimitiveData::Nothing()]
+    ].into_iter().collect() };
+
+    let mut output = TableOutputer::create();
+    output.color_mode(ColorMode::Always);
+    output.add_color_hint(ColorHint {
+        condition : Condition::parse("stat ~ ^Z").unwrap(),
+        color     : Color::Red
+    });
+
+    let table = Table::empty(&schema, TableDataSource::Parser(&mut input, false)).dump(&mut output).expect("the writer fails");
+    let text = table.render_text_table(usize::MAX, 0);
+    let body:Vec<&str> = text.lines().filter(|line| line.starts_with('|')).skip(1).collect();
+
+    assert_eq!(body.len(), 3);
+    assert!(body[1].contains("\x1b[31m"), "the zombie is not painted:\n{}", text);
+    assert!(!body[0].contains("\x1b[31m"), "the running process is painted:\n{}", text);
+    assert!(!body[2].contains("\x1b[31m"), "the row without the value is painted:\n{}", text);
+}
+
+#[test]
+fn column_widths_come_from_the_preprocess_pass()
+{
+    let schema = TableSchema::from_spec(".name:String").unwrap();
+    let mut rows:Vec<Vec<PrimitiveData>> = (0..5000).map(|_| vec![s("ab")]).collect();
+    rows.push(vec![s("abcdef")]);
+
+    /* All the loaded rows are measured */
+    let mut input = RowsInput { rows : rows.iter().cloned().collect() };
+    let mut table = Table::empty(&schema, TableDataSource::Parser(&mut input, true));
+    table.get_random_accessor();
+    assert!(table.is_loaded());
+    let text = table.dump(&mut TableOutputer::create()).expect("the writer fails").render_text_table(usize::MAX, 0);
+    assert_eq!(box_width(&text), 8);
+    assert!(text.contains("|abcdef|\n"), "unexpected layout:\n{}", text);
+
+    /* The rows read from the input are all measured as well, however late the wide row is */
+    let text = text_table(".name:String", rows).render_text_table(usize::MAX, 0);
+    assert_eq!(box_width(&text), 8);
+    assert!(text.contains("|abcdef|\n"), "unexpected layout:\n{}", text);
+    assert!(text.contains("|ab    |\n"), "unexpected layout:\n{}", text);
+}
+
+/**
+ * @brief Draw the small table of .a:String .n:Int in the style
+ * @param border The border
+ * @param row_separator If we draw a line between two rows
+ * @return The drawn table
+ **/
+fn draw_in_style(border:Border, row_separator:bool) -> String
+{
+    let mut output = TableOutputer::create();
+    output.style(TableStyle { border : border, row_separator : row_separator });
+
+    let rows = vec![vec![s("x"), PrimitiveData::Int(1)], vec![s("yy"), PrimitiveData::Int(22)]];
+    return styled_table(".a:String .n:Int", rows, output).render_text_table(usize::MAX, 0);
+}
+
+#[test]
+fn borders_are_drawn_with_the_style_glyphs()
+{
+    assert_eq!(draw_in_style(Border::Light, true), "\u{250c}\u{2500}\u{2500}\u{252c}\u{2500}\u{2500}\u{2510}\n\
+                                                   \u{2502}a \u{2502} n\u{2502}\n\
+                                                   \u{251c}\u{2500}\u{2500}\u{253c}\u{2500}\u{2500}\u{2524}\n\
+                                                   \u{2502}x \u{2502} 1\u{2502}\n\
+                                                   \u{251c}\u{2500}\u{2500}\u{253c}\u{2500}\u{2500}\u{2524}\n\
+                                                   \u{2502}yy\u{2502}22\u{2502}\n\
+                                                   \u{2514}\u{2500}\u{2500}\u{2534}\u{2500}\u{2500}\u{2518}\n");
+
+    assert_eq!(draw_in_style(Border::Heavy, true), "\u{250f}\u{2501}\u{2501}\u{2533}\u{2501}\u{2501}\u{2513}\n\
+                                                   \u{2503}a \u{2503} n\u{2503}\n\
+                                                   \u{2523}\u{2501}\u{2501}\u{254b}\u{2501}\u{2501}\u{252b}\n\
+                                                   \u{2503}x \u{2503} 1\u{2503}\n\
+                                                   \u{2523}\u{2501}\u{2501}\u{254b}\u{2501}\u{2501}\u{252b}\n\
+                                                   \u{2503}yy\u{2503}22\u{2503}\n\
+                                                   \u{2517}\u{2501}\u{2501}\u{253b}\u{2501}\u{2501}\u{251b}\n");
+
+    /* The borderless table has no lines, the columns are seperated by a space */
+    assert_eq!(draw_in_style(Border::Borderless, true), "a   n\nx   1\nyy 22\n");
+}
+
+#[test]
+fn row_separator_can_be_turned_off()
+{
+    assert_eq!(draw_in_style(Border::Ascii, false), "+--+--+\n|a | n|\n+--+--+\n|x | 1|\n|yy|22|\n+--+--+\n");
+    assert_eq!(draw_in_style(Border::Ascii, true), "+--+--+\n|a | n|\n+--+--+\n|x | 1|\n+--+--+\n|yy|22|\n+--+--+\n");
+}
+
+#[test]
+fn numbers_are_grouped_and_right_aligned()
+{
+    let mut output = TableOutputer::create();
+    output.style(TableStyle { border : Border::Borderless, row_separator : false })
+          .number_format(NumberFormat { thousands_separator : true, float_precision : Some(2) });
+
+    let rows = vec![
+        vec![PrimitiveData::Int(1234567), PrimitiveData::Float(1234.5), s("1234")],
+        vec![PrimitiveData::Int(-1000), PrimitiveData::Float(-0.5), s("x")],
+        vec![PrimitiveData::Int(12), PrimitiveData::Float(3.0), s("ab")]
+    ];
+    let text = styled_table(".i:Int .f:Float .s:String", rows, output).render_text_table(usize::MAX, 0);
+
+    /* The strings are left aligned and never grouped */
+    assert_eq!(text, "        i        f s   \n\
+                      1,234,567 1,234.50 1234\n\
+                      \x20  -1,000    -0.50 x   \n\
+                      \x20      12     3.00 ab  \n");
+}
+
+#[test]
+fn missing_values_are_dimmed_in_the_coloured_table()
+{
+    let rows = vec![vec![PrimitiveData::Nothing(), s("")]];
+
+    /* The placeholder tells the missing value from the empty string */
+    let mut output = TableOutputer::create();
+    output.color_mode(ColorMode::Always);
+    let text = styled_table(".a:String .b:String", rows.clone(), output).render_text_table(usize::MAX, 0);
+    assert!(text.contains("|\x1b[2m-\x1b[0m| |\n"), "the missing value is not dimmed:\n{}", text);
+
+    let mut output = TableOutputer::create();
+    output.color_mode(ColorMode::Always).null_text("n/a");
+    let text = styled_table(".a:String .b:String", rows.clone(), output).render_text_table(usize::MAX, 0);
+    assert!(text.contains("|\x1b[2mn/a\x1b[0m| |\n"), "the null text is not dimmed:\n{}", text);
+
+    /* Without the colours the missing value is blank as before */
+    let text = text_table(".a:String .b:String", rows).render_text_table(usize::MAX, 0);
+    assert!(text.contains("| | |\n"), "unexpected missing value:\n{}", text);
+}
+
+/**
+ * @brief The byte buffer shared by the writer and the test, so that we can see what has been
+ *        written while the table is still being read
+ **/
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn text(&self) -> String
+    {
+        return String::from_utf8(self.0.borrow().clone()).unwrap();
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf:&[u8]) -> io::Result<usize>
+    {
+        self.0.borrow_mut().extend_from_slice(buf);
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        return Ok(());
+    }
+}
+
+/**
+ * @brief The stream that counts how many times it's flushed
+ **/
+#[derive(Default)]
+struct FlushCounter {
+    flushes : usize
+}
+
+impl Write for FlushCounter {
+    fn write(&mut self, buf:&[u8]) -> io::Result<usize>
+    {
+        return Ok(buf.len());
+    }
+
+    fn flush(&mut self) -> io::Result<()>
+    {
+        self.flushes += 1;
+        return Ok(());
+    }
+}
+
+/**
+ * @brief Dump three rows with the writer and count the flushes
+ * @param output The writer, which writes to a flush counter
+ * @return The number of flushes
+ **/
+fn count_flushes<O>(output:&mut O) -> usize
+    where O : Output<IOResult = FlushCounter>
+{
+    let schema = TableSchema::from_spec(".a:Int").unwrap();
+    let mut input = RowsInput { rows : (0..3).map(|n| vec![PrimitiveData::Int(n)]).collect() };
+    let mut table = Table::empty(&schema, TableDataSource::Parser(&mut input, false));
+
+    return table.dump(output).expect("the writer fails").flushes;
+}
+
+#[test]
+fn rows_are_flushed_only_in_follow_mode()
+{
+    let counter = FlushCounter::default;
+
+    /* Otherwise the stream is flushed once at the end */
+    assert_eq!(count_flushes(&mut CsvOutputer::create(counter(), true)), 1);
+    assert_eq!(count_flushes(&mut TsvOutputer::create(counter(), true)), 1);
+    assert_eq!(count_flushes(&mut JsonOutputer::create(counter())), 1);
+    assert_eq!(count_flushes(&mut NdjsonOutputer::create(counter())), 1);
+    assert_eq!(count_flushes(&mut MarkdownOutputer::create(counter())), 1);
+    assert_eq!(count_flushes(&mut HtmlOutputer::create(counter())), 1);
+    assert_eq!(count_flushes(&mut SqueryOutputer::create(counter())), 1);
+
+    /* The header line of the separated values is flushed as well */
+    assert_eq!(count_flushes(CsvOutputer::create(counter(), true).follow(true)), 5);
+    assert_eq!(count_flushes(TsvOutputer::create(counter(), true).follow(true)), 5);
+    assert_eq!(count_flushes(JsonOutputer::create(counter()).follow(true)), 4);
+    assert_eq!(count_flushes(NdjsonOutputer::create(counter()).follow(true)), 4);
+    assert_eq!(count_flushes(MarkdownOutputer::create(counter()).follow(true)), 4);
+    assert_eq!(count_flushes(HtmlOutputer::create(counter()).follow(true)), 4);
+    assert_eq!(count_flushes(SqueryOutputer::create(counter()).follow(true)), 4);
+}
+
+/**
+ * @brief The input that keeps the text written so far each time a row is pulled
+ **/
+struct WatchedInput {
+    rows      : RowsInput,
+    out       : SharedBuffer,
+    snapshots : Vec<String>
+}
+
+impl Input for WatchedInput {
+    fn determine_table_schema(&mut self) -> Option<TableSchema>
+    {
+        return None;
+    }
+
+    fn parse_next_row<'schema>(&mut self, schema:&'schema TableSchema) -> Option<Row<'schema>>
+    {
+        self.snapshots.push(self.out.text());
+        return self.rows.parse_next_row(schema);
+    }
+}
+
+/**
+ * @brief Stream the rows of .name:String with the first rows sampled
+ * @param names The names
+ * @param sample_rows How many rows are sampled
+ * @param overflow How the wider cells are handled
+ * @return The table text and what has been written when each row is pulled
+ **/
+fn stream_names(names:&[&str], sample_rows:usize, overflow:Overflow) -> (String, Vec<String>)
+{
+    let schema = TableSchema::from_spec(".name:String").unwrap();
+    let out = SharedBuffer::default();
+    let mut input = WatchedInput {
+        rows      : RowsInput { rows : names.iter().map(|name| vec![s(name)]).collect() },
+        out       : out.clone(),
+        snapshots : Vec::new()
+    };
+
+    let mut output = StreamTableOutputer::create(out.clone(), sample_rows);
+    output.table_width(usize::MAX, 0).overflow(0, overflow);
+    Table::empty(&schema, TableDataSource::Parser(&mut input, false)).dump(&mut output).expect("the writer fails");
+
+    return (out.text(), input.snapshots);
+}
+
+#[test]
+fn stream_columns_are_sized_by_the_sampled_rows()
+{
+    /* Only the first two rows are measured, the wider row after them is wrapped */
+    let (text, _) = stream_names(&["ab", "abc", "abcdefgh"], 2, Overflow::Wrap);
+    assert_eq!(text, "+----+\n|name|\n+----+\n|ab  |\n+----+\n|abc |\n+----+\n|abcd|\n|efgh|\n+----+\n");
+
+    let (text, _) = stream_names(&["ab", "abc", "abcdefgh"], 2, Overflow::Truncate);
+    assert_eq!(text, "+----+\n|name|\n+----+\n|ab  |\n+----+\n|abc |\n+----+\n|abc\u{2026}|\n+----+\n");
+
+    /* The wide row in the sample widens the column */
+    let (text, _) = stream_names(&["ab", "abcdefgh", "abc"], 2, Overflow::Wrap);
+    assert_eq!(box_width(&text), 10);
+    assert!(text.contains("|abcdefgh|\n"), "unexpected layout:\n{}", text);
+}
+
+#[test]
+fn stream_header_is_written_before_the_input_ends()
+{
+    let (text, snapshots) = stream_names(&["a", "b", "c", "d"], 2, Overflow::Wrap);
+
+    /* The sampled rows are read before anything is written */
+    assert_eq!(snapshots.len(), 5);
+    assert_eq!(snapshots[0], "");
+    assert_eq!(snapshots[1], "");
+
+    /* The header and the sampled rows are written before the next row is read */
+    assert_eq!(snapshots[2], "+----+\n|name|\n+----+\n|a   |\n+----+\n|b   |\n");
+    assert_eq!(snapshots[3], format!("{}+----+\n|c   |\n", snapshots[2]));
+    assert!(text.starts_with(&snapshots[4]));
+}
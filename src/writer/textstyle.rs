@@ -0,0 +1,289 @@
+/*
+ * Copyright (C) 2018, Hao Hou
+ *
+ * The styling options for the text table
+ */
+use ::table::primitive::PrimitiveData;
+use ::table::filter::Condition;
+
+/**
+ * @brief The border we draw around the cells
+ **/
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Border {
+    /// The plain ASCII +-| box
+    Ascii,
+    /// The Unicode light box drawing characters
+    Light,
+    /// The Unicode heavy box drawing characters
+    Heavy,
+    /// No border at all, the columns are seperated by a space
+    Borderless
+}
+
+/**
+ * @brief The style of the text table
+ **/
+#[derive(Clone, Copy)]
+pub struct TableStyle {
+    /// The border we are using
+    pub border : Border,
+    /// If we draw a line between two rows, if this is false we only draw the line under the header
+    pub row_separator : bool
+}
+
+impl Default for TableStyle {
+    fn default() -> TableStyle
+    {
+        return TableStyle {
+            border        : Border::Ascii,
+            row_separator : true
+        };
+    }
+}
+
+/**
+ * @brief Which horizontal line we are drawing
+ **/
+#[derive(Clone, Copy)]
+pub enum HLine {
+    /// The line above the header
+    Top,
+    /// The line between the rows
+    Middle,
+    /// The line below the last row
+    Bottom
+}
+
+/**
+ * @brief The characters we used to draw the border
+ **/
+pub struct BoxChars {
+    /// The horizontal line
+    pub horizontal : &'static str,
+    /// The vertical line on the left and right edge
+    pub edge : &'static str,
+    /// The vertical line between two columns
+    pub inner : &'static str,
+    /// The left, middle and right junctions of the top, middle and bottom line
+    junctions : [[&'static str; 3]; 3]
+}
+
+impl BoxChars {
+    /**
+     * @brief Get the junctions of the horizontal line
+     * @param line Which line
+     * @return The left, middle and right junctions
+     **/
+    pub fn junctions(&self, line:HLine) -> &[&'static str; 3]
+    {
+        return &self.junctions[line as usize];
+    }
+}
+
+impl Border {
+    /**
+     * @brief Get the characters used by this border
+     * @return The box characters
+     **/
+    pub fn box_chars(&self) -> BoxChars
+    {
+        match self
+        {
+            &Border::Ascii      => BoxChars {
+                horizontal : "-",
+                edge       : "|",
+                inner      : "|",
+                junctions  : [["+", "+", "+"], ["+", "+", "+"], ["+", "+", "+"]]
+            },
+            &Border::Light      => BoxChars {
+                horizontal : "\u{2500}",
+                edge       : "\u{2502}",
+                inner      : "\u{2502}",
+                junctions  : [["\u{250c}", "\u{252c}", "\u{2510}"],
+                              ["\u{251c}", "\u{253c}", "\u{2524}"],
+                              ["\u{2514}", "\u{2534}", "\u{2518}"]]
+            },
+            &Border::Heavy      => BoxChars {
+                horizontal : "\u{2501}",
+                edge       : "\u{2503}",
+                inner      : "\u{2503}",
+                junctions  : [["\u{250f}", "\u{2533}", "\u{2513}"],
+                              ["\u{2523}", "\u{254b}", "\u{252b}"],
+                              ["\u{2517}", "\u{253b}", "\u{251b}"]]
+            },
+            &Border::Borderless => BoxChars {
+                horizontal : "",
+                edge       : "",
+                inner      : " ",
+                junctions  : [["", "", ""], ["", "", ""], ["", "", ""]]
+            }
+        }
+    }
+}
+
+/**
+ * @brief How the text is aligned in the cell
+ **/
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    Center
+}
+
+/**
+ * @brief The colours we can use in the table
+ **/
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan
+}
+
+impl Color {
+    /**
+     * @brief Parse the colour name
+     * @param name The colour name
+     * @return The colour or None if the name is unknown
+     **/
+    pub fn from_name(name:&str) -> Option<Color>
+    {
+        match name
+        {
+            "red"     => Some(Color::Red),
+            "green"   => Some(Color::Green),
+            "yellow"  => Some(Color::Yellow),
+            "blue"    => Some(Color::Blue),
+            "magenta" => Some(Color::Magenta),
+            "cyan"    => Some(Color::Cyan),
+            _         => None
+        }
+    }
+
+    /**
+     * @brief Get the ANSI SGR code for the foreground colour
+     * @return The code
+     **/
+    pub fn ansi_code(&self) -> &'static str
+    {
+        match self
+        {
+            &Color::Red     => "31",
+            &Color::Green   => "32",
+            &Color::Yellow  => "33",
+            &Color::Blue    => "34",
+            &Color::Magenta => "35",
+            &Color::Cyan    => "36"
+        }
+    }
+}
+
+/**
+ * @brief When we should use the ANSI colours
+ **/
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    /// Only when the standard output is a terminal
+    Auto,
+    Always,
+    Never
+}
+
+/**
+ * @brief Paint the row in the colour when the row meets the condition,
+ *        for example paint the row red when state == Z
+ **/
+#[derive(Clone)]
+pub struct ColorHint {
+    /// The condition on a column of the row
+    pub condition : Condition,
+    /// The colour we are using
+    pub color : Color
+}
+
+/**
+ * @brief Wrap the text with the ANSI SGR sequence
+ * @param text The text to paint
+ * @param codes The SGR codes
+ * @return The painted text
+ **/
+pub fn paint(text:&str, codes:&[&str]) -> String
+{
+    if codes.len() == 0 || text.len() == 0
+    {
+        return text.to_string();
+    }
+    return format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text);
+}
+
+/**
+ * @brief How the numbers are formatted
+ **/
+#[derive(Clone, Copy, Default)]
+pub struct NumberFormat {
+    /// If we put a comma between each group of three digits
+    pub thousands_separator : bool,
+    /// The number of digits after the decimal point for the floats, None for as many as needed
+    pub float_precision : Option<usize>
+}
+
+impl NumberFormat {
+    /**
+     * @brief Insert the thousands separators to the integer part of a number
+     * @param number The formatted number
+     * @return The number with separators
+     **/
+    fn group_digits(number:&str) -> String
+    {
+        let (sign, rest) = if number.starts_with('-') { number.split_at(1) } else { ("", number) };
+        let int_len = rest.find(|c:char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let (int_part, frac_part) = rest.split_at(int_len);
+
+        let mut ret = String::from(sign);
+        for (idx, ch) in int_part.chars().enumerate()
+        {
+            if idx > 0 && (int_len - idx) % 3 == 0
+            {
+                ret.push(',');
+            }
+            ret.push(ch);
+        }
+        ret.push_str(frac_part);
+        return ret;
+    }
+
+    /**
+     * @brief Format the cell data
+     * @param data The data to format
+     * @return The formatted text, None if the value is missing
+     **/
+    pub fn format(&self, data:&PrimitiveData) -> Option<String>
+    {
+        let text = match data
+        {
+            &PrimitiveData::Nothing()  => return None,
+            &PrimitiveData::Int(_)     => data.to_human_readable(),
+            &PrimitiveData::Float(val) => match self.float_precision {
+                Some(precision) if val.is_finite() => format!("{:.*}", precision, val),
+                _                                  => data.to_human_readable()
+            },
+            &PrimitiveData::Str(_) | &PrimitiveData::DateTime(_) => return Some(data.to_human_readable())
+        };
+
+        if self.thousands_separator
+        {
+            return Some(NumberFormat::group_digits(&text));
+        }
+
+        return Some(text);
+    }
+}